@@ -2,6 +2,7 @@ pub mod bookmark;
 pub mod config;
 pub mod context;
 pub mod creds;
+pub mod queue;
 
 pub mod action;
 pub mod cli;
@@ -9,7 +10,9 @@ mod interaction;
 
 use std::fmt;
 
-pub use action::{Action, AddTask, LogOut, RunType, ShowStatus};
+pub use action::{
+    Action, AddTask, LogOut, Outcome, RunType, ShowLists, ShowStatus, ShowTasks, SyncQueue,
+};
 pub use cli::{Cli, Command};
 pub use config::Config;
 
@@ -1,14 +1,25 @@
 mod add;
 mod add_bookmark;
+mod bookmarks;
+mod done;
 mod logout;
+mod show_lists;
+mod show_tasks;
 pub mod show_status;
+mod sync;
 
 pub use self::add::AddTask;
 pub use self::add_bookmark::AddBookmark;
+pub use self::bookmarks::ManageBookmarks;
+pub use self::done::CloseTask;
 pub use self::logout::LogOut;
+pub use self::show_lists::ShowLists;
+pub use self::show_tasks::ShowTasks;
 pub use self::show_status::ShowStatus;
+pub use self::sync::SyncQueue;
 
 use anyhow::Result;
+use serde_json::Value;
 
 use crate::app::context;
 
@@ -18,6 +29,28 @@ pub trait Action {
 
 #[derive(Debug, PartialEq)]
 pub enum RunType {
-    Completed(String),
+    Completed(Outcome),
     Cancelled,
 }
+
+/// What a completed action did: a message for people, and data
+/// describing the result for `--output json`
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub message: String,
+    pub data: Value,
+}
+
+impl Outcome {
+    pub fn new(message: impl Into<String>) -> Self {
+        Outcome {
+            message: message.into(),
+            data: Value::Null,
+        }
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = data;
+        self
+    }
+}
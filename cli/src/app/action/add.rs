@@ -1,20 +1,33 @@
+use std::fmt;
+use std::fs;
 use std::io::{self, Read};
+use std::path::PathBuf;
+use std::str::FromStr;
 
 use anyhow::{anyhow, Context as ErrContext, Result as AnyhowResult};
 use bpaf::{command, construct, long, parsers::ParseCommand, positional, Parser};
-use dialoguer::Confirm;
+use dialoguer::{Confirm, Editor};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use super::{Action, RunType};
+use super::{Action, Outcome, RunType};
 use crate::app::{
     self,
-    cli,
+    cli::{self, OutputFormat},
+    config::ExtraLines,
     context::{self, ConfigAbsentError},
     interaction,
+    queue::{Handled, Queue},
 };
 use crate::clipboard;
 use crate::colour_output::{ColourOutput, StreamKind, Style};
 use crate::progress_indicator::ProgressIndicator;
-use cvapi::{CheckvistClient, Task };
+use cvapi::due_date::DueDateResolver;
+use cvapi::idempotent::IdempotencyKey;
+use cvapi::import;
+use cvapi::outline::Outline;
+use cvapi::smart_syntax::{self, SmartContent};
+use cvapi::{CheckvistClient, CheckvistError, CheckvistLocation, Task};
 
 type Result<T> = std::result::Result<T, AddTaskError>;
 
@@ -22,6 +35,8 @@ type Result<T> = std::result::Result<T, AddTaskError>;
 enum ContentSource {
     Stdin,
     Clipboard,
+    Editor,
+    File(PathBuf),
     Arg(String),
 }
 
@@ -32,10 +47,41 @@ enum DestinationSource {
     Bookmark(String),
 }
 
+/// What `--print` prints for each task added
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrintField {
+    Id,
+    Url,
+}
+
+impl FromStr for PrintField {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "id" => Ok(Self::Id),
+            "url" => Ok(Self::Url),
+            other => Err(format!("Can't print '{}'. Use id or url", other)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AddTask {
     content_source: ContentSource,
     destination_source: DestinationSource,
+    /// indented content is added as a tree of tasks
+    outline: bool,
+    /// each line (or separated item) is added as its own task
+    each_line: bool,
+    separator: Option<String>,
+    /// show what would be added, without adding anything
+    dry_run: bool,
+    /// printed for each task added, even with `-q`
+    print: Option<PrintField>,
+    /// smart syntax (eg. `^tomorrow #tag`) becomes the task's due date and tags,
+    /// rather than being sent as written
+    smart: bool,
 }
 
 impl Action for AddTask {
@@ -54,6 +100,12 @@ impl AddTask {
         AddTask {
             content_source: ContentSource::Arg(task_content),
             destination_source: DestinationSource::Config,
+            outline: false,
+            each_line: false,
+            separator: None,
+            dry_run: false,
+            print: None,
+            smart: false,
         }
     }
 
@@ -66,9 +118,45 @@ impl AddTask {
             .short('s')
             .help("Add a task from stdin (ie. piped in) instead of the command line")
             .req_flag(ContentSource::Stdin);
+        let from_editor = long("editor")
+            .short('e')
+            .help("Write the task in your editor ($VISUAL or $EDITOR). Lines after the first become a note, or child tasks")
+            .req_flag(ContentSource::Editor);
+        let file_arg = long("file")
+            .short('f')
+            .help("Add a task from a file instead of the command line")
+            .argument::<PathBuf>("FILE");
+        let from_file = construct!(ContentSource::File(file_arg));
         let content_arg = positional("TASK_CONTENT");
         let from_content_arg = construct!(ContentSource::Arg(content_arg));
-        let content_source = construct!([from_clipboard, from_stdin, from_content_arg]);
+        let content_source = construct!([
+            from_clipboard,
+            from_stdin,
+            from_editor,
+            from_file,
+            from_content_arg
+        ]);
+        let outline = long("outline")
+            .short('o')
+            .help("Add indented lines (optionally bulleted with '-' or '*') as a tree of tasks")
+            .switch();
+        let each_line = long("each-line")
+            .help("Add each non-empty line as a separate task")
+            .switch();
+        let separator = long("separator")
+            .help("With --each-line, split tasks on SEPARATOR rather than new lines")
+            .argument::<String>("SEPARATOR")
+            .optional();
+        let dry_run = long("dry-run")
+            .help("With --each-line, show the tasks that would be added, without adding them")
+            .switch();
+        let print = long("print")
+            .help("Print only the id or url of each task added (instead of the summary), even with -q")
+            .argument::<PrintField>("FIELD")
+            .optional();
+        let smart = long("smart")
+            .help("Send smart syntax (^due, #tag) as the task's due date and tags, rather than as written")
+            .switch();
 
         let to_user_prompted = long("choose_list")
             .short('l')
@@ -87,21 +175,37 @@ impl AddTask {
             // destination_source must precede content_source
             // as latter has a positional
             destination_source,
+            outline,
+            each_line,
+            separator,
+            dry_run,
+            print,
+            smart,
             content_source,
-        });
+        })
+        .guard(
+            |add| !(add.outline && add.each_line),
+            "`--outline` and `--each-line` cannot be used together",
+        )
+        .guard(
+            |add| add.each_line || (add.separator.is_none() && !add.dry_run),
+            "`--separator` and `--dry-run` need `--each-line`",
+        )
+        .guard(
+            |add| add.separator.as_deref() != Some(""),
+            "`--separator` can't be empty",
+        );
 
         let add_task_cli_command = construct!(cli::Command::Add(add_task))
             .to_options()
-            .descr("Capture a task from commandline, clipboard, or stdin");
+            .descr("Capture a task from commandline, clipboard, stdin, a file, or your editor");
 
         command("add", add_task_cli_command)
     }
 
     pub fn prompts_user(&self) -> bool {
-        match self.destination_source {
-            DestinationSource::PromptUser => true,
-            _ => false,
-        }
+        matches!(self.destination_source, DestinationSource::PromptUser)
+            || matches!(self.content_source, ContentSource::Editor)
     }
 
     fn create_job(self, context: &context::Context) -> Result<AddTaskJob> {
@@ -133,28 +237,113 @@ impl AddTask {
                 }
             };
 
-        let content = match self.content_source {
-            ContentSource::Arg(content) => Ok(content),
-            ContentSource::Stdin => self.get_content_from_stdin(),
-            ContentSource::Clipboard => self.get_content_from_clipboard(context.allow_interaction),
+        if (self.outline || self.each_line) && matches!(self.content_source, ContentSource::Editor)
+        {
+            return Err(AddTaskError::Unhandled(
+                app::Error::Reportable("`-o` and `--each-line` can't be used with `-e`".into())
+                    .into(),
+            ));
+        }
+        let extra_lines_as = config.editor_extra_lines.unwrap_or_default();
+        let (content, extra_lines) = match self.content_source {
+            ContentSource::Arg(content) => Ok((content, Vec::new())),
+            ContentSource::Stdin => self.get_content_from_stdin().map(|c| (c, Vec::new())),
+            ContentSource::Clipboard => self
+                .get_content_from_clipboard(context.allow_interaction)
+                .map(|c| (c, Vec::new())),
+            ContentSource::Editor => self.get_content_from_editor(&location_name, extra_lines_as),
+            ContentSource::File(ref path) => {
+                self.get_content_from_file(path).map(|c| (c, Vec::new()))
+            }
         }?;
 
-        let task = Task {
-            id: None,
-            parent_id,
-            content,
-            position: 1,
+        let capture = if self.outline {
+            let outline = import::parse_indented(&content, self.smart, &DueDateResolver::local());
+            if outline.is_empty() {
+                return Err(AddTaskError::Unhandled(
+                    app::Error::Reportable("There are no tasks to add".into()).into(),
+                ));
+            }
+            Capture::Outline(outline)
+        } else if self.each_line {
+            let separator = self.separator.as_deref().unwrap_or("\n");
+            let tasks = Self::tasks_from_lines(&content, separator, parent_id, self.smart);
+            if tasks.is_empty() {
+                return Err(AddTaskError::Unhandled(
+                    app::Error::Reportable("There are no tasks to add".into()).into(),
+                ));
+            }
+            Capture::Lines(tasks)
+        } else {
+            Self::task_capture(&content, parent_id, extra_lines, extra_lines_as, self.smart)
         };
 
         Ok(AddTaskJob {
             client,
-            task,
+            capture,
             list_id,
+            parent_id,
             location_name,
             possible_new_default_list,
+            dry_run: self.dry_run,
+            print: self.print,
+            first_may_exist: false,
         })
     }
 
+    /// A task for each non-empty item in `content`, in order
+    fn tasks_from_lines(
+        content: &str,
+        separator: &str,
+        parent_id: Option<u32>,
+        smart: bool,
+    ) -> Vec<Task> {
+        let resolver = DueDateResolver::local();
+        content
+            .split(separator)
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(i, line)| parse_content(line, smart).to_task(i as u16 + 1, parent_id, &resolver))
+            .collect()
+    }
+
+    /// A single task from `content`, with `extra_lines` (from the editor) as its
+    /// note or children
+    fn task_capture(
+        content: &str,
+        parent_id: Option<u32>,
+        extra_lines: Vec<String>,
+        extra_lines_as: ExtraLines,
+        smart: bool,
+    ) -> Capture {
+        let smart_content = parse_content(content, smart);
+        let resolver = DueDateResolver::local();
+        let task = smart_content.to_task(1, parent_id, &resolver);
+        let (note, children) = match extra_lines_as {
+            _ if extra_lines.is_empty() => (None, Vec::new()),
+            ExtraLines::Note => (Some(extra_lines.join("\n").trim().to_string()), Vec::new()),
+            ExtraLines::Children => {
+                let children = extra_lines
+                    .iter()
+                    .filter(|line| !line.trim().is_empty())
+                    .enumerate()
+                    .map(|(i, line)| {
+                        parse_content(line.trim(), smart).to_task(i as u16 + 1, None, &resolver)
+                    })
+                    .collect();
+                (None, children)
+            }
+        };
+
+        Capture::Task {
+            task: Box::new(task),
+            smart_content,
+            note,
+            children,
+        }
+    }
+
     fn get_content_from_stdin(&self) -> Result<String> {
         if !is_content_piped() {
             return Err(AddTaskError::Unhandled(anyhow!(
@@ -167,6 +356,26 @@ impl AddTask {
         Ok(buffer)
     }
 
+    fn get_content_from_file(&self, path: &PathBuf) -> Result<String> {
+        let content = fs::read_to_string(path).with_context(|| {
+            app::Error::Reportable(format!("Couldn't read the file {}", path.display()))
+        })?;
+        Ok(content)
+    }
+
+    /// The first line of what's written in the editor, and the lines after it
+    fn get_content_from_editor(
+        &self,
+        location_name: &str,
+        extra_lines_as: ExtraLines,
+    ) -> Result<(String, Vec<String>)> {
+        let template = editor_template(location_name, extra_lines_as);
+        let Some(text) = Editor::new().edit(&template)? else {
+            return Err(AddTaskError::UserCancellation);
+        };
+        split_editor_text(&text).ok_or(AddTaskError::UserCancellation)
+    }
+
     fn get_content_from_clipboard(&self, allow_interaction: bool) -> Result<String> {
         let Some(cliptext) = clipboard::get_clipboard_as_string() else {
             return Err(AddTaskError::Unhandled(anyhow!("Couldn't get clipboard contents")));
@@ -187,56 +396,675 @@ impl AddTask {
 // I've decided here on Command vs Job which suits the domain
 struct AddTaskJob {
     client: CheckvistClient,
-    task: Task,
+    capture: Capture,
     list_id: u32,
+    parent_id: Option<u32>,
     /// bookmark or list name
     location_name: String,
     possible_new_default_list: bool,
+    dry_run: bool,
+    print: Option<PrintField>,
+    /// the first task was queued after an attempt that may have reached Checkvist,
+    /// so is only added if it isn't already there
+    first_may_exist: bool,
+}
+
+/// A task as added to Checkvist
+#[derive(Debug)]
+pub(super) struct AddedTask {
+    id: u32,
+    content: String,
+}
+
+impl TryFrom<Task> for AddedTask {
+    type Error = anyhow::Error;
+
+    fn try_from(task: Task) -> AnyhowResult<Self> {
+        let id = task
+            .id
+            .context("Checkvist didn't return the new task's id")?;
+        Ok(AddedTask {
+            id,
+            content: task.content,
+        })
+    }
+}
+
+/// What's being added
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Capture {
+    /// A single task, with any note or children written after it in the editor
+    Task {
+        task: Box<Task>,
+        /// as parsed from the task content, for display
+        #[serde(skip)]
+        smart_content: SmartContent,
+        /// added to the task once it's created
+        note: Option<String>,
+        /// added beneath the task once it's created
+        children: Vec<Task>,
+    },
+    /// A tree of tasks, from indented content
+    Outline(Outline),
+    /// Sibling tasks, one from each line (or separated item)
+    Lines(Vec<Task>),
+}
+
+impl Capture {
+    /// The number of tasks to add (not counting a single task's children)
+    fn len(&self) -> usize {
+        match self {
+            Capture::Task { .. } => 1,
+            Capture::Outline(outline) => outline.len(),
+            Capture::Lines(tasks) => tasks.len(),
+        }
+    }
+
+    /// Every task's content (indented beneath its parent), so none are lost without trace
+    fn contents(&self) -> Vec<String> {
+        match self {
+            Capture::Task { task, children, .. } => std::iter::once(task.content.clone())
+                .chain(children.iter().map(|child| format!("  {}", child.content)))
+                .collect(),
+            Capture::Outline(outline) => outline
+                .walk()
+                .into_iter()
+                .map(|(depth, item)| format!("{}{}", "  ".repeat(depth), item.content))
+                .collect(),
+            Capture::Lines(tasks) => tasks.iter().map(|task| task.content.clone()).collect(),
+        }
+    }
+
+    /// The first task's content, for reporting
+    fn summary(&self) -> String {
+        let first = match self {
+            Capture::Task { task, .. } => &task.content,
+            Capture::Outline(outline) => &outline.items[0].content,
+            Capture::Lines(tasks) => &tasks[0].content,
+        };
+        match self.len() {
+            1 => first.clone(),
+            count => format!("{} (and {} more)", first, count - 1),
+        }
+    }
+}
+
+/// A capture waiting to be sent to Checkvist, and where it's going
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct QueuedCapture {
+    list_id: u32,
+    parent_id: Option<u32>,
+    location_name: String,
+    capture: Capture,
+    /// whether the first task may have been added after all, by the attempt that
+    /// failed
+    #[serde(default)]
+    first_may_exist: bool,
+}
+
+impl QueuedCapture {
+    fn job(&self, client: CheckvistClient) -> AddTaskJob {
+        AddTaskJob {
+            client,
+            capture: self.capture.clone(),
+            list_id: self.list_id,
+            parent_id: self.parent_id,
+            location_name: self.location_name.clone(),
+            possible_new_default_list: false,
+            dry_run: false,
+            print: None,
+            first_may_exist: self.first_may_exist,
+        }
+    }
+}
+
+/// What happened to queued captures when they were sent
+#[derive(Debug, Default)]
+pub(super) struct SendReport {
+    /// captures sent in full, so removed from the queue
+    pub sent: Vec<(String, Vec<AddedTask>)>,
+    /// the tasks added from a capture before Checkvist couldn't be reached again.
+    /// The rest of the capture is still queued
+    pub partly_sent: Option<(String, Vec<AddedTask>)>,
+    /// captures Checkvist wouldn't take, so were removed from the queue, with why
+    pub failed: Vec<(QueuedCapture, String)>,
+}
+
+impl SendReport {
+    /// Describes each failure, so no capture is lost without the user knowing
+    pub fn print_failures(&self, context: &context::Context) -> AnyhowResult<()> {
+        if context.output_format != OutputFormat::Text {
+            return Ok(());
+        }
+        for (queued, err) in &self.failed {
+            let mut output = ColourOutput::new(StreamKind::Stderr)
+                .append("Couldn't add queued tasks to ", Style::Warning)
+                .append(&queued.location_name, Style::ListName)
+                .append(
+                    format!(", so they were removed from the queue: {}\n", err),
+                    Style::Normal,
+                );
+            for content in queued.capture.contents() {
+                output = output.append(format!("    {}\n", content), Style::TaskContent);
+            }
+            output.println()?;
+        }
+        Ok(())
+    }
+
+    /// for `--output json`
+    pub fn data(&self) -> Value {
+        let sent_data = |(location, added): &(String, Vec<AddedTask>)| {
+            let ids: Vec<u32> = added.iter().map(|task| task.id).collect();
+            json!({ "location": location, "task_ids": ids })
+        };
+        let sent: Vec<Value> = self.sent.iter().map(sent_data).collect();
+        let partly_sent = self.partly_sent.as_ref().map(sent_data);
+        let failed: Vec<Value> = self
+            .failed
+            .iter()
+            .map(|(queued, err)| {
+                json!({
+                    "location": queued.location_name,
+                    "list_id": queued.list_id,
+                    "parent_task_id": queued.parent_id,
+                    "tasks": queued.capture.contents(),
+                    "error": err,
+                })
+            })
+            .collect();
+        json!({ "sent": sent, "partly_sent": partly_sent, "failed": failed })
+    }
+}
+
+/// Sends queued captures to Checkvist in order, until the queue is empty, or
+/// Checkvist can't be reached. Those Checkvist refuses are dropped (and reported)
+pub(super) fn send_queued(
+    context: &context::Context,
+    queue: &mut Queue<QueuedCapture>,
+) -> AnyhowResult<SendReport> {
+    let mut report = SendReport::default();
+    let mut stopped_by = None;
+    loop {
+        let handled = queue.pop_with(|queued| {
+            let added = context
+                .api_client()
+                .and_then(|client| queued.job(client).add_task(false));
+            match added {
+                Ok(added) => Handled::Done(Ok((queued.location_name.clone(), added))),
+                Err(err) if is_transient(&err) => {
+                    // only what's left is kept, so nothing is added twice
+                    if let Ok(unsent) = err.downcast::<Unsent>() {
+                        if !unsent.added.is_empty() {
+                            report.partly_sent = Some((queued.location_name.clone(), unsent.added));
+                        }
+                        queued.capture = unsent.capture;
+                        queued.first_may_exist = unsent.first_may_exist;
+                    }
+                    Handled::NotYet
+                }
+                Err(err) if is_logged_out(&err) => {
+                    stopped_by = Some(err);
+                    Handled::NotYet
+                }
+                Err(err) => Handled::Done(Err((queued.clone(), format!("{:#}", err)))),
+            }
+        })?;
+        match handled {
+            Some(Ok(sent)) => report.sent.push(sent),
+            Some(Err(failed)) => report.failed.push(failed),
+            None => break,
+        }
+    }
+    if let Some(err) = stopped_by {
+        return Err(err);
+    }
+
+    Ok(report)
+}
+
+/// Errors worth trying again later
+fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<CheckvistError>(),
+            Some(CheckvistError::NetworkError(_) | CheckvistError::RateLimitedError { .. })
+        )
+    })
+}
+
+fn is_logged_out(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<CheckvistError>(),
+            Some(CheckvistError::TokenRefreshFailedError)
+        )
+    })
+}
+
+/// Checkvist couldn't be reached (or is refusing requests for now), so `capture`
+/// (or what was left of it) wasn't added
+#[derive(Debug)]
+struct Unsent {
+    capture: Capture,
+    added: Vec<AddedTask>,
+    /// the request for the first task may have reached Checkvist anyway
+    first_may_exist: bool,
+    source: CheckvistError,
+}
+
+impl fmt::Display for Unsent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Couldn't reach Checkvist to add {}",
+            self.capture.summary()
+        )
+    }
+}
+
+impl std::error::Error for Unsent {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
 }
 
 impl AddTaskJob {
     fn run(self, context: context::Context) -> AnyhowResult<RunType> {
-        if context.allow_interaction {
-            let msg = self.user_message();
-            let new_name = self.location_name.clone();
-            let new_list_id = self.list_id;
-            let user_msg = || msg.println().expect("Problem printing colour output");
-            let do_job = || self.add_task();
-            let config_dirty = ProgressIndicator::new('.', Box::new(user_msg), 250).run(do_job)?;
-            if config_dirty {
-                let new_config = crate::config::Config {
-                    list_id: new_list_id,
-                    list_name: new_name,
-                    ..context.config.unwrap()
-                };
-                interaction::offer_to_save_new_default_list(
-                    // AddTaskValidated guarantees context.config is Some
-                    &new_config,
-                    &context.config_file_path,
-                )?;
+        if self.dry_run {
+            return self.preview(context.output_format);
+        }
+        let mut queue = Queue::load(&context.queue_file_path)?;
+        // anything already queued goes first, so captures reach Checkvist in the order made
+        let from_queue = if queue.is_empty() {
+            None
+        } else {
+            let report = send_queued(&context, &mut queue)?;
+            report.print_failures(&context)?;
+            Some(report)
+        };
+        if !queue.is_empty() {
+            return self.queue_unsent(
+                &context,
+                self.capture.clone(),
+                Vec::new(),
+                false,
+                &mut queue,
+            );
+        }
+        let added = match self.add_with_progress(&context) {
+            Ok(added) => added,
+            Err(err) => match err.downcast::<Unsent>() {
+                Ok(unsent) => {
+                    return self.queue_unsent(
+                        &context,
+                        unsent.capture,
+                        unsent.added,
+                        unsent.first_may_exist,
+                        &mut queue,
+                    )
+                }
+                Err(err) => return Err(err),
+            },
+        };
+        if context.allow_interaction && self.possible_new_default_list {
+            let new_config = crate::config::Config {
+                list_id: self.list_id,
+                list_name: self.location_name.clone(),
+                ..context.config.clone().unwrap()
+            };
+            interaction::offer_to_save_new_default_list(
+                // AddTaskValidated guarantees context.config is Some
+                &new_config,
+                &context.config_file_path,
+            )?;
+        }
+        let message = match (self.print, context.output_format) {
+            (Some(field), OutputFormat::Text) => {
+                let printed = self.printed(field, &added);
+                // -q shows no message, but what's asked for is printed regardless
+                if !context.allow_interaction {
+                    println!("{}", printed);
+                }
+                printed
             }
+            _ => self.done_message(&added),
+        };
+
+        let mut data = self.data(&added);
+        if let Some(report) = from_queue {
+            data["from_queue"] = report.data();
+        }
+        Ok(RunType::Completed(Outcome::new(message).with_data(data)))
+    }
+
+    /// Adds the capture, showing progress if interaction is allowed
+    fn add_with_progress(&self, context: &context::Context) -> AnyhowResult<Vec<AddedTask>> {
+        if !context.allow_interaction {
+            return self.add_task(false);
+        }
+        let msg = self.user_message();
+        if let Capture::Lines(_) = self.capture {
+            // each task's progress is shown as it's added, rather than a stream of dots
+            msg.println()?;
+            self.add_task(true)
         } else {
-            self.add_task()?;
+            let user_msg = || msg.println().expect("Problem printing colour output");
+            let do_job = || self.add_task(false);
+            ProgressIndicator::new('.', Box::new(user_msg), 250).run(do_job)
+        }
+    }
+
+    /// Keeps what couldn't be sent, to be sent by `cvcap sync` or the next add
+    fn queue_unsent(
+        &self,
+        context: &context::Context,
+        capture: Capture,
+        added: Vec<AddedTask>,
+        first_may_exist: bool,
+        queue: &mut Queue<QueuedCapture>,
+    ) -> AnyhowResult<RunType> {
+        let queued = capture.len();
+        queue.push(QueuedCapture {
+            list_id: self.list_id,
+            parent_id: self.parent_id,
+            location_name: self.location_name.clone(),
+            capture,
+            first_may_exist,
+        })?;
+        let message = match (added.len(), queued) {
+            (0, 1) => "Couldn't reach Checkvist, so the task was queued".to_string(),
+            (0, queued) => format!("Couldn't reach Checkvist, so {} tasks were queued", queued),
+            (added, queued) => format!(
+                "Added {} tasks, then couldn't reach Checkvist, so the other {} were queued",
+                added, queued
+            ),
+        };
+        let message = format!(
+            "{}. {} capture(s) now waiting for `cvcap sync` (or the next add)",
+            message,
+            queue.len()
+        );
+        if let (Some(field), OutputFormat::Text) = (self.print, context.output_format) {
+            // a script waiting for ids or urls mustn't take getting none as success
+            if !added.is_empty() {
+                println!("{}", self.printed(field, &added));
+            }
+            return Err(app::Error::Reportable(format!(
+                "{}. Queued tasks have no id or url to print until they're sent",
+                message
+            ))
+            .into());
         }
-        Ok(RunType::Completed("Task added".into()))
+        let mut data = self.data(&added);
+        data["queued"] = json!({ "tasks": queued, "captures_waiting": queue.len() });
+
+        Ok(RunType::Completed(Outcome::new(message).with_data(data)))
+    }
+
+    fn url(&self, task: &AddedTask) -> String {
+        self.client.task_permalink(self.list_id, task.id)
     }
 
-    /// returns true if the default list should be offered to be saved
-    fn add_task(self) -> AnyhowResult<bool> {
-        self.client
-            .add_task(self.list_id, &self.task)
-            .map(|_| ())
-            .map_err(|e| anyhow!(e))
-            .context("Could not add task")?;
+    /// The tasks added, and where, for `--output json`
+    fn data(&self, added: &[AddedTask]) -> Value {
+        let tasks: Vec<Value> = added
+            .iter()
+            .map(|task| {
+                json!({
+                    "id": task.id,
+                    "content": task.content,
+                    "url": self.url(task),
+                })
+            })
+            .collect();
+        json!({
+            "list_id": self.list_id,
+            "parent_task_id": self.parent_id,
+            "location": self.location_name,
+            "tasks": tasks,
+        })
+    }
 
-        Ok(self.possible_new_default_list)
+    /// Shows what would be added, without adding it
+    fn preview(self, output_format: OutputFormat) -> AnyhowResult<RunType> {
+        let Capture::Lines(ref tasks) = self.capture else {
+            return Err(anyhow!("only --each-line has a dry run"));
+        };
+        let outcome = Outcome::new("Dry run, so no tasks were added");
+        if output_format == OutputFormat::Json {
+            let tasks: Vec<Value> = tasks
+                .iter()
+                .map(|task| {
+                    json!({
+                        "content": task.content,
+                        "tags": task.tags.keys().collect::<Vec<_>>(),
+                        "due": task.due.map(|due| due.format("%Y-%m-%d").to_string()),
+                    })
+                })
+                .collect();
+            let data = json!({
+                "dry_run": true,
+                "list_id": self.list_id,
+                "parent_task_id": self.parent_id,
+                "location": self.location_name,
+                "tasks": tasks,
+            });
+            return Ok(RunType::Completed(outcome.with_data(data)));
+        }
+        let mut output = ColourOutput::new(StreamKind::Stdout).append(
+            format!(
+                "Would add {} tasks to {}:\n",
+                tasks.len(),
+                self.location_name
+            ),
+            Style::Normal,
+        );
+        for (i, task) in tasks.iter().enumerate() {
+            output = output
+                .append(format!("{:>4}. ", i + 1), Style::Normal)
+                .append(&task.content, Style::TaskContent)
+                .append(task_details(task), Style::Normal)
+                .append("\n", Style::Normal);
+        }
+        output.println()?;
+
+        Ok(RunType::Completed(outcome))
+    }
+
+    /// The `field` of each task added, a line each, for `--print`
+    fn printed(&self, field: PrintField, added: &[AddedTask]) -> String {
+        let lines: Vec<String> = added
+            .iter()
+            .map(|task| match field {
+                PrintField::Id => task.id.to_string(),
+                PrintField::Url => self.url(task),
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    fn done_message(&self, added: &[AddedTask]) -> String {
+        match added {
+            [] => "No tasks added".into(),
+            [task] => format!("Task added (id {}): {}", task.id, self.url(task)),
+            [first, ..] => format!(
+                "{} tasks added (the first has id {}): {}",
+                added.len(),
+                first.id,
+                self.url(first)
+            ),
+        }
+    }
+
+    /// returns the tasks added (but not any children of a single task)
+    /// `show_progress` prints a line as each of several tasks is added
+    fn add_task(&self, show_progress: bool) -> AnyhowResult<Vec<AddedTask>> {
+        let (task, note, children) = match &self.capture {
+            Capture::Task {
+                task,
+                note,
+                children,
+                ..
+            } => (task, note, children),
+            Capture::Outline(outline) => {
+                let location = CheckvistLocation {
+                    list_id: self.list_id,
+                    parent_task_id: self.parent_id,
+                };
+                let report = self
+                    .client
+                    .import_outline(outline, &location)
+                    .map_err(|failed| {
+                        if !failed.may_have_added {
+                            return add_error(
+                                failed.error,
+                                self.capture.clone(),
+                                Vec::new(),
+                                "Could not add tasks",
+                            );
+                        }
+                        // not kept as the source, so that the outline is never queued
+                        // (or retried from the queue) once some of it may have been added
+                        anyhow!("{}", failed.error).context(format!(
+                            "Could not add all the tasks ({} of {} are known to have been added). Check the list before adding them again",
+                            failed.task_ids.len(),
+                            outline.len()
+                        ))
+                    })?;
+                let added = outline
+                    .walk()
+                    .into_iter()
+                    .zip(report.task_ids)
+                    .map(|((_depth, item), id)| AddedTask {
+                        id,
+                        content: item.content.clone(),
+                    })
+                    .collect();
+                return Ok(added);
+            }
+            Capture::Lines(tasks) => {
+                let mut added = Vec::new();
+                for (i, task) in tasks.iter().enumerate() {
+                    let new_task = match self.send(task, i == 0) {
+                        Ok(new_task) => new_task,
+                        Err(err) => {
+                            let context = format!(
+                                "Could not add task {}, after adding {} of {}",
+                                task.content,
+                                i,
+                                tasks.len()
+                            );
+                            let rest = Capture::Lines(tasks[i..].to_vec());
+                            return Err(add_error(err, rest, added, &context));
+                        }
+                    };
+                    if show_progress {
+                        ColourOutput::new(StreamKind::Stdout)
+                            .append(format!("  [{}/{}] ", i + 1, tasks.len()), Style::Normal)
+                            .append(&task.content, Style::TaskContent)
+                            .println()?;
+                    }
+                    added.push(AddedTask::try_from(new_task)?);
+                }
+                return Ok(added);
+            }
+        };
+        let task = self.send(task, true).map_err(|err| {
+            add_error(err, self.capture.clone(), Vec::new(), "Could not add task")
+        })?;
+        let added = AddedTask::try_from(task)?;
+        let task_id = added.id;
+        // the errors that follow aren't kept as their source, so that a queued
+        // capture isn't retried (and the task added again)
+        if let Some(note) = note {
+            self.client
+                .add_note(self.list_id, task_id, note)
+                .map_err(|e| anyhow!("{}", e))
+                .context("Added the task, but could not add its note")?;
+        }
+        for child in children {
+            let child = Task {
+                parent_id: Some(task_id),
+                ..child.clone()
+            };
+            self.client
+                .add_task(self.list_id, &child)
+                .map_err(|e| anyhow!("{}", e))
+                .context("Added the task, but could not add all of its child tasks")?;
+        }
+
+        Ok(vec![added])
+    }
+
+    /// Adds a single task. The `first` of a queued capture is only added if it
+    /// isn't already there, if the attempt that failed may have reached Checkvist
+    fn send(&self, task: &Task, first: bool) -> std::result::Result<Task, CheckvistError> {
+        if first && self.first_may_exist {
+            self.client
+                .add_task_idempotent(self.list_id, task, &IdempotencyKey::Content)
+                .map(|outcome| outcome.task().clone())
+        } else {
+            self.client.add_task(self.list_id, task)
+        }
     }
 
     fn user_message(&self) -> ColourOutput {
-        ColourOutput::new(StreamKind::Stdout)
+        let (task, smart_content, note, children) = match &self.capture {
+            Capture::Task {
+                task,
+                smart_content,
+                note,
+                children,
+            } => (task, smart_content, note, children),
+            Capture::Lines(tasks) => {
+                return ColourOutput::new(StreamKind::Stdout).append(
+                    format!("Adding {} tasks to {}", tasks.len(), &self.location_name),
+                    Style::Normal,
+                );
+            }
+            Capture::Outline(outline) => {
+                let first = &outline.items[0].content;
+                return ColourOutput::new(StreamKind::Stdout)
+                    .append(
+                        format!("Adding {} tasks, starting with ", outline.len()),
+                        Style::Normal,
+                    )
+                    .append(first, Style::TaskContent)
+                    .append(format!(", to {}", &self.location_name), Style::Normal);
+            }
+        };
+        let msg = ColourOutput::new(StreamKind::Stdout)
             .append("Adding task ", Style::Normal)
-            .append(&self.task.content, Style::TaskContent)
-            .append(format!(" to {}", &self.location_name), Style::Normal)
+            .append(&smart_content.text, Style::TaskContent)
+            .append(format!(" to {}", &self.location_name), Style::Normal);
+        let msg = match (note, children.len()) {
+            (Some(_), _) => msg.append(" with a note", Style::Normal),
+            (None, 0) => msg,
+            (None, 1) => msg.append(" with 1 child task", Style::Normal),
+            (None, count) => msg.append(format!(" with {} child tasks", count), Style::Normal),
+        };
+        if !smart_content.has_smart_syntax() {
+            return msg;
+        }
+
+        let mut details = Vec::new();
+        match (&smart_content.due, task.due) {
+            (Some(due), Some(date)) => details.push(format!("due: {} ({})", due, date)),
+            (Some(due), None) => details.push(format!("due: {} (left for Checkvist to interpret)", due)),
+            _ => (),
+        }
+        if !smart_content.tags.is_empty() {
+            details.push(format!("tags: {}", smart_content.tags.join(", ")));
+        }
+        if let Some(priority) = smart_content.priority {
+            details.push(format!("priority: {}", priority));
+        }
+        if let Some(ref assignee) = smart_content.assignee {
+            details.push(format!("assigned to: {}", assignee));
+        }
+        msg.append(format!("\n  ({})", details.join("; ")), Style::Normal)
     }
 }
 
@@ -280,6 +1108,157 @@ impl std::fmt::Display for AddTaskError {
     }
 }
 
+/// The error from adding `unsent` (after `added`). If Checkvist couldn't be reached,
+/// it's an `Unsent`, so what's left can be queued and sent later
+fn add_error(
+    err: CheckvistError,
+    unsent: Capture,
+    added: Vec<AddedTask>,
+    context: &str,
+) -> anyhow::Error {
+    match err {
+        CheckvistError::NetworkError(_) | CheckvistError::RateLimitedError { .. } => Unsent {
+            capture: unsent,
+            added,
+            first_may_exist: err.may_have_succeeded(),
+            source: err,
+        }
+        .into(),
+        err => anyhow!(err).context(context.to_string()),
+    }
+}
+
+/// A task's tags and due date, as smart syntax
+fn task_details(task: &Task) -> String {
+    let mut details = String::new();
+    for tag in task.tags.keys() {
+        details.push_str(&format!(" #{}", tag));
+    }
+    if let Some(due) = task.due {
+        details.push_str(&format!(" ^{}", due.format("%Y-%m-%d")));
+    }
+    details
+}
+
+/// `content`'s smart syntax with `--smart`. Otherwise, `content` as written
+fn parse_content(content: &str, smart: bool) -> SmartContent {
+    if smart {
+        smart_syntax::parse(content)
+    } else {
+        SmartContent {
+            text: content.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
 fn is_content_piped() -> bool {
     atty::isnt(atty::Stream::Stdin)
 }
+
+/// Lines starting with this are left out of the task. It's unlike anything written
+/// in a task, so tags and Markdown headings aren't mistaken for comments
+const EDITOR_COMMENT: &str = "#:";
+
+fn editor_template(location_name: &str, extra_lines_as: ExtraLines) -> String {
+    let extra_lines = match extra_lines_as {
+        ExtraLines::Note => "a note on the task",
+        ExtraLines::Children => "child tasks, one per line",
+    };
+    format!(
+        "\n\
+         {c} Write the task on the first line. Any lines after it become {}.\n\
+         {c} Lines starting with '{c}' are ignored, and an empty task cancels.\n\
+         {c}\n\
+         {c} Adding to: {}\n",
+        extra_lines,
+        location_name,
+        c = EDITOR_COMMENT
+    )
+}
+
+/// The task (the first line with content) and the lines after it, leaving out
+/// comments. None if there's no task
+fn split_editor_text(text: &str) -> Option<(String, Vec<String>)> {
+    let mut lines = text
+        .lines()
+        .filter(|line| !line.starts_with(EDITOR_COMMENT))
+        .skip_while(|line| line.trim().is_empty());
+    let task = lines.next()?.trim().to_string();
+    let mut rest: Vec<String> = lines.map(|line| line.trim_end().to_string()).collect();
+    while rest.last().is_some_and(|line| line.is_empty()) {
+        rest.pop();
+    }
+    while rest.first().is_some_and(|line| line.is_empty()) {
+        rest.remove(0);
+    }
+
+    Some((task, rest))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn editor_text_is_split_into_task_and_following_lines() {
+        let template = editor_template("Inbox", ExtraLines::Note);
+        let text = format!("Buy milk #shop\n\n  semi-skimmed\nor oat\n\n{}", template);
+
+        assert_eq!(
+            split_editor_text(&text),
+            Some((
+                "Buy milk #shop".to_string(),
+                vec!["  semi-skimmed".to_string(), "or oat".to_string()]
+            ))
+        );
+    }
+
+    #[test]
+    fn each_line_is_a_task_in_order() {
+        let tasks =
+            AddTask::tasks_from_lines("one #a\n\n  two\nthree; four\n", "\n", Some(7), true);
+
+        let contents: Vec<(&str, u16)> = tasks
+            .iter()
+            .map(|task| (task.content.as_str(), task.position))
+            .collect();
+        assert_eq!(contents, vec![("one", 1), ("two", 2), ("three; four", 3)]);
+        assert_eq!(tasks[0].tags.keys().collect::<Vec<_>>(), vec!["a"]);
+        assert!(tasks.iter().all(|task| task.parent_id == Some(7)));
+
+        let tasks = AddTask::tasks_from_lines("three; four;", ";", None, true);
+        assert_eq!(tasks.len(), 2);
+    }
+
+    #[test]
+    fn content_is_sent_as_written_without_smart() {
+        let tasks = AddTask::tasks_from_lines("call  mum #42 ^tomorrow", "\n", None, false);
+
+        assert_eq!(tasks[0].content, "call  mum #42 ^tomorrow");
+        assert!(tasks[0].tags.is_empty());
+        assert_eq!(tasks[0].due, None);
+    }
+
+    #[test]
+    fn editor_text_keeps_markdown_headings() {
+        let template = editor_template("Inbox", ExtraLines::Note);
+        let text = format!("Plan the trip\n# Travel\n## Hotels\n{}", template);
+
+        assert_eq!(
+            split_editor_text(&text),
+            Some((
+                "Plan the trip".to_string(),
+                vec!["# Travel".to_string(), "## Hotels".to_string()]
+            ))
+        );
+    }
+
+    #[test]
+    fn editor_text_without_a_task_cancels() {
+        assert_eq!(
+            split_editor_text(&editor_template("Inbox", ExtraLines::Children)),
+            None
+        );
+    }
+}
@@ -4,10 +4,11 @@ use anyhow::{anyhow, Context as AnyhowContext, Result as AnyhowResult};
 use bpaf::{command, construct, params::ParseCommand, positional, Parser};
 use cvapi::CheckvistError;
 use dialoguer::Confirm;
+use serde_json::json;
 
 use super::{
     context::{self, Context},
-    Action, RunType,
+    Action, Outcome, RunType,
 };
 use crate::{
     app::{bookmark::Bookmark, cli::Command, Error as AppError},
@@ -170,9 +171,12 @@ impl AddBookmarkJob {
     /// Add the bookmark in self.bookmark to the config in self.config
     /// This method does no user interaction
     fn add_bookmark(mut self, context: Context) -> AnyhowResult<RunType> {
+        let data = json!({ "bookmark": self.bookmark });
         self.config.add_bookmark(self.bookmark, true)?;
         self.config.save(&context.config_file_path)?;
 
-        Ok(RunType::Completed("\nBookmark Added".into()))
+        Ok(RunType::Completed(
+            Outcome::new("\nBookmark Added").with_data(data),
+        ))
     }
 }
@@ -0,0 +1,398 @@
+//! Lists, shows, renames, removes and validates bookmarks (`add-bookmark` adds them)
+use anyhow::{anyhow, Result};
+use bpaf::{command, construct, long, parsers::ParseCommand, positional, pure, Parser};
+use cvapi::{CheckvistClient, CheckvistError};
+use dialoguer::Confirm;
+use serde_json::{json, Value};
+
+use super::{Action, Outcome, RunType};
+use crate::app::{
+    bookmark::Bookmark,
+    cli::{Command, OutputFormat},
+    context::{ConfigAbsentError, Context},
+    Config, Error as AppError,
+};
+use crate::colour_output::{ColourOutput, StreamKind, Style};
+use crate::progress_indicator::ProgressIndicator;
+
+const NOT_FOUND: &str = "(not found)";
+
+#[derive(Debug, Clone)]
+enum BookmarkCommand {
+    List,
+    Show(String),
+    Rename { name: String, new_name: String },
+    Remove(String),
+    Validate { prune: bool },
+}
+
+#[derive(Debug, Clone)]
+pub struct ManageBookmarks {
+    subcommand: BookmarkCommand,
+}
+
+impl ManageBookmarks {
+    pub fn command() -> ParseCommand<Command> {
+        let bookmark_name = || positional::<String>("BOOKMARK").help("The bookmark's name");
+
+        let list = pure(BookmarkCommand::List)
+            .to_options()
+            .descr("List bookmarks, with the lists and tasks they point to");
+        let list = command("list", list).help("Lists bookmarks (the default)");
+
+        let name = bookmark_name();
+        let show = construct!(BookmarkCommand::Show(name))
+            .to_options()
+            .descr("Show the list and task a bookmark points to, and its url");
+        let show = command("show", show).help("Shows a bookmark");
+
+        let name = bookmark_name();
+        let new_name = positional::<String>("NEW_NAME").help("The bookmark's new name");
+        let rename = construct!(BookmarkCommand::Rename { name, new_name })
+            .to_options()
+            .descr("Rename a bookmark");
+        let rename = command("rename", rename).help("Renames a bookmark");
+
+        let name = bookmark_name();
+        let remove = construct!(BookmarkCommand::Remove(name))
+            .to_options()
+            .descr("Remove a bookmark (from cvcap only: Checkvist isn't changed)");
+        let remove = command("remove", remove).help("Removes a bookmark");
+
+        let prune = long("prune")
+            .help("Remove invalid bookmarks without asking (as is needed with -q)")
+            .switch();
+        let validate = construct!(BookmarkCommand::Validate { prune })
+            .to_options()
+            .descr("Check that each bookmark's list or task still exists in Checkvist, offering to remove those that don't");
+        let validate = command("validate", validate).help("Checks bookmarks are still valid");
+
+        let subcommand =
+            construct!([list, show, rename, remove, validate]).fallback(BookmarkCommand::List);
+        let bookmarks = construct!(ManageBookmarks { subcommand });
+        let bookmarks = construct!(Command::ManageBookmarks(bookmarks))
+            .to_options()
+            .descr("Manage bookmarks: list (the default), show, rename, remove or validate them");
+        command("bookmarks", bookmarks).help("Lists and manages bookmarks")
+    }
+}
+
+impl Action for ManageBookmarks {
+    fn run(self, context: Context) -> Result<RunType> {
+        let config = match &context.config {
+            Ok(config) => config.clone(),
+            Err(ConfigAbsentError::UserCancellation) => return Ok(RunType::Cancelled),
+            Err(err) => return Err(anyhow!(err.to_string())),
+        };
+        match self.subcommand {
+            BookmarkCommand::List => list(&context, &config),
+            BookmarkCommand::Show(name) => show(&context, &config, &name),
+            BookmarkCommand::Rename { name, new_name } => {
+                rename(&context, config, &name, &new_name)
+            }
+            BookmarkCommand::Remove(name) => remove(&context, config, &name),
+            BookmarkCommand::Validate { prune } => validate(&context, config, prune),
+        }
+    }
+}
+
+fn list(context: &Context, config: &Config) -> Result<RunType> {
+    let bookmarks = config.bookmarks.clone().unwrap_or_default();
+    if bookmarks.is_empty() {
+        return Ok(RunType::Completed(
+            Outcome::new("No bookmarks. `cvcap add-bookmark` adds one")
+                .with_data(json!({ "bookmarks": [] })),
+        ));
+    }
+    let resolved = resolve_all(context, bookmarks)?;
+    let message = format!("{} bookmark(s)", resolved.len());
+    let data = json!({
+        "bookmarks": resolved.iter().map(Resolved::data).collect::<Vec<Value>>(),
+    });
+    if context.output_format == OutputFormat::Json {
+        return Ok(RunType::Completed(Outcome::new(message).with_data(data)));
+    }
+
+    let name_width = resolved
+        .iter()
+        .map(|r| r.bookmark.name.chars().count())
+        .max()
+        .unwrap_or_default();
+    let location_width = resolved
+        .iter()
+        .map(|r| r.location_description().chars().count())
+        .max()
+        .unwrap_or_default();
+    let mut output = ColourOutput::new(StreamKind::Stdout);
+    for r in &resolved {
+        let location_style = if r.is_found() {
+            Style::ListName
+        } else {
+            Style::Warning
+        };
+        output = output
+            .append(
+                format!("{:<width$}  ", r.bookmark.name, width = name_width),
+                Style::Normal,
+            )
+            .append(
+                format!(
+                    "{:<width$}  ",
+                    r.location_description(),
+                    width = location_width
+                ),
+                location_style,
+            )
+            .append(&r.url, Style::Link)
+            .append("\n", Style::Normal);
+    }
+    output.println()?;
+
+    Ok(RunType::Completed(Outcome::new(message).with_data(data)))
+}
+
+fn show(context: &Context, config: &Config, name: &str) -> Result<RunType> {
+    let bookmark = config
+        .bookmark(name)
+        .ok_or_else(|| no_such_bookmark(name))?;
+    let resolved = resolve_all(context, vec![bookmark])?.remove(0);
+    let message = if resolved.is_found() {
+        format!("`cvcap add -b {}` adds tasks here", name)
+    } else {
+        format!(
+            "Checkvist can't find this location. `cvcap bookmarks remove {}` removes the bookmark",
+            name
+        )
+    };
+    let data = json!({ "bookmark": resolved.data() });
+    if context.output_format == OutputFormat::Json {
+        return Ok(RunType::Completed(Outcome::new(message).with_data(data)));
+    }
+
+    let location = &resolved.bookmark.location;
+    let mut output = ColourOutput::new(StreamKind::Stdout)
+        .append(&resolved.bookmark.name, Style::Normal)
+        .append("\n    list:  ", Style::Normal);
+    output = append_found(output, resolved.list_name.as_deref(), Style::ListName)
+        .append(format!(" (id {})", location.list_id), Style::Normal);
+    if let Some(task_id) = location.parent_task_id {
+        output = output.append("\n    task:  ", Style::Normal);
+        output = append_found(output, resolved.task_content.as_deref(), Style::TaskContent)
+            .append(format!(" (id {})", task_id), Style::Normal);
+    }
+    output
+        .append("\n    url:   ", Style::Normal)
+        .append(&resolved.url, Style::Link)
+        .append("\n", Style::Normal)
+        .println()?;
+
+    Ok(RunType::Completed(Outcome::new(message).with_data(data)))
+}
+
+fn rename(context: &Context, mut config: Config, name: &str, new_name: &str) -> Result<RunType> {
+    config.rename_bookmark(name, new_name)?;
+    config.save(&context.config_file_path)?;
+
+    Ok(RunType::Completed(
+        Outcome::new(format!("Bookmark '{}' renamed to '{}'", name, new_name))
+            .with_data(json!({ "bookmark": config.bookmark(new_name) })),
+    ))
+}
+
+fn remove(context: &Context, mut config: Config, name: &str) -> Result<RunType> {
+    let removed = config
+        .remove_bookmark(name)
+        .ok_or_else(|| no_such_bookmark(name))?;
+    config.save(&context.config_file_path)?;
+
+    Ok(RunType::Completed(
+        Outcome::new(format!("Bookmark '{}' removed", name))
+            .with_data(json!({ "bookmark": removed })),
+    ))
+}
+
+/// Checks each bookmark with Checkvist, removing the invalid ones if `prune`
+/// is set or the user agrees
+fn validate(context: &Context, mut config: Config, prune: bool) -> Result<RunType> {
+    let bookmarks = config.bookmarks.clone().unwrap_or_default();
+    if bookmarks.is_empty() {
+        return Ok(RunType::Completed(
+            Outcome::new("No bookmarks to validate")
+                .with_data(json!({ "invalid": [], "pruned": false })),
+        ));
+    }
+    let client = context.api_client()?;
+    let check = || -> Result<Vec<Bookmark>> {
+        let mut invalid = Vec::new();
+        for bookmark in &bookmarks {
+            if !client.is_location_valid(&bookmark.location)? {
+                invalid.push(bookmark.clone());
+            }
+        }
+        Ok(invalid)
+    };
+    let invalid = if context.allow_interaction {
+        ProgressIndicator::new(
+            '.',
+            Box::new(|| println!("Checking {} bookmark(s) with Checkvist", bookmarks.len())),
+            250,
+        )
+        .run(check)?
+    } else {
+        check()?
+    };
+    if invalid.is_empty() {
+        return Ok(RunType::Completed(
+            Outcome::new(format!("All {} bookmark(s) are valid", bookmarks.len()))
+                .with_data(json!({ "invalid": [], "pruned": false })),
+        ));
+    }
+
+    if context.allow_interaction {
+        let mut output = ColourOutput::new(StreamKind::Stdout).append(
+            "Checkvist can't find the location of these bookmarks:\n",
+            Style::Normal,
+        );
+        for bookmark in &invalid {
+            output = output.append(format!("    {}\n", bookmark.name), Style::Warning);
+        }
+        output.println()?;
+    }
+    let pruned = prune
+        || (context.allow_interaction
+            && Confirm::new()
+                .with_prompt(format!("Remove {} invalid bookmark(s)?", invalid.len()))
+                .interact()?);
+    let message = if pruned {
+        for bookmark in &invalid {
+            config.remove_bookmark(&bookmark.name);
+        }
+        config.save(&context.config_file_path)?;
+        format!("Removed {} invalid bookmark(s)", invalid.len())
+    } else {
+        format!(
+            "{} of {} bookmark(s) are invalid. `cvcap bookmarks validate --prune` removes them",
+            invalid.len(),
+            bookmarks.len()
+        )
+    };
+
+    Ok(RunType::Completed(Outcome::new(message).with_data(
+        json!({ "invalid": invalid, "pruned": pruned }),
+    )))
+}
+
+/// A bookmark, with the names Checkvist has for where it points
+struct Resolved {
+    bookmark: Bookmark,
+    /// None if Checkvist can't find the list
+    list_name: Option<String>,
+    /// None if the bookmark is to a list, or Checkvist can't find the task
+    task_content: Option<String>,
+    url: String,
+}
+
+impl Resolved {
+    /// Whether Checkvist has the list and (if any) task the bookmark points to
+    fn is_found(&self) -> bool {
+        self.list_name.is_some()
+            && (self.bookmark.location.parent_task_id.is_none() || self.task_content.is_some())
+    }
+
+    /// eg. "Work > Inbox", for a bookmark to the task "Inbox" in the list "Work"
+    fn location_description(&self) -> String {
+        if !self.is_found() {
+            return NOT_FOUND.to_string();
+        }
+        let list_name = self.list_name.clone().unwrap_or_default();
+        match &self.task_content {
+            Some(task_content) => format!("{} > {}", list_name, task_content),
+            None => list_name,
+        }
+    }
+
+    fn data(&self) -> Value {
+        json!({
+            "name": self.bookmark.name,
+            "list_id": self.bookmark.location.list_id,
+            "parent_task_id": self.bookmark.location.parent_task_id,
+            "list_name": self.list_name,
+            "task_content": self.task_content,
+            "url": self.url,
+            "found": self.is_found(),
+        })
+    }
+}
+
+fn resolve_all(context: &Context, bookmarks: Vec<Bookmark>) -> Result<Vec<Resolved>> {
+    let client = context.api_client()?;
+    let fetch = || -> Result<Vec<Resolved>> {
+        bookmarks
+            .iter()
+            .map(|bookmark| Ok(resolve(&client, bookmark.clone())?))
+            .collect()
+    };
+    if context.allow_interaction {
+        ProgressIndicator::new(
+            '.',
+            Box::new(|| println!("Fetching bookmark locations from Checkvist")),
+            250,
+        )
+        .run(fetch)
+    } else {
+        fetch()
+    }
+}
+
+fn resolve(client: &CheckvistClient, bookmark: Bookmark) -> Result<Resolved, CheckvistError> {
+    let location = &bookmark.location;
+    let list_name = match client.get_list(location.list_id) {
+        Ok(list) => Some(list.name),
+        Err(CheckvistError::InvalidListError) => None,
+        Err(err) => return Err(err),
+    };
+    let (task_content, url) = match location.parent_task_id {
+        Some(task_id) => {
+            let task_content = if list_name.is_some() {
+                match client.get_task(location.list_id, task_id) {
+                    // the task comes with its parents
+                    Ok(tasks) => tasks
+                        .into_iter()
+                        .find(|task| task.id == Some(task_id))
+                        .map(|task| first_line(&task.content)),
+                    Err(CheckvistError::InvalidTaskError) => None,
+                    Err(err) => return Err(err),
+                }
+            } else {
+                None
+            };
+            (
+                task_content,
+                client.task_permalink(location.list_id, task_id),
+            )
+        }
+        None => (None, client.list_permalink(location.list_id)),
+    };
+
+    Ok(Resolved {
+        bookmark,
+        list_name,
+        task_content,
+        url,
+    })
+}
+
+fn first_line(content: &str) -> String {
+    content.lines().next().unwrap_or_default().to_string()
+}
+
+fn append_found(output: ColourOutput, found: Option<&str>, style: Style) -> ColourOutput {
+    match found {
+        Some(text) => output.append(text, style),
+        None => output.append(NOT_FOUND, Style::Warning),
+    }
+}
+
+fn no_such_bookmark(name: &str) -> AppError {
+    AppError::Reportable(format!("No bookmark named '{}' was found", name))
+}
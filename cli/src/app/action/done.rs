@@ -0,0 +1,329 @@
+//! Closes a task, chosen by id, permalink, or from the open tasks at a location.
+//! Honours -q by returning errors where the user would need to pick a task
+use anyhow::{anyhow, Result as AnyhowResult};
+use bpaf::{command, construct, long, parsers::ParseCommand, positional, Parser};
+use cvapi::outline::OutlineItem;
+use cvapi::{CheckvistClient, CheckvistError, CheckvistLocation, TaskStatus};
+use serde_json::{json, Value};
+
+use super::{show_tasks, Action, Outcome, RunType};
+use crate::app::{
+    self,
+    bookmark::Bookmark,
+    cli,
+    context::{self, ConfigAbsentError},
+    interaction,
+};
+use crate::colour_output::{ColourOutput, StreamKind, Style};
+use crate::progress_indicator::ProgressIndicator;
+
+type Result<T> = std::result::Result<T, CloseTaskError>;
+
+#[derive(Debug, Clone)]
+pub struct CloseTask {
+    bookmark: Option<String>,
+    /// a task id or permalink
+    task: Option<String>,
+}
+
+impl Action for CloseTask {
+    fn run(self, context: context::Context) -> AnyhowResult<RunType> {
+        match self.create_job(&context) {
+            Ok(job) => job.run(context),
+            Err(CloseTaskError::UserCancellation) => Ok(RunType::Cancelled),
+            Err(CloseTaskError::Unhandled(e)) => Err(e),
+        }
+    }
+}
+
+impl CloseTask {
+    pub fn command() -> ParseCommand<cli::Command> {
+        let bookmark = long("bookmark")
+            .short('b')
+            .help("Close a task at a bookmarked location (instead of in the default list)")
+            .argument::<String>("BOOKMARK")
+            .optional();
+        let task = positional::<String>("TASK")
+            .help("The id or permalink of the task to close\nWithout one, you pick from the open tasks in the default list or bookmark")
+            .optional();
+        let close_task = construct!(CloseTask { bookmark, task });
+        let done = construct!(cli::Command::CloseTask(close_task))
+            .to_options()
+            .descr("Close a task, given its id or permalink, or by picking from open tasks");
+        command("done", done).help("Closes a task")
+    }
+
+    fn create_job(self, context: &context::Context) -> Result<CloseTaskJob> {
+        let client = context.api_client()?;
+        let task = match self.task.as_deref().map(parse_task_arg).transpose()? {
+            Some(TaskArg::Permalink(_)) if self.bookmark.is_some() => {
+                return Err(reportable(
+                    "A permalink already says where the task is, so can't be used with a bookmark",
+                ))
+            }
+            Some(TaskArg::Permalink(location)) => {
+                let task_id = location.parent_task_id.expect("permalinks have a task id");
+                find_task(&client, location.list_id, task_id)?
+            }
+            Some(TaskArg::Id(task_id)) => {
+                let location = self.location(context)?;
+                find_task(&client, location.list_id, task_id)?
+            }
+            None => {
+                if !context.allow_interaction {
+                    return Err(CloseTaskError::Unhandled(anyhow!(
+                        "no task given, and interaction isn't allowed to pick one (probably `-q` flag set)"
+                    )));
+                }
+                if atty::isnt(atty::Stream::Stdin) {
+                    return Err(reportable(
+                        "Picking a task needs a terminal. Give the task's id or permalink instead",
+                    ));
+                }
+                let location = self.location(context)?;
+                pick_task(&client, &location)?
+            }
+        };
+
+        Ok(CloseTaskJob { client, task })
+    }
+
+    /// The bookmarked location, or the default list
+    fn location(&self, context: &context::Context) -> Result<CheckvistLocation> {
+        let config = context.config.as_ref()?;
+        match &self.bookmark {
+            Some(name) => config
+                .bookmark(name)
+                .map(|bookmark| bookmark.location)
+                .ok_or_else(|| reportable(&format!("No bookmark named '{}' was found", name))),
+            None => Ok(CheckvistLocation {
+                list_id: config.list_id,
+                parent_task_id: None,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum TaskArg {
+    Id(u32),
+    Permalink(CheckvistLocation),
+}
+
+fn parse_task_arg(arg: &str) -> Result<TaskArg> {
+    if let Ok(task_id) = arg.parse() {
+        return Ok(TaskArg::Id(task_id));
+    }
+    match Bookmark::try_from(arg) {
+        Ok(Bookmark {
+            location:
+                location @ CheckvistLocation {
+                    parent_task_id: Some(_),
+                    ..
+                },
+            ..
+        }) => Ok(TaskArg::Permalink(location)),
+        Ok(_) => Err(reportable(&format!("'{}' is a list, not a task", arg))),
+        Err(_) => Err(reportable(&format!(
+            "'{}' isn't a task id or permalink",
+            arg
+        ))),
+    }
+}
+
+/// A task to close, with the list it's in
+#[derive(Debug)]
+struct TaskToClose {
+    list_id: u32,
+    id: u32,
+    content: String,
+    status: TaskStatus,
+}
+
+fn find_task(client: &CheckvistClient, list_id: u32, task_id: u32) -> Result<TaskToClose> {
+    let tasks = match client.get_task(list_id, task_id) {
+        Ok(tasks) => tasks,
+        Err(
+            CheckvistError::InvalidTaskError
+            | CheckvistError::InvalidListError
+            | CheckvistError::InvalidParentIdError,
+        ) => {
+            return Err(reportable(&format!(
+                "Couldn't find task {} in list {}",
+                task_id, list_id
+            )))
+        }
+        Err(err) => return Err(CloseTaskError::Unhandled(err.into())),
+    };
+    tasks
+        .into_iter()
+        .find(|task| task.id == Some(task_id))
+        .map(|task| TaskToClose {
+            list_id,
+            id: task_id,
+            content: task.content,
+            status: task.status,
+        })
+        .ok_or_else(|| {
+            reportable(&format!(
+                "Couldn't find task {} in list {}",
+                task_id, list_id
+            ))
+        })
+}
+
+fn pick_task(client: &CheckvistClient, location: &CheckvistLocation) -> Result<TaskToClose> {
+    let outline = ProgressIndicator::new(
+        '.',
+        Box::new(|| println!("Fetching tasks from Checkvist")),
+        250,
+    )
+    .run(|| show_tasks::outline_at(client, location))?;
+    let open_tasks = open_tasks(&outline.items);
+    if open_tasks.is_empty() {
+        return Err(reportable(&format!(
+            "There are no open tasks in {}",
+            outline.title
+        )));
+    }
+    ColourOutput::new(StreamKind::Stdout)
+        .append("Open tasks in ", Style::Normal)
+        .append(&outline.title, Style::ListName)
+        .println()?;
+    let task =
+        interaction::user_select_task(&open_tasks).ok_or(CloseTaskError::UserCancellation)?;
+
+    Ok(TaskToClose {
+        list_id: location.list_id,
+        id: task.id.expect("tasks from Checkvist have ids"),
+        content: task.content.clone(),
+        status: task.status,
+    })
+}
+
+/// Open tasks (excluding those beneath closed ones), depth first, with their depths
+fn open_tasks(items: &[OutlineItem]) -> Vec<(usize, &OutlineItem)> {
+    fn add_open<'a>(
+        items: &'a [OutlineItem],
+        depth: usize,
+        open: &mut Vec<(usize, &'a OutlineItem)>,
+    ) {
+        for item in items.iter().filter(|item| item.status == TaskStatus::Open) {
+            open.push((depth, item));
+            add_open(&item.children, depth + 1, open);
+        }
+    }
+    let mut open = Vec::new();
+    add_open(items, 0, &mut open);
+    open
+}
+
+// Validated data needed to close the task
+struct CloseTaskJob {
+    client: CheckvistClient,
+    task: TaskToClose,
+}
+
+impl CloseTaskJob {
+    fn run(self, context: context::Context) -> AnyhowResult<RunType> {
+        if self.task.status != TaskStatus::Open {
+            return Ok(RunType::Completed(
+                Outcome::new(format!("'{}' is already closed", self.task.content))
+                    .with_data(self.data(true)),
+            ));
+        }
+        if context.allow_interaction {
+            let msg = ColourOutput::new(StreamKind::Stdout)
+                .append("Closing task ", Style::Normal)
+                .append(&self.task.content, Style::TaskContent);
+            let user_msg = || msg.println().expect("Problem printing colour output");
+            ProgressIndicator::new('.', Box::new(user_msg), 250).run(|| self.close_task())?;
+        } else {
+            self.close_task()?;
+        }
+        Ok(RunType::Completed(
+            Outcome::new("Task closed").with_data(self.data(false)),
+        ))
+    }
+
+    fn data(&self, already_closed: bool) -> Value {
+        json!({
+            "task": {
+                "id": self.task.id,
+                "list_id": self.task.list_id,
+                "content": self.task.content,
+                "url": self.client.task_permalink(self.task.list_id, self.task.id),
+            },
+            "already_closed": already_closed,
+        })
+    }
+
+    fn close_task(&self) -> AnyhowResult<()> {
+        self.client
+            .close_task(self.task.list_id, self.task.id)
+            .map_err(|e| anyhow!(e))
+    }
+}
+
+#[derive(Debug)]
+enum CloseTaskError {
+    UserCancellation,
+    Unhandled(anyhow::Error),
+}
+
+fn reportable(msg: &str) -> CloseTaskError {
+    CloseTaskError::Unhandled(app::Error::Reportable(msg.into()).into())
+}
+
+impl From<&ConfigAbsentError> for CloseTaskError {
+    fn from(value: &ConfigAbsentError) -> Self {
+        match value {
+            ConfigAbsentError::UserCancellation => Self::UserCancellation,
+            ConfigAbsentError::InteractionDisallowed => {
+                Self::Unhandled(anyhow!("-q flag and no config file"))
+            }
+        }
+    }
+}
+
+impl From<anyhow::Error> for CloseTaskError {
+    fn from(value: anyhow::Error) -> Self {
+        Self::Unhandled(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn task_arg_can_be_id_or_permalink() {
+        assert_eq!(parse_task_arg("12").unwrap(), TaskArg::Id(12));
+        assert_eq!(
+            parse_task_arg("https://checkvist.com/checklists/1/tasks/2").unwrap(),
+            TaskArg::Permalink(CheckvistLocation {
+                list_id: 1,
+                parent_task_id: Some(2)
+            })
+        );
+        assert!(parse_task_arg("https://checkvist.com/checklists/1").is_err());
+        assert!(parse_task_arg("twelve").is_err());
+    }
+
+    #[test]
+    fn closed_tasks_and_their_children_cant_be_picked() {
+        let mut closed = OutlineItem::new("closed");
+        closed.status = TaskStatus::Closed;
+        closed.children = vec![OutlineItem::new("beneath closed")];
+        let mut open = OutlineItem::new("open");
+        open.children = vec![OutlineItem::new("beneath open")];
+        let items = vec![closed, open];
+
+        let picks: Vec<(usize, &str)> = open_tasks(&items)
+            .into_iter()
+            .map(|(depth, item)| (depth, item.content.as_str()))
+            .collect();
+
+        assert_eq!(picks, vec![(0, "open"), (1, "beneath open")]);
+    }
+}
@@ -1,7 +1,8 @@
 use anyhow::Result;
 use bpaf::{command, construct, parsers::ParseCommand, pure, Parser};
+use serde_json::json;
 
-use super::{Action, RunType};
+use super::{Action, Outcome, RunType};
 use crate::app::{action, cli::Command, context, creds};
 
 #[derive(Debug, Clone)]
@@ -19,13 +20,16 @@ impl LogOut {
 
 impl Action for LogOut {
     fn run(self, context: context::Context) -> Result<RunType> {
-        let msg = if context.api_token.is_some() {
+        let was_logged_in = context.api_token.is_some();
+        let msg = if was_logged_in {
             creds::delete_api_token(&context.keychain_service_name)?;
             "cvcap is now logged out"
         } else {
             "cvcap is already logged out"
         };
 
-        Ok(RunType::Completed(msg.into()))
+        Ok(RunType::Completed(
+            Outcome::new(msg).with_data(json!({ "was_logged_in": was_logged_in })),
+        ))
     }
 }
@@ -0,0 +1,284 @@
+//! Lists the user's Checkvist lists, marking the default list and any with bookmarks
+use std::cmp::Reverse;
+use std::str::FromStr;
+
+use anyhow::Result;
+use bpaf::{command, construct, long, parsers::ParseCommand, Parser};
+use cvapi::Checklist;
+use serde_json::{json, Value};
+
+use super::{Action, Outcome, RunType};
+use crate::app::{
+    cli::{Command, OutputFormat},
+    context::Context,
+    Config,
+};
+use crate::colour_output::{ColourOutput, StreamKind, Style};
+use crate::progress_indicator::ProgressIndicator;
+
+const DEFAULT_MARKER: char = '*';
+const BOOKMARK_MARKER: char = '+';
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListSort {
+    Name,
+    Id,
+    Tasks,
+    Updated,
+}
+
+impl FromStr for ListSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(Self::Name),
+            "id" => Ok(Self::Id),
+            "tasks" => Ok(Self::Tasks),
+            "updated" => Ok(Self::Updated),
+            other => Err(format!(
+                "Can't sort by '{}'. Use name, id, tasks or updated",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShowLists {
+    sort: ListSort,
+    reverse: bool,
+    filter: Option<String>,
+    archived: bool,
+}
+
+impl ShowLists {
+    pub fn command() -> ParseCommand<Command> {
+        let sort = long("sort")
+            .help(
+                "Sort by name (the default), id, tasks (most first) or updated (most recent first)",
+            )
+            .argument::<ListSort>("FIELD")
+            .fallback(ListSort::Name);
+        let reverse = long("reverse")
+            .short('r')
+            .help("Reverse the sort order")
+            .switch();
+        let filter = long("filter")
+            .short('f')
+            .help("Only show lists whose names contain TEXT (ignoring case)")
+            .argument::<String>("TEXT")
+            .optional();
+        let archived = long("archived")
+            .short('a')
+            .help("Include archived lists")
+            .switch();
+        let show_lists = construct!(ShowLists {
+            sort,
+            reverse,
+            filter,
+            archived
+        });
+        let lists = construct!(Command::ShowLists(show_lists))
+            .to_options()
+            .descr("Show your Checkvist lists, with their ids, task counts, and when they were last updated");
+        command("lists", lists).help("Shows your Checkvist lists")
+    }
+
+    /// The lists to show, in order, with whether each is archived
+    fn select(&self, lists: Vec<(Checklist, bool)>) -> Vec<(Checklist, bool)> {
+        let filter = self.filter.as_ref().map(|filter| filter.to_lowercase());
+        let mut lists: Vec<(Checklist, bool)> = lists
+            .into_iter()
+            .filter(|(list, _archived)| match filter {
+                Some(ref filter) => list.name.to_lowercase().contains(filter),
+                None => true,
+            })
+            .collect();
+        match self.sort {
+            ListSort::Name => lists.sort_by_key(|(list, _)| list.name.to_lowercase()),
+            ListSort::Id => lists.sort_by_key(|(list, _)| list.id),
+            ListSort::Tasks => lists.sort_by_key(|(list, _)| Reverse(list.task_count)),
+            ListSort::Updated => lists.sort_by(|(a, _), (b, _)| b.updated_at.cmp(&a.updated_at)),
+        }
+        if self.reverse {
+            lists.reverse();
+        }
+        lists
+    }
+}
+
+impl Action for ShowLists {
+    fn run(self, context: Context) -> Result<RunType> {
+        let client = context.api_client()?;
+        let fetch = || -> Result<Vec<(Checklist, bool)>> {
+            let mut lists: Vec<(Checklist, bool)> = client
+                .get_lists()?
+                .into_iter()
+                .map(|list| (list, false))
+                .collect();
+            if self.archived {
+                lists.extend(
+                    client
+                        .get_archived_lists()?
+                        .into_iter()
+                        .map(|list| (list, true)),
+                );
+            }
+            Ok(lists)
+        };
+        let lists = if context.allow_interaction {
+            ProgressIndicator::new(
+                '.',
+                Box::new(|| println!("Fetching lists from Checkvist")),
+                250,
+            )
+            .run(fetch)?
+        } else {
+            fetch()?
+        };
+
+        let lists = self.select(lists);
+        let config = context.config.as_ref().ok();
+        let list_data: Vec<Value> = lists
+            .iter()
+            .map(|(list, archived)| list_data(config, list, *archived))
+            .collect();
+        let data = json!({ "lists": list_data });
+        let message = format!(
+            "{} list(s). {} default list, {} has bookmarks",
+            lists.len(),
+            DEFAULT_MARKER,
+            BOOKMARK_MARKER
+        );
+        if context.output_format == OutputFormat::Json {
+            return Ok(RunType::Completed(Outcome::new(message).with_data(data)));
+        }
+        let mut output = ColourOutput::new(StreamKind::Stdout).append(
+            format!("   {:>9}  {:>5}  {:<25}  NAME\n", "ID", "TASKS", "UPDATED"),
+            Style::Normal,
+        );
+        for (list, archived) in &lists {
+            output = output
+                .append(
+                    format!(
+                        "{}{} {:>9}  {:>5}  {:<25}  ",
+                        marker(config, list.id, DEFAULT_MARKER, is_default),
+                        marker(config, list.id, BOOKMARK_MARKER, has_bookmark),
+                        list.id,
+                        list.task_count,
+                        list.updated_at
+                    ),
+                    Style::Normal,
+                )
+                .append(&list.name, Style::ListName);
+            if *archived {
+                output = output.append(" (archived)", Style::Normal);
+            }
+            output = output.append("\n", Style::Normal);
+        }
+        output.println()?;
+
+        Ok(RunType::Completed(Outcome::new(message).with_data(data)))
+    }
+}
+
+fn list_data(config: Option<&Config>, list: &Checklist, archived: bool) -> Value {
+    json!({
+        "id": list.id,
+        "name": list.name,
+        "task_count": list.task_count,
+        "updated_at": list.updated_at,
+        "archived": archived,
+        "default": config.is_some_and(|config| is_default(config, list.id)),
+        "bookmarked": config.is_some_and(|config| has_bookmark(config, list.id)),
+    })
+}
+
+fn is_default(config: &Config, list_id: u32) -> bool {
+    config.list_id == list_id
+}
+
+fn has_bookmark(config: &Config, list_id: u32) -> bool {
+    config.bookmarks.as_ref().is_some_and(|bookmarks| {
+        bookmarks
+            .iter()
+            .any(|bookmark| bookmark.location.list_id == list_id)
+    })
+}
+
+fn marker(
+    config: Option<&Config>,
+    list_id: u32,
+    marker: char,
+    applies: fn(&Config, u32) -> bool,
+) -> char {
+    match config {
+        Some(config) if applies(config, list_id) => marker,
+        _ => ' ',
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn list(id: u32, name: &str, task_count: u16, updated_at: &str) -> (Checklist, bool) {
+        (
+            Checklist {
+                id,
+                name: name.into(),
+                task_count,
+                updated_at: updated_at.into(),
+                ..Default::default()
+            },
+            false,
+        )
+    }
+
+    fn lists() -> Vec<(Checklist, bool)> {
+        vec![
+            list(1, "work", 3, "2023/03/01 10:00:00 +0000"),
+            list(2, "Home", 10, "2023/01/01 10:00:00 +0000"),
+            list(3, "Homework", 1, "2023/05/01 10:00:00 +0000"),
+        ]
+    }
+
+    fn ids(lists: Vec<(Checklist, bool)>) -> Vec<u32> {
+        lists.into_iter().map(|(list, _)| list.id).collect()
+    }
+
+    fn show_lists(sort: ListSort, reverse: bool, filter: Option<&str>) -> ShowLists {
+        ShowLists {
+            sort,
+            reverse,
+            filter: filter.map(String::from),
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn sorts_lists() {
+        assert_eq!(
+            ids(show_lists(ListSort::Name, false, None).select(lists())),
+            vec![2, 3, 1]
+        );
+        assert_eq!(
+            ids(show_lists(ListSort::Tasks, false, None).select(lists())),
+            vec![2, 1, 3]
+        );
+        assert_eq!(
+            ids(show_lists(ListSort::Updated, true, None).select(lists())),
+            vec![2, 1, 3]
+        );
+        assert!("size".parse::<ListSort>().is_err());
+    }
+
+    #[test]
+    fn filters_lists_ignoring_case() {
+        assert_eq!(
+            ids(show_lists(ListSort::Id, false, Some("home")).select(lists())),
+            vec![2, 3]
+        );
+    }
+}
@@ -1,15 +1,19 @@
 use anyhow::Result;
 use bpaf::{command, construct, parsers::ParseCommand, pure, Parser};
+use serde_json::{json, Value};
 
-use super::{Action, RunType};
-use crate::app::{cli::Command, context};
+use super::{Action, Outcome, RunType};
+use crate::app::{cli::Command, context, queue::Queue};
 
 #[derive(Debug, Clone)]
 pub struct ShowStatus;
 
 impl Action for ShowStatus {
     fn run(self, context: context::Context) -> Result<RunType> {
-        Ok(RunType::Completed(self.get_status(context)))
+        let data = self.status_data(&context);
+        Ok(RunType::Completed(
+            Outcome::new(self.get_status(context)).with_data(data),
+        ))
     }
 }
 
@@ -23,6 +27,16 @@ impl ShowStatus {
             .help("Check cvcap status: whether logged in and has default list and/or bookmark")
     }
 
+    fn status_data(&self, context: &context::Context) -> Value {
+        let config = context.config.as_ref().ok();
+        json!({
+            "logged_in": context.api_token.is_some(),
+            "default_list": config.map(|config| json!({ "id": config.list_id, "name": config.list_name })),
+            "bookmarks": config.and_then(|config| config.bookmarks.clone()).unwrap_or_default(),
+            "queued_captures": queued_captures(context),
+        })
+    }
+
     fn get_status(&self, context: context::Context) -> String {
         let mut status_text = String::from("\n    - logged in to Checkvist: \t");
         match &context.api_token {
@@ -62,7 +76,21 @@ impl ShowStatus {
             }
         }
         status_text.push('\n');
+        let queued = queued_captures(&context);
+        if queued > 0 {
+            status_text.push_str(&format!(
+                "    - queued captures: \t{} (`cvcap sync` sends them)\n",
+                queued
+            ));
+        }
 
         status_text
     }
 }
+
+/// How many captures are waiting to be sent to Checkvist
+fn queued_captures(context: &context::Context) -> usize {
+    Queue::<serde_json::Value>::read(&context.queue_file_path)
+        .map(|items| items.len())
+        .unwrap_or_default()
+}
@@ -0,0 +1,346 @@
+//! Shows the tasks at a location as an indented tree
+use anyhow::{anyhow, Result};
+use bpaf::{command, construct, long, parsers::ParseCommand, Parser};
+use cvapi::outline::{Outline, OutlineItem};
+use cvapi::task_path::TaskPath;
+use cvapi::{CheckvistClient, CheckvistError, CheckvistLocation, TaskStatus};
+use serde_json::{json, Value};
+
+use super::{Action, Outcome, RunType};
+use crate::app::{
+    cli::{Command, OutputFormat},
+    context::{ConfigAbsentError, Context},
+    Error as AppError,
+};
+use crate::colour_output::{ColourOutput, StreamKind, Style};
+use crate::progress_indicator::ProgressIndicator;
+
+const INDENT: &str = "  ";
+
+#[derive(Debug, Clone)]
+enum LocationSource {
+    Config,
+    Bookmark(String),
+    List(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ShowTasks {
+    location_source: LocationSource,
+    depth: Option<usize>,
+    open_only: bool,
+    show_ids: bool,
+}
+
+impl ShowTasks {
+    pub fn command() -> ParseCommand<Command> {
+        let bookmark = long("bookmark")
+            .short('b')
+            .help("Show the tasks at a bookmarked location")
+            .argument::<String>("BOOKMARK");
+        let from_bookmark = construct!(LocationSource::Bookmark(bookmark));
+        let list = long("list")
+            .short('l')
+            .help("Show the tasks in the named list, or beneath a task given as a path like \"Work > Inbox\"")
+            .argument::<String>("LIST");
+        let from_list = construct!(LocationSource::List(list));
+        let location_source =
+            construct!([from_bookmark, from_list]).fallback(LocationSource::Config);
+        let depth = long("depth")
+            .short('d')
+            .help("Show only this many levels of tasks")
+            .argument::<usize>("DEPTH")
+            .guard(|depth| *depth > 0, "DEPTH must be at least 1")
+            .optional();
+        let open_only = long("open")
+            .short('o')
+            .help("Hide closed and invalidated tasks (and their children)")
+            .switch();
+        let show_ids = long("ids").short('i').help("Show task ids").switch();
+        let show_tasks = construct!(ShowTasks {
+            location_source,
+            depth,
+            open_only,
+            show_ids
+        });
+        let show = construct!(Command::ShowTasks(show_tasks))
+            .to_options()
+            .descr("Show the tasks in the default list, a bookmarked location, or a named list");
+        command("show", show).help("Shows tasks from Checkvist")
+    }
+
+    /// The items to show, depth first, with their depths
+    fn visible<'a>(&self, items: &'a [OutlineItem]) -> Vec<(usize, &'a OutlineItem)> {
+        let mut visible = Vec::new();
+        self.add_visible(items, 0, &mut visible);
+        visible
+    }
+
+    fn add_visible<'a>(
+        &self,
+        items: &'a [OutlineItem],
+        depth: usize,
+        visible: &mut Vec<(usize, &'a OutlineItem)>,
+    ) {
+        for item in self.shown(items, depth) {
+            visible.push((depth, item));
+            self.add_visible(&item.children, depth + 1, visible);
+        }
+    }
+
+    /// Those of `items` (all at `depth`) to show
+    fn shown<'a>(&self, items: &'a [OutlineItem], depth: usize) -> Vec<&'a OutlineItem> {
+        if self.depth.is_some_and(|max_depth| depth >= max_depth) {
+            return Vec::new();
+        }
+        items
+            .iter()
+            .filter(|item| !self.open_only || item.status == TaskStatus::Open)
+            .collect()
+    }
+
+    /// The items to show, as a tree, for `--output json`
+    fn tree_data(&self, items: &[OutlineItem], depth: usize) -> Vec<Value> {
+        self.shown(items, depth)
+            .into_iter()
+            .map(|item| {
+                json!({
+                    "id": item.id,
+                    "content": item.content,
+                    "status": status_name(item.status),
+                    "tags": item.tags.keys().collect::<Vec<_>>(),
+                    "due": item.due.map(|due| due.format("%Y-%m-%d").to_string()),
+                    "children": self.tree_data(&item.children, depth + 1),
+                })
+            })
+            .collect()
+    }
+
+    /// The location to show, or None if the user cancelled setting up a config
+    fn location(&self, context: &Context) -> Result<Option<CheckvistLocation>> {
+        let config = match (&self.location_source, &context.config) {
+            (LocationSource::List(path), _) => {
+                let path = TaskPath::parse(path).map_err(reportable_path_error)?;
+                let location = context
+                    .api_client()?
+                    .resolve_path(&path)
+                    .map_err(reportable_path_error)?;
+                return Ok(Some(location));
+            }
+            (_, Ok(config)) => config,
+            (_, Err(ConfigAbsentError::UserCancellation)) => return Ok(None),
+            (_, Err(err)) => return Err(anyhow!(err.to_string())),
+        };
+        let location = match &self.location_source {
+            LocationSource::Bookmark(name) => {
+                config
+                    .bookmark(name)
+                    .ok_or_else(|| {
+                        AppError::Reportable(format!("No bookmark named '{}' was found", name))
+                    })?
+                    .location
+            }
+            _ => CheckvistLocation {
+                list_id: config.list_id,
+                parent_task_id: None,
+            },
+        };
+        Ok(Some(location))
+    }
+}
+
+impl Action for ShowTasks {
+    fn run(self, context: Context) -> Result<RunType> {
+        let Some(location) = self.location(&context)? else {
+            return Ok(RunType::Cancelled);
+        };
+        let client = context.api_client()?;
+        let fetch = || outline_at(&client, &location);
+        let outline = if context.allow_interaction {
+            ProgressIndicator::new(
+                '.',
+                Box::new(|| println!("Fetching tasks from Checkvist")),
+                250,
+            )
+            .run(fetch)?
+        } else {
+            fetch()?
+        };
+
+        let visible = self.visible(&outline.items);
+        let message = format!("{} of {} tasks shown", visible.len(), outline.len());
+        if context.output_format == OutputFormat::Json {
+            let data = json!({
+                "title": outline.title,
+                "tasks": self.tree_data(&outline.items, 0),
+            });
+            return Ok(RunType::Completed(Outcome::new(message).with_data(data)));
+        }
+        let mut output = ColourOutput::new(StreamKind::Stdout)
+            .append(&outline.title, Style::ListName)
+            .append("\n", Style::Normal);
+        if visible.is_empty() {
+            output = output.append("No tasks\n", Style::Normal);
+        }
+        for (depth, item) in &visible {
+            output = self.append_item(output, *depth, item);
+        }
+        output.println()?;
+
+        Ok(RunType::Completed(Outcome::new(message)))
+    }
+}
+
+impl ShowTasks {
+    fn append_item(&self, output: ColourOutput, depth: usize, item: &OutlineItem) -> ColourOutput {
+        let indent = INDENT.repeat(depth);
+        let (marker, content_style) = match item.status {
+            TaskStatus::Open => ('•', Style::TaskContent),
+            TaskStatus::Closed => ('✓', Style::Normal),
+            TaskStatus::Invalidated => ('✗', Style::Normal),
+        };
+        let mut output = output.append(format!("{}{} ", indent, marker), Style::Normal);
+        if self.show_ids {
+            if let Some(id) = item.id {
+                output = output.append(format!("[{}] ", id), Style::Link);
+            }
+        }
+        // continuation lines line up with the first
+        let content = item
+            .content
+            .replace('\n', &format!("\n{}{}", indent, INDENT));
+        output = output.append(content, content_style);
+        let mut details = String::new();
+        for tag in item.tags.keys() {
+            details.push_str(&format!(" #{}", tag));
+        }
+        if let Some(due) = item.due {
+            details.push_str(&format!(" ^{}", due.format("%Y-%m-%d")));
+        }
+        details.push('\n');
+
+        output.append(details, Style::Normal)
+    }
+}
+
+/// The tasks at `location`, titled with the list name (and parent task, if any)
+pub(super) fn outline_at(
+    client: &CheckvistClient,
+    location: &CheckvistLocation,
+) -> Result<Outline> {
+    let list = client.get_list(location.list_id)?;
+    let tasks = client.get_tasks(location.list_id)?;
+    let outline = match location.parent_task_id {
+        None => Outline::from_tasks(&list.name, &tasks),
+        Some(task_id) => {
+            let subtree = Outline::from_subtree(&list.name, &tasks, task_id)
+                .ok_or(CheckvistError::InvalidParentIdError)?;
+            let parent = subtree.items.into_iter().next().unwrap_or_default();
+            Outline {
+                title: format!("{} > {}", list.name, parent.content),
+                items: parent.children,
+            }
+        }
+    };
+    Ok(outline)
+}
+
+fn status_name(status: TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Open => "open",
+        TaskStatus::Closed => "closed",
+        TaskStatus::Invalidated => "invalidated",
+    }
+}
+
+/// Bad or ambiguous paths are the user's to fix, so report them as such
+fn reportable_path_error(err: CheckvistError) -> anyhow::Error {
+    match err {
+        CheckvistError::InvalidPathError { .. } | CheckvistError::AmbiguousPathError { .. } => {
+            AppError::Reportable(err.to_string()).into()
+        }
+        err => err.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn item(id: u32, status: TaskStatus, children: Vec<OutlineItem>) -> OutlineItem {
+        OutlineItem {
+            id: Some(id),
+            status,
+            children,
+            ..OutlineItem::new(&format!("task {}", id))
+        }
+    }
+
+    fn items() -> Vec<OutlineItem> {
+        vec![
+            item(
+                1,
+                TaskStatus::Open,
+                vec![
+                    item(
+                        2,
+                        TaskStatus::Closed,
+                        vec![item(3, TaskStatus::Open, vec![])],
+                    ),
+                    item(4, TaskStatus::Open, vec![]),
+                ],
+            ),
+            item(5, TaskStatus::Invalidated, vec![]),
+        ]
+    }
+
+    fn show_tasks(depth: Option<usize>, open_only: bool) -> ShowTasks {
+        ShowTasks {
+            location_source: LocationSource::Config,
+            depth,
+            open_only,
+            show_ids: false,
+        }
+    }
+
+    fn visible_ids(show_tasks: ShowTasks) -> Vec<(usize, u32)> {
+        show_tasks
+            .visible(&items())
+            .into_iter()
+            .map(|(depth, item)| (depth, item.id.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn shows_everything_by_default() {
+        assert_eq!(
+            visible_ids(show_tasks(None, false)),
+            vec![(0, 1), (1, 2), (2, 3), (1, 4), (0, 5)]
+        );
+    }
+
+    #[test]
+    fn depth_limits_levels_shown() {
+        assert_eq!(
+            visible_ids(show_tasks(Some(2), false)),
+            vec![(0, 1), (1, 2), (1, 4), (0, 5)]
+        );
+    }
+
+    #[test]
+    fn open_only_hides_closed_subtrees() {
+        assert_eq!(visible_ids(show_tasks(None, true)), vec![(0, 1), (1, 4)]);
+    }
+
+    #[test]
+    fn json_tree_shows_the_same_tasks() {
+        let tree = show_tasks(Some(2), true).tree_data(&items(), 0);
+
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0]["id"], 1);
+        let children = tree[0]["children"].as_array().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0]["id"], 4);
+        assert_eq!(children[0]["status"], "open");
+    }
+}
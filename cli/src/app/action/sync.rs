@@ -0,0 +1,67 @@
+//! Sends captures that were queued because Checkvist couldn't be reached
+use anyhow::Result;
+use bpaf::{command, construct, parsers::ParseCommand, pure, Parser};
+
+use super::add::{self, QueuedCapture};
+use super::{Action, Outcome, RunType};
+use crate::app::{cli::Command, context::Context, queue::Queue, Error as AppError};
+use crate::progress_indicator::ProgressIndicator;
+
+#[derive(Debug, Clone)]
+pub struct SyncQueue;
+
+impl SyncQueue {
+    pub fn command() -> ParseCommand<Command> {
+        let sync_action = pure(SyncQueue);
+        let sync = construct!(Command::SyncQueue(sync_action))
+            .to_options()
+            .descr("Send tasks that were queued because Checkvist couldn't be reached, in the order they were captured");
+        command("sync", sync).help("Sends queued tasks to Checkvist")
+    }
+}
+
+impl Action for SyncQueue {
+    fn run(self, context: Context) -> Result<RunType> {
+        let mut queue: Queue<QueuedCapture> = Queue::load(&context.queue_file_path)?;
+        if queue.is_empty() {
+            return Ok(RunType::Completed(Outcome::new("Nothing is queued")));
+        }
+        let queued = queue.len();
+        let report = if context.allow_interaction {
+            ProgressIndicator::new(
+                '.',
+                Box::new(|| println!("Sending {} queued capture(s) to Checkvist", queued)),
+                250,
+            )
+            .run(|| add::send_queued(&context, &mut queue))?
+        } else {
+            add::send_queued(&context, &mut queue)?
+        };
+        report.print_failures(&context)?;
+        if !queue.is_empty() {
+            let partly = match &report.partly_sent {
+                Some((_location, added)) => format!(", and {} task(s) of the next", added.len()),
+                None => String::new(),
+            };
+            return Err(AppError::Reportable(format!(
+                "Sent {} of {} queued capture(s){}, then couldn't reach Checkvist. {} capture(s) are still queued",
+                report.sent.len(),
+                queued,
+                partly,
+                queue.len()
+            ))
+            .into());
+        }
+
+        let mut message = format!("Sent {} queued capture(s)", report.sent.len());
+        if !report.failed.is_empty() {
+            message.push_str(&format!(
+                ". {} couldn't be added, and were removed from the queue",
+                report.failed.len()
+            ));
+        }
+        Ok(RunType::Completed(
+            Outcome::new(message).with_data(report.data()),
+        ))
+    }
+}
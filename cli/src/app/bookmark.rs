@@ -85,7 +85,7 @@ mod test {
         let cliptext = "https://checkvist.com/checklists/1/tasks/2".to_string();
         let Ok(mut clip_ctx) = ClipboardContext::new() else {
             eprintln!("Aborting test because this environment doesn't seem to have a clipboard we can access");
-            return ();
+            return;
         };
         clip_ctx.set_contents(cliptext).unwrap();
 
@@ -102,7 +102,7 @@ mod test {
         let cliptext = "".to_string();
         let Ok(mut clip_ctx) = ClipboardContext::new() else {
             eprintln!("Aborting test because this environment doesn't seem to have a clipboard we can access");
-            return ();
+            return;
         };
         clip_ctx.set_contents(cliptext).unwrap();
 
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use anyhow::{anyhow, Result};
 use bpaf::{
     batteries::get_usage, command, construct, long, positional, pure, OptionParser, Parser,
@@ -19,6 +21,7 @@ A minimal cli capture tool for Checkvist (https://checkvist.com)
 #[derive(Debug)]
 pub struct Cli {
     pub interactivity_level: InteractivityLevel,
+    pub output_format: OutputFormat,
     pub subcommand: Command,
 }
 
@@ -34,6 +37,31 @@ pub enum InteractivityLevel {
     Verbose,
 }
 
+/// Text - for people, in colour where the terminal allows
+/// Json - a single JSON document describing the result (or error), for
+///        scripts. Implies no interaction, as for Silent
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "Unknown output format '{}'. Use text or json",
+                other
+            )),
+        }
+    }
+}
+
 // how is this for typing compared to within the kernel? Actually this doesn't seem so bad at all.
 // rEally 
 #[derive(Debug, Clone)]
@@ -44,6 +72,11 @@ pub enum Command {
     ShowStatus(action::ShowStatus),
     LogOut(action::LogOut),
     AddBookmark(action::AddBookmark),
+    ManageBookmarks(action::ManageBookmarks),
+    CloseTask(action::CloseTask),
+    ShowLists(action::ShowLists),
+    ShowTasks(action::ShowTasks),
+    SyncQueue(action::SyncQueue),
     ShowUsage,
 }
 
@@ -51,7 +84,7 @@ impl Cli {
     fn parser() -> OptionParser<Cli> {
         let add_to_default_list = positional::<String>("TASK_CONTENT")
             .help("Quickly adds a task to the default list")
-            .map(|s| AddTask::from_string(s));
+            .map(AddTask::from_string);
         // convert to a Command::Add
         let add_to_default_list_parser = construct!(Command::Add(add_to_default_list));
 
@@ -64,6 +97,10 @@ impl Cli {
             .help("Reduces output, and requires no interaction")
             .req_flag(InteractivityLevel::Silent);
         let interactivity_level = construct!([verbose, quiet]).fallback(InteractivityLevel::Normal);
+        let output_format = long("output")
+            .help("Output format: text (the default) or json. json needs no interaction, as for -q")
+            .argument::<OutputFormat>("FORMAT")
+            .fallback(OutputFormat::Text);
 
         let show_usage = pure(Command::ShowUsage);
         let s = construct!(show_usage).to_options();
@@ -73,11 +110,21 @@ impl Cli {
         let logout_command = action::LogOut::command();
         let status_command = action::ShowStatus::command();
         let add_bookmark_command = action::AddBookmark::command();
+        let bookmarks_command = action::ManageBookmarks::command();
+        let done_command = action::CloseTask::command();
+        let lists_command = action::ShowLists::command();
+        let show_command = action::ShowTasks::command();
+        let sync_command = action::SyncQueue::command();
 
         let subcommand = construct!([
             add_task_command,
             add_bookmark_command,
+            bookmarks_command,
+            done_command,
+            lists_command,
+            show_command,
             status_command,
+            sync_command,
             logout_command,
             show_usage,
             add_to_default_list_parser,
@@ -86,11 +133,12 @@ impl Cli {
 
         construct!(Cli {
             interactivity_level,
+            output_format,
             subcommand,
         })
         .guard(
-            Self::reject_q_and_l_flags,
-            "`-q` and `-l` cannot be used together",
+            Self::reject_q_and_prompting_flags,
+            "`-q` and `--output json` cannot be used with `-l` or `-e`",
         )
         .to_options()
         .descr(BANNER)
@@ -101,12 +149,15 @@ impl Cli {
         Self::parser().run()
     }
 
-    fn reject_q_and_l_flags(cli: &Cli) -> bool {
+    /// Whether the user may be asked for input
+    pub fn allows_interaction(&self) -> bool {
+        self.interactivity_level != InteractivityLevel::Silent
+            && self.output_format != OutputFormat::Json
+    }
+
+    fn reject_q_and_prompting_flags(cli: &Cli) -> bool {
         match &cli.subcommand {
-            Command::Add(add_action) => {
-                !(add_action.prompts_user()
-                    && (cli.interactivity_level == InteractivityLevel::Silent))
-            }
+            Command::Add(add_action) => !add_action.prompts_user() || cli.allows_interaction(),
             _ => true,
         }
     }
@@ -120,6 +171,11 @@ impl Command {
             Command::ShowStatus(_) => Context::new(false),
             Command::LogOut(_) => Context::new(false),
             Command::AddBookmark(_) => Context::new(allow_interaction),
+            Command::ManageBookmarks(_) => Context::new(allow_interaction),
+            Command::CloseTask(_) => Context::new(allow_interaction),
+            Command::ShowLists(_) => Context::new(allow_interaction),
+            Command::ShowTasks(_) => Context::new(allow_interaction),
+            Command::SyncQueue(_) => Context::new(allow_interaction),
             Command::ShowUsage => Context::new(allow_interaction),
             // no arm for AddToDefaultList(_) which is always converted to an Add
            _ => Err(anyhow!("Attempt to context for an unrecognised command")),
@@ -134,7 +190,15 @@ impl action::Action for Command {
             Command::ShowStatus(cmd) => cmd.run(context),
             Command::LogOut(cmd) => cmd.run(context),
             Command::AddBookmark(cmd) => cmd.run(context),
-            Command::ShowUsage => Ok(action::RunType::Completed(get_usage(Cli::parser()))),
+            Command::ManageBookmarks(cmd) => cmd.run(context),
+            Command::CloseTask(cmd) => cmd.run(context),
+            Command::ShowLists(cmd) => cmd.run(context),
+            Command::ShowTasks(cmd) => cmd.run(context),
+            Command::SyncQueue(cmd) => cmd.run(context),
+            Command::ShowUsage => {
+                let usage = get_usage(Cli::parser());
+                Ok(action::RunType::Completed(action::Outcome::new(usage)))
+            }
             // no arm for AddToDefaultList(_) which is always converted to an Add
            _ => Err(anyhow!("Attempt to run an unrecognised command")),
         }
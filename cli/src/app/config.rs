@@ -13,9 +13,21 @@ pub struct Config {
     pub list_id: u32,
     #[serde(rename = "default_list_name")]
     pub list_name: String,
+    /// What `add -e` does with lines after the first (a note if not set)
+    // must precede bookmarks, as TOML values can't follow tables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor_extra_lines: Option<ExtraLines>,
     pub bookmarks: Option<Vec<Bookmark>>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtraLines {
+    #[default]
+    Note,
+    Children,
+}
+
 impl Config {
     pub fn from_file(path: &PathBuf) -> Result<Option<Self>> {
         if !path.is_file() {
@@ -85,6 +97,39 @@ impl Config {
         }
     }
 
+    /// Removes the bookmark with this name, returning it, or None if there's no such bookmark
+    pub fn remove_bookmark(&mut self, name: &str) -> Option<Bookmark> {
+        let index = self.find_bookmark_by_name(name)?;
+        let bookmarks = self.bookmarks.as_mut()?;
+        let removed = bookmarks.remove(index);
+        // so `status` reports no bookmarks, rather than an empty list of them
+        if bookmarks.is_empty() {
+            self.bookmarks = None;
+        }
+        Some(removed)
+    }
+
+    /// Gives the bookmark `name` the name `new_name`
+    /// Errors if there's no bookmark `name`, or there's already one called `new_name`
+    pub fn rename_bookmark(&mut self, name: &str, new_name: &str) -> Result<()> {
+        let index = self
+            .find_bookmark_by_name(name)
+            .ok_or_else(|| Error::Reportable(format!("No bookmark named '{}' was found", name)))?;
+        if self.find_bookmark_by_name(new_name).is_some() {
+            return Err(Error::Reportable(format!(
+                "There's already a bookmark named '{}'",
+                new_name
+            ))
+            .into());
+        }
+        let bookmarks = self
+            .bookmarks
+            .as_mut()
+            .expect("bookmarks must exist, as one was found");
+        bookmarks[index].name = new_name.to_string();
+        Ok(())
+    }
+
     /// return the index of the bookmark with this name, or None
     pub fn find_bookmark_by_name(&self, name: &str) -> Option<usize> {
         match self.bookmarks {
@@ -120,6 +165,7 @@ mod test {
         let config = Config {
             list_id: 1,
             list_name: "test_list".into(),
+            editor_extra_lines: None,
             bookmarks: None,
         };
         let t = TempDir::new().unwrap();
@@ -146,6 +192,7 @@ mod test {
         let source_config = Config {
             list_id: 1,
             list_name: "test_list".into(),
+            editor_extra_lines: Some(ExtraLines::Children),
             bookmarks: Some(vec![
                 Bookmark {
                     name: "bm1".into(),
@@ -192,14 +239,14 @@ mod test {
         let mut config = Config {
             list_id: 1,
             list_name: "list".into(),
+            editor_extra_lines: None,
             bookmarks: None,
         };
 
-        let result = config.add_bookmark(bookmark, false).unwrap();
+        config.add_bookmark(bookmark, false).unwrap();
         {
             let bookmarks = config.bookmarks.as_ref().unwrap();
 
-            assert_eq!(result, ());
             assert_eq!(bookmarks.len(), 1usize);
             assert_eq!(bookmarks[0].location.list_id, 1);
         }
@@ -237,6 +284,7 @@ mod test {
         let mut config = Config {
             list_id: 1,
             list_name: "list".into(),
+            editor_extra_lines: None,
             bookmarks: Some(bookmarks),
         };
 
@@ -265,11 +313,57 @@ mod test {
         let mut config = Config {
             list_id: 1,
             list_name: "list".into(),
+            editor_extra_lines: None,
             bookmarks: Some(bookmarks),
         };
 
-        let _result = config.add_bookmark(new, true).unwrap();
+        config.add_bookmark(new, true).unwrap();
 
         assert!(config.find_bookmark_by_location(&new_location).is_some());
     }
+
+    fn config_with_bookmarks(names: &[&str]) -> Config {
+        let bookmarks = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| Bookmark {
+                name: name.to_string(),
+                location: CheckvistLocation {
+                    list_id: index as u32 + 1,
+                    parent_task_id: None,
+                },
+            })
+            .collect();
+        Config {
+            list_id: 1,
+            list_name: "list".into(),
+            editor_extra_lines: None,
+            bookmarks: Some(bookmarks),
+        }
+    }
+
+    #[test]
+    fn remove_bookmark() {
+        let mut config = config_with_bookmarks(&["bm1", "bm2"]);
+
+        let removed = config.remove_bookmark("bm1").unwrap();
+        assert_eq!(removed.location.list_id, 1);
+        assert!(config.remove_bookmark("bm1").is_none());
+        assert_eq!(config.bookmarks.as_ref().unwrap().len(), 1);
+
+        config.remove_bookmark("bm2").unwrap();
+        assert!(config.bookmarks.is_none());
+    }
+
+    #[test]
+    fn rename_bookmark() {
+        let mut config = config_with_bookmarks(&["bm1", "bm2"]);
+
+        config.rename_bookmark("bm1", "renamed").unwrap();
+
+        assert!(config.bookmark("bm1").is_none());
+        assert_eq!(config.bookmark("renamed").unwrap().location.list_id, 1);
+        assert!(config.rename_bookmark("none", "bm3").is_err());
+        assert!(config.rename_bookmark("renamed", "bm2").is_err());
+    }
 }
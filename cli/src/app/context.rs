@@ -10,7 +10,7 @@ use dialoguer::{Confirm, Input, Password};
 use directories::ProjectDirs;
 use log::error;
 
-use super::{config::Config, creds, interaction};
+use super::{cli::OutputFormat, config::Config, creds, interaction};
 use crate::colour_output::{ColourOutput, StreamKind, Style};
 
 const KEYCHAIN_SERVICE_NAME: &str = "cvcap-api-token";
@@ -29,10 +29,14 @@ pub struct Context {
     /// config Result::Err indicates any reason for not having a valid config
     pub config: Result<Config, ConfigAbsentError>,
     pub config_file_path: PathBuf,
+    /// captures that couldn't be sent to Checkvist wait here (see queue.rs)
+    pub queue_file_path: PathBuf,
     // TODO: this may change after bpaf migration
     // this becase I haven't found a way to get access to higher level Command args from a
     // subcommand. see https://github.com/crispinb/cvcap/issues/26
     pub allow_interaction: bool,
+    /// actions print their own text output (eg. a tree of tasks) only for OutputFormat::Text
+    pub output_format: OutputFormat,
     // these are only needed for building a CheckvistClient,
     // but as that's tricky to make Cloneable (because of
     // the callback), we hold them rather than a prebuilt client
@@ -106,14 +110,21 @@ impl Context {
 
         Ok(Context {
             config,
+            queue_file_path: config_file_path.with_extension("queue.json"),
             config_file_path,
             api_token,
             checkvist_base_url: service_url,
             allow_interaction,
+            output_format: OutputFormat::Text,
             keychain_service_name,
         })
     }
 
+    pub fn with_output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
     /// Builds and returns CheckvistClient, or error if user is not logged in
     pub fn api_client(&self) -> Result<CheckvistClient> {
         let Some(ref api_token) = self.api_token else {
@@ -211,6 +222,7 @@ impl Context {
         let config = Config {
             list_id: selected_list.0,
             list_name: selected_list.1,
+            editor_extra_lines: None,
             bookmarks: None,
         };
         config.save(path)?;
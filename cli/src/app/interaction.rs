@@ -10,6 +10,7 @@ use crate::{
     config::Config,
     progress_indicator::ProgressIndicator,
 };
+use cvapi::outline::OutlineItem;
 use cvapi::CheckvistClient;
 
 /// Present the user with a dialogue to select one from their lists
@@ -62,18 +63,35 @@ fn select_list(lists: Vec<(u32, String)>) -> Option<(u32, String)> {
                     .to_owned()
             })
     }
-    .map(|list| {
+    .inspect(|list| {
         ColourOutput::new(StreamKind::Stdout)
             .append("You picked list '", Style::Normal)
             .append(&list.1, Style::ListName)
             .append("'", Style::Normal)
             .println()
             .expect("Problem printing colour output");
-
-        list
     })
 }
 
+/// Present the user with a dialogue to select one of `tasks`, each indented by its depth
+/// Returns the selected task, or None if the user cancels
+pub fn user_select_task<'a>(tasks: &[(usize, &'a OutlineItem)]) -> Option<&'a OutlineItem> {
+    println!("Use arrow keys (or j/k) to pick a task. Enter/Space to choose. ESC to cancel\n");
+    let items: Vec<String> = tasks
+        .iter()
+        .map(|(depth, task)| format!("{}{}", "  ".repeat(*depth), task.content.replace('\n', " ")))
+        .collect();
+    Select::new()
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        // as for select_list, log & continue with None
+        .map_err(|e| error!("{:?}", e))
+        .ok()
+        .flatten()
+        .map(|index| tasks[index].1)
+}
+
 pub fn offer_to_save_new_default_list(config: &Config, path: &PathBuf) -> Result<()> {
     // TODO: confirmation question should follow standard list name colour scheme
     // Don't think TermColour can do this directly; build into ColourOutput somehow
@@ -0,0 +1,283 @@
+//! Items kept, in order, in a JSON file until they can be dealt with.
+//! Used for captures that couldn't be sent to Checkvist (eg. while offline)
+//!
+//! A loaded queue holds a lock file (alongside the queue's) until it's dropped, so
+//! another cvcap can't change the queue while this one is working through it.
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::Error;
+
+/// How long to wait for another cvcap to finish with the queue
+const LOCK_WAIT: Duration = Duration::from_secs(30);
+/// A lock that hasn't been renewed for this long was left by a cvcap that didn't finish
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+pub struct Queue<T> {
+    path: PathBuf,
+    items: Vec<T>,
+    lock: Lock,
+}
+
+impl<T: Serialize + DeserializeOwned> Queue<T> {
+    /// The queue kept at `path`, which is empty if there's no file there.
+    /// Waits for any other cvcap using the queue to finish with it
+    pub fn load(path: &PathBuf) -> Result<Self> {
+        Self::load_waiting(path, LOCK_WAIT)
+    }
+
+    fn load_waiting(path: &PathBuf, wait: Duration) -> Result<Self> {
+        let lock = Lock::acquire(path, wait)?;
+        Ok(Queue {
+            path: path.clone(),
+            items: Self::read(path)?,
+            lock,
+        })
+    }
+
+    /// The items queued at `path`, without locking the queue, so only for looking at
+    pub fn read(path: &PathBuf) -> Result<Vec<T>> {
+        let items = if path.is_file() {
+            let json = fs::read_to_string(path)?;
+            serde_json::from_str(&json).with_context(|| {
+                Error::Reportable(format!(
+                    "The cvcap queue file \"{}\" is invalid and cannot be read",
+                    path.to_string_lossy()
+                ))
+            })?
+        } else {
+            Vec::new()
+        };
+
+        Ok(items)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Adds `item` to the end of the queue, and saves it
+    pub fn push(&mut self, item: T) -> Result<()> {
+        self.items.push(item);
+        self.save()
+    }
+
+    /// Removes and returns the first item, saving what's left.
+    /// Nothing is removed if the item can't be dealt with, so it can be tried again,
+    /// though `deal_with` may change it (eg. to the part that wasn't dealt with)
+    pub fn pop_with<R>(
+        &mut self,
+        deal_with: impl FnOnce(&mut T) -> Handled<R>,
+    ) -> Result<Option<R>> {
+        let Some(first) = self.items.first_mut() else {
+            return Ok(None);
+        };
+        match deal_with(first) {
+            Handled::Done(result) => {
+                self.items.remove(0);
+                self.save()?;
+                Ok(Some(result))
+            }
+            Handled::NotYet => {
+                self.save()?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Writes the queue to its file, or removes the file once the queue is empty
+    fn save(&self) -> Result<()> {
+        self.lock.renew()?;
+        if self.items.is_empty() {
+            if self.path.is_file() {
+                fs::remove_file(&self.path)?;
+            }
+            return Ok(());
+        }
+        let dir = self.path.parent().expect("Couldn't construct queue path");
+        if !dir.is_dir() {
+            fs::create_dir_all(dir)?;
+        }
+        // written alongside, then moved into place, so a queue is never half-written
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&self.items)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Whether a queued item was dealt with (successfully or not), with the result
+pub enum Handled<R> {
+    Done(R),
+    /// eg. Checkvist still can't be reached
+    NotYet,
+}
+
+/// A file that exists only while one cvcap has the queue, holding when it was last renewed
+#[derive(Debug)]
+struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    fn acquire(queue_path: &Path, wait: Duration) -> Result<Self> {
+        let path = queue_path.with_extension("lock");
+        let dir = path.parent().expect("Couldn't construct queue path");
+        if !dir.is_dir() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut waited = Duration::ZERO;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => {
+                    let lock = Lock { path };
+                    lock.renew()?;
+                    return Ok(lock);
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if Self::is_stale(&path) {
+                        fs::remove_file(&path).ok();
+                        continue;
+                    }
+                    if waited >= wait {
+                        return Err(Error::Reportable(format!(
+                            "Another cvcap is using the queue. If none is running, remove \"{}\"",
+                            path.to_string_lossy()
+                        ))
+                        .into());
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                    waited += Duration::from_millis(100);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Records that the lock is still in use
+    fn renew(&self) -> Result<()> {
+        fs::write(&self.path, now().as_secs().to_string())?;
+        Ok(())
+    }
+
+    fn is_stale(path: &Path) -> bool {
+        // an unreadable lock may be one still being written
+        let Ok(renewed) = fs::read_to_string(path) else {
+            return false;
+        };
+        match renewed.trim().parse::<u64>() {
+            Ok(renewed) => now().saturating_sub(Duration::from_secs(renewed)) > LOCK_STALE_AFTER,
+            Err(_) => false,
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use temp_dir::TempDir;
+
+    #[test]
+    fn items_are_kept_in_order_between_loads() {
+        let t = TempDir::new().unwrap();
+        let path = t.child("queue.json");
+        let mut queue = Queue::load(&path).unwrap();
+        queue.push("first".to_string()).unwrap();
+        queue.push("second".to_string()).unwrap();
+        drop(queue);
+
+        let mut queue: Queue<String> = Queue::load(&path).unwrap();
+        let first = queue.pop_with(|item| Handled::Done(item.clone())).unwrap();
+        let not_yet = queue.pop_with(|_| Handled::<String>::NotYet).unwrap();
+
+        assert_eq!(first, Some("first".to_string()));
+        assert_eq!(not_yet, None);
+        assert_eq!(Queue::<String>::read(&path).unwrap(), vec!["second"]);
+    }
+
+    #[test]
+    fn file_is_removed_once_queue_is_empty() {
+        let t = TempDir::new().unwrap();
+        let path = t.child("queue.json");
+        let mut queue = Queue::load(&path).unwrap();
+        queue.push(1).unwrap();
+        assert!(path.is_file());
+
+        queue.pop_with(|item| Handled::Done(*item)).unwrap();
+
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn queue_is_locked_until_dropped() {
+        let t = TempDir::new().unwrap();
+        let path = t.child("queue.json");
+        let mut queue = Queue::load(&path).unwrap();
+
+        let locked = Queue::<String>::load_waiting(&path, Duration::ZERO);
+        let waiting = {
+            let path = path.clone();
+            thread::spawn(move || Queue::<String>::load(&path).unwrap().items)
+        };
+        thread::sleep(Duration::from_millis(200));
+        queue.push("pushed while waiting".to_string()).unwrap();
+        drop(queue);
+
+        assert!(locked.is_err());
+        assert_eq!(waiting.join().unwrap(), vec!["pushed while waiting"]);
+        assert!(!path.with_extension("lock").exists());
+    }
+
+    #[test]
+    fn stale_lock_is_taken_over() {
+        let t = TempDir::new().unwrap();
+        let path = t.child("queue.json");
+        let lock_path = path.with_extension("lock");
+        fs::write(&lock_path, "0").unwrap();
+
+        let queue = Queue::<String>::load_waiting(&path, Duration::ZERO);
+
+        assert!(queue.is_ok());
+    }
+
+    #[test]
+    fn item_changed_but_not_dealt_with_is_kept_changed() {
+        let t = TempDir::new().unwrap();
+        let path = t.child("queue.json");
+        let mut queue = Queue::load(&path).unwrap();
+        queue.push("first and second".to_string()).unwrap();
+
+        let not_yet = queue
+            .pop_with(|item| {
+                *item = "second".to_string();
+                Handled::<()>::NotYet
+            })
+            .unwrap();
+
+        assert_eq!(not_yet, None);
+        assert_eq!(Queue::<String>::read(&path).unwrap(), vec!["second"]);
+    }
+}
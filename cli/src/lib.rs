@@ -4,4 +4,4 @@ pub mod colour_output;
 pub mod progress_indicator;
 
 pub mod app;
-pub use app::{bookmark, config, context, creds, Action, Cli, Command, Error, RunType};
+pub use app::{bookmark, config, context, creds, Action, Cli, Command, Error, Outcome, RunType};
@@ -2,11 +2,22 @@ use anyhow::Error;
 use cvcap::app::cli;
 use env_logger::Env;
 use log::{error, info};
+use serde_json::{json, Value};
 
 use cvapi::CheckvistError;
 use cvcap::colour_output::{ColourOutput, StreamKind, Style};
 use cvcap::{creds, Action, Cli, Error as AppError, RunType};
 
+/// How the result of running a command (or its error) is reported
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Report {
+    /// `-q`: nothing
+    Silent,
+    Text,
+    /// `--output json`: a single JSON document on stdout, even with `-q`
+    Json,
+}
+
 // Logging.
 // Convention: reserve trace and debug levels for libraries (eg. checkvist api)
 // Levels used in executable:
@@ -19,13 +30,23 @@ use cvcap::{creds, Action, Cli, Error as AppError, RunType};
 // 1 - any error generated by cvcap
 // 2 - arg parsing errors
 
+// JSON error codes (as "code" in `{"status": "error", "error": {"code": .., "message": ..}}`)
+// one per CheckvistError variant - see checkvist_error_code
+// error - any other expected error (eg. a bookmark that doesn't exist)
+// unexpected_error - anything else, most likely a bug
+
 fn main() {
     let cli = Cli::parse();
+    let allow_interaction = cli.allows_interaction();
+    let report = match (cli.output_format, &cli.interactivity_level) {
+        (cli::OutputFormat::Json, _) => Report::Json,
+        (_, cli::InteractivityLevel::Silent) => Report::Silent,
+        _ => Report::Text,
+    };
     let subcommand = cli.subcommand;
-    let allow_interaction = cli.interactivity_level != cli::InteractivityLevel::Silent;
     let context = match subcommand.new_context(allow_interaction) {
-        Ok(context) => context,
-        Err(e) => std::process::exit(handle_error(e, allow_interaction, "")),
+        Ok(context) => context.with_output_format(cli.output_format),
+        Err(e) => std::process::exit(handle_error(e, report, "")),
     };
 
     let log_level = if cli.interactivity_level == cli::InteractivityLevel::Verbose {
@@ -38,54 +59,93 @@ fn main() {
     match subcommand.run(context.clone()) {
         Err(err) => {
             error!("Fatal error. Cause: {:?}", err.root_cause());
-            std::process::exit(handle_error(
-                err,
-                allow_interaction,
-                &context.keychain_service_name,
-            ));
-        }
-        Ok(RunType::Completed(msg)) => {
-            if allow_interaction {
-                println!("{}", msg)
-            }
-        }
-        Ok(RunType::Cancelled) => {
-            if allow_interaction {
-                println!("Cancelled")
-            }
+            std::process::exit(handle_error(err, report, &context.keychain_service_name));
         }
+        Ok(RunType::Completed(outcome)) => match report {
+            Report::Silent => (),
+            Report::Text => println!("{}", outcome.message),
+            Report::Json => print_json(json!({
+                "status": "completed",
+                "message": outcome.message.trim(),
+                "data": outcome.data,
+            })),
+        },
+        Ok(RunType::Cancelled) => match report {
+            Report::Silent => (),
+            Report::Text => println!("Cancelled"),
+            Report::Json => print_json(json!({ "status": "cancelled" })),
+        },
     }
     std::process::exit(0);
 }
 
-fn handle_error(err: Error, allow_interaction: bool, keychain_service_name: &str) -> i32 {
+fn handle_error(err: Error, report: Report, keychain_service_name: &str) -> i32 {
     // CheckVistError
     // Hacky: downcast the concrete error types
-    // requiring specific handling
-    match err.root_cause().downcast_ref::<CheckvistError>() {
-        Some(CheckvistError::InvalidListError) => eprint_error("Couldn't find or access the list you are trying to add to.\nAre you using an invalid bookmark?", allow_interaction),
-        Some(CheckvistError::InvalidParentIdError) => eprint_error("Couldn't find the task you are trying to add a child task to.\nAre you using an valid bookmark?", allow_interaction),
-        Some(CheckvistError::TokenRefreshFailedError) => { eprint_logged_out(allow_interaction);
+    // requiring specific handling. Not the root cause: some wrap another error
+    match err.chain().find_map(|cause| cause.downcast_ref::<CheckvistError>()) {
+        Some(CheckvistError::InvalidListError) => eprint_error("invalid_list", "Couldn't find or access the list you are trying to add to.\nAre you using an invalid bookmark?", report),
+        Some(CheckvistError::InvalidParentIdError) => eprint_error("invalid_parent_task", "Couldn't find the task you are trying to add a child task to.\nAre you using an valid bookmark?", report),
+        Some(CheckvistError::TokenRefreshFailedError) => { eprint_logged_out(report);
             match creds::delete_api_token(keychain_service_name) {
                 Err(err) => error!("Something went wrong deleting invalid api token: {}", err),
                 _ => info!("Expired api token was deleted"),
             }
         }
+        Some(cv_err) => eprint_error(checkvist_error_code(cv_err), &format!("{:#}", err), report),
         _possible_app_error => match err.downcast_ref::<AppError>() {
-            Some(AppError::Reportable(msg)) => eprint_error(msg, allow_interaction),
+            Some(AppError::Reportable(msg)) => eprint_error("error", msg, report),
             _all_other_errors => {
-                eprint_unexpected_error(err, allow_interaction);
+                eprint_unexpected_error(err, report);
             }
         },
     }
     1
 }
 
+/// A stable code for each kind of Checkvist error, so scripts needn't parse messages.
+/// Documented in the README: don't change an existing code
+fn checkvist_error_code(err: &CheckvistError) -> &'static str {
+    match err {
+        CheckvistError::InvalidListError => "invalid_list",
+        CheckvistError::InvalidParentIdError => "invalid_parent_task",
+        CheckvistError::InvalidTaskError => "invalid_task",
+        CheckvistError::TokenRefreshFailedError => "logged_out",
+        CheckvistError::NetworkError(_) => "network_error",
+        CheckvistError::RateLimitedError { .. } => "rate_limited",
+        CheckvistError::PermissionDeniedError { .. } => "permission_denied",
+        CheckvistError::UnknownUserError { .. } => "unknown_user",
+        CheckvistError::InvalidDueDateError { .. } => "invalid_due_date",
+        CheckvistError::InvalidQueryError { .. } => "invalid_query",
+        CheckvistError::InvalidPathError { .. } => "invalid_path",
+        CheckvistError::AmbiguousPathError { .. } => "ambiguous_path",
+        CheckvistError::ImportParseError { .. } => "invalid_import",
+        CheckvistError::MoveIntoSubtreeError { .. } => "invalid_move",
+        CheckvistError::DatabaseError(_) => "mirror_error",
+        CheckvistError::IoError(_) => "io_error",
+        CheckvistError::UnknownError { .. } => "checkvist_error",
+    }
+}
+
+fn print_json(document: Value) {
+    println!("{}", document);
+}
+
+/// Errors are reported on stdout in json, so scripts only need read one stream
+fn print_json_error(code: &str, message: &str) {
+    print_json(json!({
+        "status": "error",
+        "error": { "code": code, "message": message },
+    }));
+}
+
 #[inline(always)]
-fn eprint_error(message: &str, allow_interaction: bool) {
-    if !allow_interaction {
-        return;
-    };
+fn eprint_error(code: &str, message: &str, report: Report) {
+    match report {
+        Report::Silent => return,
+        Report::Json => return print_json_error(code, message),
+        Report::Text => (),
+    }
 
     let out = ColourOutput::new(StreamKind::Stderr);
     out.append("\nError: ", Style::Error)
@@ -95,9 +155,14 @@ fn eprint_error(message: &str, allow_interaction: bool) {
 }
 
 #[inline(always)]
-fn eprint_logged_out(allow_interaction: bool) {
-    if !allow_interaction {
-        return;
+fn eprint_logged_out(report: Report) {
+    match report {
+        Report::Silent => return,
+        Report::Json => return print_json_error(
+            "logged_out",
+            "You have been logged out of the Checkvist API. Please run cvcap again to log back in",
+        ),
+        Report::Text => (),
     }
 
     eprintln!(
@@ -108,9 +173,11 @@ fn eprint_logged_out(allow_interaction: bool) {
 }
 
 #[inline(always)]
-fn eprint_unexpected_error(err: Error, allow_interaction: bool) {
-    if !allow_interaction {
-        return;
+fn eprint_unexpected_error(err: Error, report: Report) {
+    match report {
+        Report::Silent => return,
+        Report::Json => return print_json_error("unexpected_error", &format!("{:#}", err)),
+        Report::Text => (),
     }
 
     let err_msg: String = format!(
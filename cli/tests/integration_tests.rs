@@ -25,6 +25,8 @@ use cvcap::{
 /// ## Untested interactive features
 /// * add-bookmark:
 ///     * y/n prompt if bookmark already exists
+/// * done:
+///     * picking a task when none is given
 
 #[tokio::test]
 async fn run_without_args_shows_help() {
@@ -84,6 +86,195 @@ async fn status_reports_bookmark_count() {
         .success();
 }
 
+#[tokio::test]
+async fn json_output_is_one_document() {
+    let (mut cmd, _test_config) = configure_command(None, true, false).await;
+
+    let output = cmd.args(["--output", "json", "status"]).output().unwrap();
+
+    assert!(output.status.success());
+    let document: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be a json document");
+    assert_eq!(document["status"], "completed");
+    assert_eq!(document["data"]["logged_in"], false);
+}
+
+#[tokio::test]
+async fn json_output_has_added_task_url() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    let output = cmd
+        .args(["--output", "json", "add", "test task"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let url = document["data"]["tasks"][0]["url"].as_str().unwrap();
+    assert!(url.contains("/checklists/1/tasks/"));
+}
+
+#[tokio::test]
+async fn json_output_has_error_codes() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    let output = cmd
+        .args(["--output", "json", "add", "-b", "nonexistent", "test task"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["status"], "error");
+    assert_eq!(document["error"]["code"], "error");
+}
+
+#[tokio::test]
+async fn json_output_has_a_code_for_each_checkvist_error() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    let output = cmd
+        .env(CUSTOM_SERVICE_URL_KEY, "http://127.0.0.1:1")
+        .args(["--output", "json", "show"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["error"]["code"], "network_error");
+}
+
+#[tokio::test]
+async fn sync_with_nothing_queued_succeeds() {
+    let (mut cmd, _test_config) = configure_command(None, true, false).await;
+
+    cmd.args(["--output", "json", "sync"])
+        .assert()
+        .stdout(predicate::str::contains("Nothing is queued"))
+        .success();
+}
+
+#[tokio::test]
+async fn bookmarks_can_be_renamed_and_removed() {
+    let (mut cmd, test_config) = configure_command(None, true, false).await;
+    let config_path = test_config._temp_dir.child("temp.toml");
+
+    cmd.args([
+        "--output",
+        "json",
+        "bookmarks",
+        "rename",
+        "list1_bookmark",
+        "inbox",
+    ])
+    .assert()
+    .stdout(predicate::str::contains(r#""name":"inbox""#))
+    .success();
+    Command::cargo_bin("cvcap")
+        .unwrap()
+        .env(CUSTOM_SERVICE_URL_KEY, test_config.mock_server.uri())
+        .env(CUSTOM_CONFIG_FILE_PATH_ENV_KEY, &config_path)
+        .env(
+            context::CUSTOM_SERVICE_NAME_ENV_KEY,
+            &test_config.keychain_service_name,
+        )
+        .args(["-q", "bookmarks", "remove", "task1_bookmark"])
+        .assert()
+        .success();
+
+    let config = Config::from_file(&config_path).unwrap().unwrap();
+    let names: Vec<String> = config
+        .bookmarks
+        .unwrap()
+        .into_iter()
+        .map(|bookmark| bookmark.name)
+        .collect();
+    assert_eq!(names, vec!["inbox"]);
+}
+
+#[tokio::test]
+async fn renaming_bookmark_to_existing_name_fails() {
+    let (mut cmd, _test_config) = configure_command(None, true, false).await;
+
+    cmd.args([
+        "--output",
+        "json",
+        "bookmarks",
+        "rename",
+        "list1_bookmark",
+        "task1_bookmark",
+    ])
+    .assert()
+    .stdout(predicate::str::contains(
+        "already a bookmark named 'task1_bookmark'",
+    ))
+    .failure();
+}
+
+// the mock server can't find any task, so only the list bookmark is valid
+#[tokio::test]
+async fn bookmarks_validate_reports_invalid_bookmarks() {
+    let (mut cmd, test_config) = configure_command(None, true, true).await;
+    let config_path = test_config._temp_dir.child("temp.toml");
+
+    cmd.args(["--output", "json", "bookmarks", "validate"])
+        .assert()
+        .stdout(predicate::str::contains("1 of 2 bookmark(s) are invalid"))
+        .stdout(predicate::str::contains(r#""name":"task1_bookmark""#))
+        .stdout(predicate::str::contains(r#""pruned":false"#))
+        .success();
+
+    let config = Config::from_file(&config_path).unwrap().unwrap();
+    assert_eq!(config.bookmarks.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn bookmarks_validate_prune_removes_invalid_bookmarks() {
+    let (mut cmd, test_config) = configure_command(None, true, true).await;
+    let config_path = test_config._temp_dir.child("temp.toml");
+
+    cmd.args(["-q", "bookmarks", "validate", "--prune"])
+        .assert()
+        .success();
+
+    let config = Config::from_file(&config_path).unwrap().unwrap();
+    let names: Vec<String> = config
+        .bookmarks
+        .unwrap()
+        .into_iter()
+        .map(|bookmark| bookmark.name)
+        .collect();
+    assert_eq!(names, vec!["list1_bookmark"]);
+}
+
+#[tokio::test]
+async fn add_queues_task_when_checkvist_cant_be_reached() {
+    let (mut cmd, test_config) = configure_command(None, true, true).await;
+
+    cmd.env(CUSTOM_SERVICE_URL_KEY, "http://127.0.0.1:1")
+        .args(["add", "offline task"])
+        .assert()
+        .stdout(predicate::str::contains("the task was queued"))
+        .success();
+    let queue_path = test_config._temp_dir.child("temp.queue.json");
+    let queue = std::fs::read_to_string(queue_path).unwrap();
+    assert!(queue.contains("offline task"));
+}
+
+#[tokio::test]
+async fn quiet_print_fails_when_task_is_queued() {
+    let (mut cmd, test_config) = configure_command(None, true, true).await;
+
+    cmd.env(CUSTOM_SERVICE_URL_KEY, "http://127.0.0.1:1")
+        .args(["-q", "add", "--print", "url", "offline task"])
+        .assert()
+        .stdout(predicate::str::is_empty())
+        .failure();
+    let queue_path = test_config._temp_dir.child("temp.queue.json");
+    let queue = std::fs::read_to_string(queue_path).unwrap();
+    assert!(queue.contains("offline task"));
+}
+
 // FIX: see https://github.com/crispinb/cvcap/issues/29
 #[tokio::test]
 async fn logout_subcommand_when_not_logged_in_succeeds_with_message() {
@@ -127,6 +318,84 @@ async fn add_task_from_stdin() {
         .success();
 }
 
+#[tokio::test]
+async fn add_outline_from_stdin() {
+    let (mut cmd, test_config) = configure_command(None, true, true).await;
+    // one new task for each item in the outline
+    Mock::given(method("POST"))
+        .and(path("/checklists/1/import.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![task(), task(), task()]))
+        .with_priority(1)
+        .mount(&test_config.mock_server)
+        .await;
+
+    cmd.arg("add")
+        .arg("-s")
+        .arg("-o")
+        .pipe_stdin("tests/data/outline.txt")
+        .unwrap()
+        .assert()
+        .stdout(predicate::str::contains("3 tasks added"))
+        .success();
+}
+
+#[tokio::test]
+async fn add_outline_sends_smart_syntax_as_written() {
+    let (mut cmd, test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["add", "-s", "-o"])
+        .write_stdin("Fix #42")
+        .assert()
+        .success();
+
+    let bodies = posted_bodies(&test_config.mock_server).await;
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies[0]["content"], "Fix #42");
+    assert!(bodies[0].get("tags").is_none());
+}
+
+#[tokio::test]
+async fn add_outline_with_smart_parses_smart_syntax() {
+    let (mut cmd, test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["add", "-s", "-o", "--smart"])
+        .write_stdin("Fix ^2023-05-01")
+        .assert()
+        .success();
+
+    let bodies = posted_bodies(&test_config.mock_server).await;
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies[0]["import_content"], "Fix ^2023-05-01\n");
+}
+
+#[tokio::test]
+async fn quiet_add_prints_only_url() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["-q", "add", "--print", "url", "test task"])
+        .assert()
+        .stdout(predicate::str::ends_with("/checklists/1/tasks/1\n"))
+        .stdout(predicate::str::contains("Task added").not())
+        .success();
+}
+
+#[tokio::test]
+async fn add_each_line_dry_run_adds_nothing() {
+    let (mut cmd, test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["add", "-s", "--each-line", "--dry-run"])
+        .pipe_stdin("tests/data/lines.txt")
+        .unwrap()
+        .assert()
+        .stdout(predicate::str::contains("Would add 2 tasks"))
+        .stdout(predicate::str::contains("second task"))
+        .success();
+    let requests = test_config.mock_server.received_requests().await.unwrap();
+    assert!(requests
+        .iter()
+        .all(|request| request.method != wiremock::http::Method::Post));
+}
+
 #[tokio::test]
 async fn add_task_with_list_bookmark() {
     let (mut cmd, _test_config) = configure_command(None, true, true).await;
@@ -173,7 +442,7 @@ async fn add_bookmark() {
     let cliptext = "https://checkvist.com/checklists/3";
     let Ok(mut clip_ctx) = ClipboardContext::new() else {
             eprintln!("Aborting test because this environment doesn't seem to have a clipboard we can access");
-            return ();
+            return;
         };
     clip_ctx.set_contents(cliptext.into()).unwrap();
 
@@ -192,9 +461,9 @@ async fn add_bookmark_with_invalid_list_id_fails() {
     let cliptext = format!("https://checkvist.com/checklists/{}", list_id);
     let Ok(mut clip_ctx) = ClipboardContext::new() else {
             eprintln!("Aborting test because this environment doesn't seem to have a clipboard we can access");
-            return ();
+            return;
         };
-    clip_ctx.set_contents(cliptext.into()).unwrap();
+    clip_ctx.set_contents(cliptext).unwrap();
 
     cmd.arg("add-bookmark")
         .arg("test_bookmark")
@@ -214,9 +483,9 @@ async fn add_bookmark_with_invalid_parent_task_id_fails() {
     );
     let Ok(mut clip_ctx) = ClipboardContext::new() else {
             eprintln!("Aborting test because this environment doesn't seem to have a clipboard we can access");
-            return ();
+            return;
         };
-    clip_ctx.set_contents(cliptext.into()).unwrap();
+    clip_ctx.set_contents(cliptext).unwrap();
 
     cmd.arg("add-bookmark")
         .arg("test_bookmark")
@@ -231,7 +500,7 @@ async fn add_bookmark_with_q_succeeds_silently() {
     let cliptext = "https://checkvist.com/checklists/3";
     let Ok(mut clip_ctx) = ClipboardContext::new() else {
             eprintln!("Aborting test because this environment doesn't seem to have a clipboard we can access");
-            return ();
+            return;
         };
     clip_ctx.set_contents(cliptext.into()).unwrap();
 
@@ -280,9 +549,9 @@ async fn add_bookmark_with_invalid_list_id_q_fails_silently() {
     let cliptext = format!("https://checkvist.com/checklists/{}", list_id);
     let Ok(mut clip_ctx) = ClipboardContext::new() else {
             eprintln!("Aborting test because this environment doesn't seem to have a clipboard we can access");
-            return ();
+            return;
         };
-    clip_ctx.set_contents(cliptext.into()).unwrap();
+    clip_ctx.set_contents(cliptext).unwrap();
 
     cmd.arg("add-bookmark")
         .arg("test_bookmark")
@@ -301,7 +570,7 @@ async fn add_duplicate_bookmark_q_fails_silently() {
     let cliptext = "https://checkvist.com/checklists/1";
     let Ok(mut clip_ctx) = ClipboardContext::new() else {
             eprintln!("Aborting test because this environment doesn't seem to have a clipboard we can access");
-            return ();
+            return;
         };
     clip_ctx.set_contents(cliptext.into()).unwrap();
 
@@ -313,6 +582,81 @@ async fn add_duplicate_bookmark_q_fails_silently() {
         .failure();
 }
 
+#[tokio::test]
+async fn lists_marks_default_and_bookmarked_lists() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    cmd.arg("lists")
+        .assert()
+        .stdout(
+            predicate::str::is_match(r"\*\+\s+1\s+1\s+.*Test List")
+                .expect("bad regex")
+                .count(1),
+        )
+        .success();
+}
+
+#[tokio::test]
+async fn lists_filter_excludes_unmatched_lists() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["lists", "--filter", "nonexistent"])
+        .assert()
+        .stdout(predicate::str::contains("Test List").not())
+        .success();
+}
+
+#[tokio::test]
+async fn show_displays_default_list_tasks() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    cmd.arg("show")
+        .assert()
+        .stdout(predicate::str::contains("Test List").count(1))
+        .stdout(predicate::str::contains("some text").count(1))
+        .success();
+}
+
+#[tokio::test]
+async fn show_with_nonexistent_bookmark_fails() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["show", "-b", "nonexistent"])
+        .assert()
+        .stderr(predicate::str::contains("No bookmark named 'nonexistent'"))
+        .failure();
+}
+
+#[tokio::test]
+async fn done_reports_task_not_found() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["done", "1"])
+        .assert()
+        .stderr(predicate::str::contains("Couldn't find task 1 in list 1"))
+        .failure();
+}
+
+#[tokio::test]
+async fn done_rejects_list_permalink() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["done", "https://checkvist.com/checklists/1"])
+        .assert()
+        .stderr(predicate::str::contains("is a list, not a task"))
+        .failure();
+}
+
+#[tokio::test]
+async fn done_without_task_q_fails_silently() {
+    let (mut cmd, _test_config) = configure_command(None, true, true).await;
+
+    cmd.args(["done", "-q"])
+        .assert()
+        .stderr(predicate::str::is_empty())
+        .failure();
+}
+
 #[tokio::test]
 async fn default_add_and_options_conflict() {
     args_should_conflict(vec!["a task", "-l"]).await;
@@ -323,6 +667,31 @@ async fn s_and_c_flags_conflict() {
     args_should_conflict(vec!["add", "-c", "-s"]).await;
 }
 
+#[tokio::test]
+async fn json_output_conflicts_with_l_flag() {
+    args_should_conflict(vec!["--output", "json", "add", "-l", "test task"]).await;
+}
+
+#[tokio::test]
+async fn separator_needs_each_line() {
+    args_should_conflict(vec!["add", "-s", "--separator", ";"]).await;
+}
+
+#[tokio::test]
+async fn separator_cant_be_empty() {
+    let (mut cmd, _test_config) = configure_command(None, false, false).await;
+
+    cmd.args(["add", "-s", "--each-line", "--separator", ""])
+        .assert()
+        .stderr(predicate::str::contains("can't be empty"))
+        .failure();
+}
+
+#[tokio::test]
+async fn e_and_q_flags_conflict() {
+    args_should_conflict(vec!["add", "-e", "-q"]).await;
+}
+
 async fn args_should_conflict(args: Vec<&str>) {
     let (mut cmd, _test_config) = configure_command(None, false, false).await;
     cmd.args(args).assert().failure();
@@ -373,16 +742,19 @@ enum HttpResponse {
 /// - config file if 'config_file_exists', with:
 ///     - 1 each list and task bookmark with list/parent_task_id 1
 /// - mock server responding thusly:
-///      Auth successs/failure is determined by `response`.
-///      Then success/failure for specific responses is determined by the list/task_id
-///      args sent to CheckvistClient methods.
-///     Successes:
-///      - GET request for list ids 1-9
-///      - GET request for tasks from list 1-9
-///      Failures:
-///      - GET request 403 invalid list for any other list
-///      - GET request 403 invalid parent task id for any other task
-///      - POST to add a task. Returns response & payload from args
+///   Auth successs/failure is determined by `response`.
+///   Then success/failure for specific responses is determined by the list/task_id
+///   args sent to CheckvistClient methods.
+///   Successes:
+///     - GET request for all lists (just list 1)
+///     - GET request for list ids 1-9
+///     - GET request for tasks from list 1-9
+///     - POST to import tasks to list 1-9. Returns the one task
+///
+///   Failures:
+///     - GET request 403 invalid list for any other list
+///     - GET request 403 invalid parent task id for any other task
+///     - POST to add a task. Returns response & payload from args
 async fn configure_command(
     response: Option<HttpResponse>,
     config_file_exists: bool,
@@ -408,7 +780,7 @@ async fn configure_command(
     let mock_server = mock_server(response).await;
 
     let mut cmd = Command::cargo_bin("cvcap").unwrap();
-    cmd.env(CUSTOM_SERVICE_URL_KEY, &mock_server.uri())
+    cmd.env(CUSTOM_SERVICE_URL_KEY, mock_server.uri())
         .env(CUSTOM_CONFIG_FILE_PATH_ENV_KEY, &config_path)
         .env(context::CUSTOM_SERVICE_NAME_ENV_KEY, &keychain_service_name);
 
@@ -441,6 +813,12 @@ async fn mock_server(response: Option<HttpResponse>) -> MockServer {
     // wiremock docs don't make clear how matchers interact
     // It seems exact path matches beat regexes, and that
     // order only matters for an exact clash (first wins)
+    Mock::given(method("GET"))
+        .and(path("/checklists.json"))
+        .respond_with(default_response.clone().set_body_json(vec![&list]))
+        .mount(&mock_server)
+        .await;
+
     Mock::given(method("GET"))
         .and(path_regex(r#"/checklists/[1-9].json"#))
         .respond_with(default_response.clone().set_body_json(list))
@@ -460,8 +838,14 @@ async fn mock_server(response: Option<HttpResponse>) -> MockServer {
         .mount(&mock_server)
         .await;
 
+    Mock::given(method("POST"))
+        .and(path_regex("/checklists/[1-9]/import.json"))
+        .respond_with(default_response.clone().set_body_json(&tasks))
+        .mount(&mock_server)
+        .await;
+
     Mock::given(method("GET"))
-        .and(path("/checklists/[1-9]/tasks.json"))
+        .and(path_regex("/checklists/[1-9]/tasks.json"))
         .respond_with(default_response.clone().set_body_json(&tasks))
         .mount(&mock_server)
         .await;
@@ -477,12 +861,25 @@ async fn mock_server(response: Option<HttpResponse>) -> MockServer {
     mock_server
 }
 
+/// The JSON bodies of the POST requests the mock server received, in order
+async fn posted_bodies(mock_server: &MockServer) -> Vec<serde_json::Value> {
+    mock_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.method == wiremock::http::Method::Post)
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect()
+}
+
 fn list() -> Checklist {
     Checklist {
         id: 1,
         name: "Test List".into(),
         updated_at: "".into(),
         task_count: 1,
+        ..Default::default()
     }
 }
 
@@ -492,6 +889,7 @@ fn task() -> Task {
         position: 1,
         content: "some text".into(),
         parent_id: Some(2),
+        ..Default::default()
     }
 }
 
@@ -507,6 +905,7 @@ fn config() -> Config {
     Config {
         list_id: 1,
         list_name: "Test List".into(),
+        editor_extra_lines: None,
         bookmarks: Some(vec![
             Bookmark {
                 name: "list1_bookmark".into(),
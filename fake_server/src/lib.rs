@@ -0,0 +1,556 @@
+//! A fake Checkvist server, for testing Checkvist clients without the network.
+//!
+//! `FakeCheckvist` runs an HTTP server on a local port, in process, and keeps its
+//! lists, tasks and notes in memory. Unlike a mock, it behaves like the real service
+//! across a sequence of requests: tasks added are returned by later requests, their
+//! positions shift as siblings are added or removed, and so on.
+//!
+//! It can also be switched to misbehave in the ways the real service does: tokens
+//! that have expired, lists that are forbidden, rate limits, and slow or lost
+//! responses.
+//!
+//! ```no_run
+//! let server = cvfake::FakeCheckvist::start();
+//! server.add_user("user", "remote key");
+//! let list_id = server.add_list("A list");
+//! server.expire_tokens();
+//! println!("Checkvist is at {}, with list {}", server.uri(), list_id);
+//! ```
+use std::collections::HashSet;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use log::{debug, error};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::Url;
+
+mod store;
+
+pub use store::{List, Note, Task};
+use store::{Store, StoreError};
+
+const LIST_UNAVAILABLE: &str = "The list doesn't exist or is not available to you";
+const UNAUTHENTICATED: &str = "Unauthenticated: no valid authentication data in request";
+
+/// Behaviour that tests can switch on and off while the server runs
+#[derive(Default)]
+struct Switches {
+    latency: Duration,
+    forbidden_lists: HashSet<u32>,
+    fail_token_refresh: bool,
+    lost_responses: usize,
+    rate_limited_requests: usize,
+    /// How many more requests that change anything are handled before all are refused
+    changes_before_refusal: Option<usize>,
+}
+
+#[derive(Default)]
+struct State {
+    store: Mutex<Store>,
+    switches: Mutex<Switches>,
+    request_count: Mutex<usize>,
+    /// (requests being handled now, most ever handled at once)
+    concurrency: Mutex<(usize, usize)>,
+}
+
+/// A running fake Checkvist server. It stops when dropped
+pub struct FakeCheckvist {
+    server: Arc<Server>,
+    state: Arc<State>,
+    address: SocketAddr,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl FakeCheckvist {
+    /// Starts a server on a free local port
+    pub fn start() -> Self {
+        Self::start_on("127.0.0.1:0")
+    }
+
+    /// Starts a server on `address` (eg. "127.0.0.1:8080")
+    pub fn start_on(address: &str) -> Self {
+        let server = Arc::new(Server::http(address).expect("Couldn't start fake Checkvist server"));
+        let address = server
+            .server_addr()
+            .to_ip()
+            .expect("Fake Checkvist server isn't listening on an IP address");
+        let state = Arc::new(State::default());
+
+        let listener = {
+            let server = Arc::clone(&server);
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                // each request gets its own thread, so slow responses don't hold up others
+                for request in server.incoming_requests() {
+                    let state = Arc::clone(&state);
+                    thread::spawn(move || handle(&state, request));
+                }
+            })
+        };
+
+        Self {
+            server,
+            state,
+            address,
+            listener: Some(listener),
+        }
+    }
+
+    /// The base url to give to a client
+    pub fn uri(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// Adds a user who can log in with `remote_key`
+    pub fn add_user(&self, username: &str, remote_key: &str) {
+        self.store()
+            .users
+            .insert(username.to_string(), remote_key.to_string());
+    }
+
+    /// A valid token, as if a user had logged in
+    pub fn token(&self) -> String {
+        self.store().issue_token()
+    }
+
+    pub fn add_list(&self, name: &str) -> u32 {
+        self.store().add_list(name).id
+    }
+
+    /// Adds a task as the last child of `parent_id` (or at the top level if None)
+    /// Panics if the list or parent doesn't exist
+    pub fn add_task(&self, list_id: u32, content: &str, parent_id: Option<u32>) -> u32 {
+        self.store()
+            .add_task(
+                list_id,
+                &json!({"content": content, "parent_id": parent_id.unwrap_or(0)}),
+            )
+            .expect("Couldn't add task to fake Checkvist")
+            .id
+    }
+
+    pub fn list(&self, list_id: u32) -> Option<List> {
+        self.store().lists.get(&list_id).cloned()
+    }
+
+    pub fn lists(&self) -> Vec<List> {
+        self.store().lists.values().cloned().collect()
+    }
+
+    /// All tasks in the list, ordered by parent id then position
+    pub fn tasks(&self, list_id: u32) -> Vec<Task> {
+        self.store()
+            .tasks(list_id)
+            .map(|tasks| tasks.into_iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn task(&self, task_id: u32) -> Option<Task> {
+        self.store().tasks.get(&task_id).cloned()
+    }
+
+    /// The number of requests received so far
+    pub fn request_count(&self) -> usize {
+        *self.state.request_count.lock().unwrap()
+    }
+
+    /// The most requests that have been handled at the same time
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.state.concurrency.lock().unwrap().1
+    }
+
+    /// Makes every token issued so far invalid, as if they had timed out.
+    /// They can still be refreshed (unless `fail_token_refresh` is set)
+    pub fn expire_tokens(&self) {
+        for valid in self.store().tokens.values_mut() {
+            *valid = false;
+        }
+    }
+
+    /// Makes token refreshes fail (or succeed again)
+    pub fn fail_token_refresh(&self, fail: bool) {
+        self.switches().fail_token_refresh = fail;
+    }
+
+    /// Responds to all requests for the list with a 403, as Checkvist does for
+    /// lists the user can't access
+    pub fn forbid_list(&self, list_id: u32) {
+        self.switches().forbidden_lists.insert(list_id);
+    }
+
+    pub fn allow_list(&self, list_id: u32) {
+        self.switches().forbidden_lists.remove(&list_id);
+    }
+
+    /// Handles the next `count` requests that change anything (ie. aren't GETs), but
+    /// sends back garbage instead of their responses, as if the connection failed
+    /// after Checkvist had acted on them
+    pub fn lose_responses(&self, count: usize) {
+        self.switches().lost_responses = count;
+    }
+
+    /// Refuses the next `count` requests with a 429 (Too Many Requests), asking the
+    /// client to retry immediately
+    pub fn rate_limit(&self, count: usize) {
+        self.switches().rate_limited_requests = count;
+    }
+
+    /// Handles `count` more requests that change anything, then refuses every later
+    /// one with a 429, as if Checkvist had started rate limiting partway through.
+    /// None handles them all again
+    pub fn refuse_changes_after(&self, count: Option<usize>) {
+        self.switches().changes_before_refusal = count;
+    }
+
+    /// Delays every response by `latency`
+    pub fn set_latency(&self, latency: Duration) {
+        self.switches().latency = latency;
+    }
+
+    fn store(&self) -> std::sync::MutexGuard<'_, Store> {
+        self.state.store.lock().unwrap()
+    }
+
+    fn switches(&self) -> std::sync::MutexGuard<'_, Switches> {
+        self.state.switches.lock().unwrap()
+    }
+}
+
+impl Drop for FakeCheckvist {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+    }
+}
+
+/// A response yet to be sent: status and JSON body
+struct Reply {
+    status: u16,
+    body: Value,
+}
+
+impl Reply {
+    fn ok<T: Serialize>(body: T) -> Self {
+        Self {
+            status: 200,
+            body: json!(body),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "message": message }),
+        }
+    }
+}
+
+impl From<StoreError> for Reply {
+    fn from(error: StoreError) -> Self {
+        match error {
+            StoreError::ListUnavailable => Reply::error(403, LIST_UNAVAILABLE),
+            StoreError::InvalidParentId(parent_id) => {
+                Reply::error(400, &format!("Invalid parent_id: {}", parent_id))
+            }
+            StoreError::TaskNotFound => Reply::error(404, "Task not found"),
+        }
+    }
+}
+
+fn handle(state: &State, request: Request) {
+    *state.request_count.lock().unwrap() += 1;
+    {
+        let mut concurrency = state.concurrency.lock().unwrap();
+        concurrency.0 += 1;
+        concurrency.1 = concurrency.1.max(concurrency.0);
+    }
+    respond(state, request);
+    state.concurrency.lock().unwrap().0 -= 1;
+}
+
+fn respond(state: &State, mut request: Request) {
+    let latency = state.switches.lock().unwrap().latency;
+    if !latency.is_zero() {
+        thread::sleep(latency);
+    }
+
+    if take_rate_limited(state) || is_refused_change(state, &request) {
+        let response = Response::from_string(json!({"message": "Too many requests"}).to_string())
+            .with_status_code(429)
+            .with_header(Header::from_bytes("Retry-After", "0").expect("header is valid"));
+        if let Err(err) = request.respond(response) {
+            error!("Fake Checkvist couldn't respond: {}", err);
+        }
+        return;
+    }
+
+    let mut body = String::new();
+    let reply = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => route(state, &request, &body),
+        Err(_) => Reply::error(400, "Couldn't read request body"),
+    };
+    debug!("{} {} -> {}", request.method(), request.url(), reply.status);
+
+    if *request.method() != Method::Get && take_lost_response(state) {
+        let mut writer = request.into_writer();
+        if let Err(err) = writer
+            .write_all(b"not http\r\n\r\n")
+            .and_then(|_| writer.flush())
+        {
+            error!("Fake Checkvist couldn't send a broken response: {}", err);
+        }
+        return;
+    }
+
+    let response = Response::from_string(reply.body.to_string())
+        .with_status_code(reply.status)
+        .with_header(
+            Header::from_bytes("Content-Type", "application/json").expect("header is valid"),
+        );
+    if let Err(err) = request.respond(response) {
+        error!("Fake Checkvist couldn't respond: {}", err);
+    }
+}
+
+fn take_rate_limited(state: &State) -> bool {
+    let mut switches = state.switches.lock().unwrap();
+    if switches.rate_limited_requests == 0 {
+        return false;
+    }
+    switches.rate_limited_requests -= 1;
+    true
+}
+
+fn is_refused_change(state: &State, request: &Request) -> bool {
+    if *request.method() == Method::Get {
+        return false;
+    }
+    let mut switches = state.switches.lock().unwrap();
+    match switches.changes_before_refusal {
+        Some(0) => true,
+        Some(count) => {
+            switches.changes_before_refusal = Some(count - 1);
+            false
+        }
+        None => false,
+    }
+}
+
+fn take_lost_response(state: &State) -> bool {
+    let mut switches = state.switches.lock().unwrap();
+    if switches.lost_responses == 0 {
+        return false;
+    }
+    switches.lost_responses -= 1;
+    true
+}
+
+fn route(state: &State, request: &Request, body: &str) -> Reply {
+    let url = Url::parse("http://localhost")
+        .and_then(|base| base.join(request.url()))
+        .expect("request url should be a valid path");
+    let fields: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    let method = request.method();
+
+    match (method, segments.as_slice()) {
+        (Method::Post, ["auth", "login.json"]) => return login(state, &fields),
+        (Method::Post, ["auth", "refresh_token.json"]) => return refresh_token(state, &fields),
+        _ => (),
+    }
+
+    let token = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("X-Client-token"))
+        .map(|header| header.value.to_string())
+        .or_else(|| query_param(&url, "token"));
+    let mut store = state.store.lock().unwrap();
+    if !token.is_some_and(|token| store.is_valid_token(&token)) {
+        return Reply::error(401, UNAUTHENTICATED);
+    }
+
+    let list_id = match segments.as_slice() {
+        ["checklists", list, ..] => parse_id(list),
+        _ => None,
+    };
+    if let Some(list_id) = list_id {
+        if state
+            .switches
+            .lock()
+            .unwrap()
+            .forbidden_lists
+            .contains(&list_id)
+        {
+            return Reply::error(403, LIST_UNAVAILABLE);
+        }
+    }
+    let task_id = match segments.as_slice() {
+        ["checklists", _, "tasks", task, ..] => parse_id(task),
+        _ => None,
+    };
+    // Checkvist accepts fields either bare, or wrapped in the item type
+    let task_fields = fields.get("task").unwrap_or(&fields);
+    let list_fields = fields.get("checklist").unwrap_or(&fields);
+
+    let result = match (method, segments.as_slice(), list_id, task_id) {
+        (Method::Get, ["checklists.json"], _, _) => {
+            let archived = query_param(&url, "archived").as_deref() == Some("true");
+            let forbidden = &state.switches.lock().unwrap().forbidden_lists;
+            let lists: Vec<&List> = store
+                .lists
+                .values()
+                .filter(|list| list.archived == archived && !forbidden.contains(&list.id))
+                .collect();
+            Ok(Reply::ok(lists))
+        }
+        (Method::Post, ["checklists.json"], _, _) => {
+            let name = list_fields
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            Ok(Reply::ok(store.add_list(name)))
+        }
+        (Method::Get, ["checklists", _], Some(list_id), _) => store.list(list_id).map(Reply::ok),
+        (Method::Put, ["checklists", _], Some(list_id), _) => {
+            store.update_list(list_id, list_fields).map(Reply::ok)
+        }
+        (Method::Delete, ["checklists", _], Some(list_id), _) => {
+            store.delete_list(list_id).map(Reply::ok)
+        }
+        (Method::Get, ["checklists", _, "tasks.json"], Some(list_id), _) => {
+            let with_notes = query_param(&url, "with_notes").as_deref() == Some("true");
+            store.tasks(list_id).map(|tasks| {
+                let tasks: Vec<Value> = tasks
+                    .into_iter()
+                    .map(|task| task_json(task, with_notes))
+                    .collect();
+                Reply::ok(tasks)
+            })
+        }
+        (Method::Post, ["checklists", _, "tasks.json"], Some(list_id), _) => {
+            store.add_task(list_id, task_fields).map(Reply::ok)
+        }
+        (Method::Post, ["checklists", _, "import.json"], Some(list_id), _) => {
+            let content = fields
+                .get("import_content")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let parse_tasks = fields.get("parse_tasks").and_then(Value::as_bool) == Some(true);
+            let parent_id = fields.get("parent_id").and_then(Value::as_u64).unwrap_or(0);
+            store
+                .import(list_id, parent_id as u32, content, parse_tasks)
+                .map(Reply::ok)
+        }
+        (Method::Get, ["checklists", _, "tasks", _], Some(list_id), Some(task_id)) => {
+            store.task_with_parents(list_id, task_id).map(|tasks| {
+                Reply::ok(
+                    tasks
+                        .into_iter()
+                        .map(|task| task_json(task, false))
+                        .collect::<Vec<_>>(),
+                )
+            })
+        }
+        (Method::Put, ["checklists", _, "tasks", _], Some(list_id), Some(task_id)) => store
+            .update_task(list_id, task_id, task_fields)
+            .map(Reply::ok),
+        (Method::Delete, ["checklists", _, "tasks", _], Some(list_id), Some(task_id)) => {
+            store.delete_task(list_id, task_id).map(Reply::ok)
+        }
+        (Method::Post, ["checklists", _, "tasks", _, action], Some(list_id), Some(task_id))
+            if status_for(action).is_some() =>
+        {
+            let status = status_for(action).expect("action was just checked");
+            store.set_status(list_id, task_id, status).map(Reply::ok)
+        }
+        (
+            Method::Get,
+            ["checklists", _, "tasks", _, "comments.json"],
+            Some(list_id),
+            Some(task_id),
+        ) => store.notes(list_id, task_id).map(Reply::ok),
+        (
+            Method::Post,
+            ["checklists", _, "tasks", _, "comments.json"],
+            Some(list_id),
+            Some(task_id),
+        ) => {
+            let comment = fields
+                .get("comment")
+                .map(|comment| comment.get("comment").unwrap_or(comment))
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            store.add_note(list_id, task_id, comment).map(Reply::ok)
+        }
+        _ => Ok(Reply::error(404, "Not found")),
+    };
+
+    result.unwrap_or_else(Reply::from)
+}
+
+fn login(state: &State, fields: &Value) -> Reply {
+    let username = fields.get("username").and_then(Value::as_str);
+    let remote_key = fields.get("remote_key").and_then(Value::as_str);
+    let mut store = state.store.lock().unwrap();
+    match (username, remote_key) {
+        (Some(username), Some(remote_key))
+            if store.users.get(username).map(String::as_str) == Some(remote_key) =>
+        {
+            Reply::ok(json!({ "token": store.issue_token() }))
+        }
+        _ => Reply::error(401, UNAUTHENTICATED),
+    }
+}
+
+fn refresh_token(state: &State, fields: &Value) -> Reply {
+    if state.switches.lock().unwrap().fail_token_refresh {
+        return Reply::error(401, UNAUTHENTICATED);
+    }
+    let mut store = state.store.lock().unwrap();
+    let old_token = fields.get("old_token").and_then(Value::as_str);
+    match old_token {
+        // expired tokens can be refreshed, but only once
+        Some(old_token) if store.tokens.remove(old_token).is_some() => {
+            Reply::ok(json!({ "token": store.issue_token() }))
+        }
+        _ => Reply::error(401, UNAUTHENTICATED),
+    }
+}
+
+fn task_json(task: &Task, with_notes: bool) -> Value {
+    let mut value = json!(task);
+    if with_notes {
+        value["notes"] = json!(task.notes);
+    }
+    value
+}
+
+fn status_for(action: &str) -> Option<u8> {
+    match action {
+        "reopen.json" => Some(0),
+        "close.json" => Some(1),
+        "invalidate.json" => Some(2),
+        _ => None,
+    }
+}
+
+fn parse_id(segment: &str) -> Option<u32> {
+    segment.trim_end_matches(".json").parse().ok()
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _value)| key == name)
+        .map(|(_key, value)| value.into_owned())
+}
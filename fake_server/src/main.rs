@@ -0,0 +1,25 @@
+//! Runs a fake Checkvist server until killed, for trying out clients by hand.
+//!
+//! Usage: cvfake [ADDRESS]   (default 127.0.0.1:3000)
+//!
+//! The server starts with user "user" (remote key "remote key") and one list.
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+use cvfake::FakeCheckvist;
+
+fn main() {
+    let address = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:3000".into());
+    let server = FakeCheckvist::start_on(&address);
+    server.add_user("user", "remote key");
+    let list_id = server.add_list("Inbox");
+
+    println!("Fake Checkvist listening on {}", server.uri());
+    println!("Log in as \"user\" with remote key \"remote key\". List \"Inbox\" has id {list_id}");
+    loop {
+        thread::sleep(Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,464 @@
+//! In-memory state of the fake Checkvist service, and the rules the real service
+//! enforces on it (task hierarchy, positions, and so on).
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct List {
+    pub id: u32,
+    pub name: String,
+    pub updated_at: String,
+    pub task_count: u16,
+    pub archived: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Task {
+    pub id: u32,
+    #[serde(skip)]
+    pub list_id: u32,
+    pub content: String,
+    /// 0 for top level tasks, as the real service does
+    pub parent_id: u32,
+    pub position: u16,
+    pub status: u8,
+    pub due: Option<String>,
+    pub tags: BTreeMap<String, bool>,
+    pub tags_as_text: String,
+    pub assignee_ids: Vec<u32>,
+    pub updated_at: String,
+    #[serde(skip)]
+    pub notes: Vec<Note>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Note {
+    pub id: u32,
+    pub comment: String,
+    pub task_id: u32,
+}
+
+/// Failures the store reports, which the server maps to the real service's responses
+#[derive(Debug, PartialEq)]
+pub enum StoreError {
+    /// 403, as the real service responds to missing or inaccessible lists
+    ListUnavailable,
+    /// 400
+    InvalidParentId(u32),
+    /// 404
+    TaskNotFound,
+}
+
+#[derive(Default)]
+pub struct Store {
+    pub lists: BTreeMap<u32, List>,
+    pub tasks: BTreeMap<u32, Task>,
+    /// username -> remote key
+    pub users: HashMap<String, String>,
+    /// token -> whether it is still valid
+    pub tokens: HashMap<String, bool>,
+    next_id: u32,
+    /// a fake clock, ticking once per change, so updated_at values are deterministic
+    revision: u32,
+}
+
+type Result<T> = std::result::Result<T, StoreError>;
+
+impl Store {
+    pub fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub fn issue_token(&mut self) -> String {
+        let token = format!("fake-token-{}", self.next_id());
+        self.tokens.insert(token.clone(), true);
+        token
+    }
+
+    pub fn is_valid_token(&self, token: &str) -> bool {
+        self.tokens.get(token).copied().unwrap_or(false)
+    }
+
+    fn tick(&mut self) -> String {
+        self.revision += 1;
+        let seconds = self.revision;
+        format!(
+            "2023/01/01 {:02}:{:02}:{:02} +0000",
+            seconds / 3600 % 24,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+
+    pub fn add_list(&mut self, name: &str) -> List {
+        let list = List {
+            id: self.next_id(),
+            name: name.to_string(),
+            updated_at: self.tick(),
+            task_count: 0,
+            archived: false,
+        };
+        self.lists.insert(list.id, list.clone());
+        list
+    }
+
+    pub fn list(&self, list_id: u32) -> Result<&List> {
+        self.lists.get(&list_id).ok_or(StoreError::ListUnavailable)
+    }
+
+    pub fn update_list(&mut self, list_id: u32, fields: &Value) -> Result<List> {
+        let updated_at = self.tick();
+        let list = self
+            .lists
+            .get_mut(&list_id)
+            .ok_or(StoreError::ListUnavailable)?;
+        if let Some(name) = fields.get("name").and_then(Value::as_str) {
+            list.name = name.to_string();
+        }
+        if let Some(archived) = fields.get("archived").and_then(Value::as_bool) {
+            list.archived = archived;
+        }
+        list.updated_at = updated_at;
+        Ok(list.clone())
+    }
+
+    pub fn delete_list(&mut self, list_id: u32) -> Result<List> {
+        let list = self
+            .lists
+            .remove(&list_id)
+            .ok_or(StoreError::ListUnavailable)?;
+        self.tasks.retain(|_id, task| task.list_id != list_id);
+        Ok(list)
+    }
+
+    /// Tasks in a list, ordered by parent and position
+    pub fn tasks(&self, list_id: u32) -> Result<Vec<&Task>> {
+        self.list(list_id)?;
+        let mut tasks: Vec<&Task> = self
+            .tasks
+            .values()
+            .filter(|task| task.list_id == list_id)
+            .collect();
+        tasks.sort_by_key(|task| (task.parent_id, task.position));
+        Ok(tasks)
+    }
+
+    pub fn task(&self, list_id: u32, task_id: u32) -> Result<&Task> {
+        self.list(list_id)?;
+        self.tasks
+            .get(&task_id)
+            .filter(|task| task.list_id == list_id)
+            .ok_or(StoreError::TaskNotFound)
+    }
+
+    /// The task followed by its ancestors, as the real service returns
+    pub fn task_with_parents(&self, list_id: u32, task_id: u32) -> Result<Vec<&Task>> {
+        let mut tasks = vec![self.task(list_id, task_id)?];
+        while let Some(parent) = tasks
+            .last()
+            .and_then(|task| self.tasks.get(&task.parent_id))
+        {
+            tasks.push(parent);
+        }
+        Ok(tasks)
+    }
+
+    /// Adds a task from the fields the real service accepts. The new task is
+    /// inserted at `position` among its siblings (or last if absent or too large)
+    pub fn add_task(&mut self, list_id: u32, fields: &Value) -> Result<Task> {
+        self.list(list_id)?;
+        let parent_id = parent_id_field(fields);
+        self.check_parent(list_id, parent_id)?;
+
+        let id = self.next_id();
+        let updated_at = self.tick();
+        let task = Task {
+            id,
+            list_id,
+            content: fields
+                .get("content")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            parent_id,
+            position: 0,
+            status: 0,
+            due: None,
+            tags: BTreeMap::new(),
+            tags_as_text: String::new(),
+            assignee_ids: Vec::new(),
+            updated_at,
+            notes: Vec::new(),
+        };
+        self.tasks.insert(id, task);
+        self.apply_fields(id, fields);
+        self.place(id, parent_id, position_field(fields));
+        self.touch_list(list_id);
+
+        Ok(self.tasks[&id].clone())
+    }
+
+    /// Adds tasks from indented lines of text, as the real service's import does.
+    /// Each line is a task, nested beneath the nearest less indented line before
+    /// it. With `parse_tasks`, `#tags` and `^yyyy-mm-dd` due dates are extracted
+    /// from the content. Returns the new tasks in order
+    pub fn import(
+        &mut self,
+        list_id: u32,
+        parent_id: u32,
+        content: &str,
+        parse_tasks: bool,
+    ) -> Result<Vec<Task>> {
+        self.list(list_id)?;
+        self.check_parent(list_id, parent_id)?;
+
+        let mut added = Vec::new();
+        // (indent, id) of each line that later lines may be nested beneath
+        let mut open: Vec<(usize, u32)> = Vec::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let indent = line.len() - line.trim_start().len();
+            while open
+                .last()
+                .is_some_and(|(open_indent, _)| *open_indent >= indent)
+            {
+                open.pop();
+            }
+            let parent = open.last().map_or(parent_id, |(_, id)| *id);
+            let fields = if parse_tasks {
+                import_fields(line.trim(), parent)
+            } else {
+                json!({ "content": line.trim(), "parent_id": parent })
+            };
+            let task = self.add_task(list_id, &fields)?;
+            open.push((indent, task.id));
+            added.push(task);
+        }
+
+        Ok(added)
+    }
+
+    pub fn update_task(&mut self, list_id: u32, task_id: u32, fields: &Value) -> Result<Task> {
+        let current_parent = self.task(list_id, task_id)?.parent_id;
+        let parent_id = if fields.get("parent_id").is_some() {
+            parent_id_field(fields)
+        } else {
+            current_parent
+        };
+        self.check_parent(list_id, parent_id)?;
+        if parent_id == task_id || self.is_descendant(parent_id, task_id) {
+            return Err(StoreError::InvalidParentId(parent_id));
+        }
+
+        self.apply_fields(task_id, fields);
+        if parent_id != current_parent || fields.get("position").is_some() {
+            self.place(task_id, parent_id, position_field(fields));
+        }
+        self.touch_task(task_id);
+        self.touch_list(list_id);
+
+        Ok(self.tasks[&task_id].clone())
+    }
+
+    /// Deletes the task and its descendants, returning the task
+    pub fn delete_task(&mut self, list_id: u32, task_id: u32) -> Result<Task> {
+        let task = self.task(list_id, task_id)?.clone();
+        let doomed: Vec<u32> = self
+            .tasks
+            .keys()
+            .copied()
+            .filter(|id| *id == task_id || self.is_descendant(*id, task_id))
+            .collect();
+        for id in doomed {
+            self.tasks.remove(&id);
+        }
+        self.renumber(task.parent_id, list_id);
+        self.touch_list(list_id);
+
+        Ok(task)
+    }
+
+    pub fn set_status(&mut self, list_id: u32, task_id: u32, status: u8) -> Result<Task> {
+        self.task(list_id, task_id)?;
+        self.tasks
+            .get_mut(&task_id)
+            .expect("task was just found")
+            .status = status;
+        self.touch_task(task_id);
+        self.touch_list(list_id);
+
+        Ok(self.tasks[&task_id].clone())
+    }
+
+    pub fn notes(&self, list_id: u32, task_id: u32) -> Result<Vec<Note>> {
+        Ok(self.task(list_id, task_id)?.notes.clone())
+    }
+
+    pub fn add_note(&mut self, list_id: u32, task_id: u32, comment: &str) -> Result<Note> {
+        self.task(list_id, task_id)?;
+        let note = Note {
+            id: self.next_id(),
+            comment: comment.to_string(),
+            task_id,
+        };
+        self.tasks
+            .get_mut(&task_id)
+            .expect("task was just found")
+            .notes
+            .push(note.clone());
+        self.touch_list(list_id);
+
+        Ok(note)
+    }
+
+    fn check_parent(&self, list_id: u32, parent_id: u32) -> Result<()> {
+        if parent_id == 0 {
+            return Ok(());
+        }
+        match self.tasks.get(&parent_id) {
+            Some(parent) if parent.list_id == list_id => Ok(()),
+            _ => Err(StoreError::InvalidParentId(parent_id)),
+        }
+    }
+
+    fn is_descendant(&self, task_id: u32, ancestor_id: u32) -> bool {
+        let mut current = self.tasks.get(&task_id);
+        while let Some(task) = current {
+            if task.parent_id == ancestor_id {
+                return true;
+            }
+            current = self.tasks.get(&task.parent_id);
+        }
+        false
+    }
+
+    fn apply_fields(&mut self, task_id: u32, fields: &Value) {
+        let task = self.tasks.get_mut(&task_id).expect("task exists");
+        if let Some(content) = fields.get("content").and_then(Value::as_str) {
+            task.content = content.to_string();
+        }
+        if let Some(due) = fields.get("due_date") {
+            task.due = due.as_str().filter(|due| !due.is_empty()).map(String::from);
+        }
+        // tags are sent as Checkvist returns them (name to whether it's private),
+        // or as a comma-separated string of public tags
+        let tags = match fields.get("tags") {
+            Some(Value::String(tags)) => Some(
+                tags.split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(|tag| (tag.to_string(), false))
+                    .collect(),
+            ),
+            Some(Value::Object(tags)) => Some(
+                tags.iter()
+                    .map(|(tag, private)| (tag.clone(), private.as_bool().unwrap_or(false)))
+                    .collect(),
+            ),
+            _ => None,
+        };
+        if let Some(tags) = tags {
+            task.tags = tags;
+            task.tags_as_text = task.tags.keys().cloned().collect::<Vec<_>>().join(", ");
+        }
+        if let Some(ids) = fields.get("assignee_ids").and_then(Value::as_array) {
+            task.assignee_ids = ids
+                .iter()
+                .filter_map(Value::as_u64)
+                .map(|id| id as u32)
+                .collect();
+        }
+    }
+
+    /// Moves the task to `position` (1 based) among the children of `parent_id`
+    fn place(&mut self, task_id: u32, parent_id: u32, position: Option<u16>) {
+        let (list_id, old_parent) = {
+            let task = &self.tasks[&task_id];
+            (task.list_id, task.parent_id)
+        };
+        let mut siblings = self.sibling_ids(list_id, parent_id);
+        siblings.retain(|id| *id != task_id);
+        let index = match position {
+            Some(position) if position > 0 => (position as usize - 1).min(siblings.len()),
+            _ => siblings.len(),
+        };
+        siblings.insert(index, task_id);
+        self.tasks.get_mut(&task_id).expect("task exists").parent_id = parent_id;
+        for (index, id) in siblings.iter().enumerate() {
+            self.tasks.get_mut(id).expect("sibling exists").position = index as u16 + 1;
+        }
+        if old_parent != parent_id {
+            self.renumber(old_parent, list_id);
+        }
+    }
+
+    fn renumber(&mut self, parent_id: u32, list_id: u32) {
+        for (index, id) in self.sibling_ids(list_id, parent_id).iter().enumerate() {
+            self.tasks.get_mut(id).expect("sibling exists").position = index as u16 + 1;
+        }
+    }
+
+    /// ids of the children of `parent_id`, in position order
+    fn sibling_ids(&self, list_id: u32, parent_id: u32) -> Vec<u32> {
+        let mut siblings: Vec<&Task> = self
+            .tasks
+            .values()
+            .filter(|task| task.list_id == list_id && task.parent_id == parent_id)
+            .collect();
+        siblings.sort_by_key(|task| task.position);
+        siblings.iter().map(|task| task.id).collect()
+    }
+
+    fn touch_task(&mut self, task_id: u32) {
+        let updated_at = self.tick();
+        if let Some(task) = self.tasks.get_mut(&task_id) {
+            task.updated_at = updated_at;
+        }
+    }
+
+    fn touch_list(&mut self, list_id: u32) {
+        let updated_at = self.tick();
+        let task_count = self
+            .tasks
+            .values()
+            .filter(|task| task.list_id == list_id)
+            .count() as u16;
+        if let Some(list) = self.lists.get_mut(&list_id) {
+            list.updated_at = updated_at;
+            list.task_count = task_count;
+        }
+    }
+}
+
+/// The fields for an imported line, with smart syntax moved out of the content
+fn import_fields(line: &str, parent_id: u32) -> Value {
+    let mut content = Vec::new();
+    let mut tags = Vec::new();
+    let mut due = None;
+    for word in line.split_whitespace() {
+        match (word.strip_prefix('#'), word.strip_prefix('^')) {
+            (Some(tag), _) if !tag.is_empty() => tags.push(tag),
+            (_, Some(date)) if !date.is_empty() => due = Some(date.replace('-', "/")),
+            _ => content.push(word),
+        }
+    }
+    json!({
+        "content": content.join(" "),
+        "parent_id": parent_id,
+        "tags": tags.join(","),
+        "due_date": due,
+    })
+}
+
+fn parent_id_field(fields: &Value) -> u32 {
+    fields.get("parent_id").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+fn position_field(fields: &Value) -> Option<u16> {
+    fields
+        .get("position")
+        .and_then(Value::as_u64)
+        .map(|position| position as u16)
+}
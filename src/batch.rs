@@ -0,0 +1,223 @@
+//! Running many task operations at once.
+//!
+//! `CheckvistClient::run_batch` spreads operations over a bounded number of worker
+//! threads, all sharing the one client (and so the one token, which is refreshed
+//! only once however many workers find it expired). When Checkvist rate limits a
+//! request, every worker pauses before its next request, and the limited operation
+//! is retried.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use crate::{CheckvistClient, CheckvistError, Note, Result, Task, TaskStatus};
+
+/// The longest a rate limited operation waits, however many times it's retried
+/// (and however long Checkvist asks it to wait)
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchOperation {
+    AddTask {
+        list_id: u32,
+        task: Task,
+    },
+    UpdateTask {
+        list_id: u32,
+        task: Task,
+    },
+    SetTaskStatus {
+        list_id: u32,
+        task_id: u32,
+        status: TaskStatus,
+    },
+    DeleteTask {
+        list_id: u32,
+        task_id: u32,
+    },
+    AddNote {
+        list_id: u32,
+        task_id: u32,
+        comment: String,
+    },
+}
+
+/// What a successful operation returned
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchOutcome {
+    /// The added or updated task
+    Task(Task),
+    Note(Note),
+    Done,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// The most operations run at once
+    pub concurrency: usize,
+    /// How many times an operation is retried when rate limited
+    pub max_retries: u32,
+    /// How long to wait when rate limited, if Checkvist doesn't say. Doubled with
+    /// each retry, up to a minute
+    pub backoff: Duration,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            max_retries: 5,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+impl CheckvistClient {
+    /// Runs `operations` concurrently, returning each one's result in the same
+    /// order as the operations
+    pub fn run_batch(
+        &self,
+        operations: &[BatchOperation],
+        options: &BatchOptions,
+    ) -> Vec<Result<BatchOutcome>> {
+        let next = AtomicUsize::new(0);
+        let paused_until: Mutex<Option<Instant>> = Mutex::new(None);
+        let results: Mutex<Vec<Option<Result<BatchOutcome>>>> =
+            Mutex::new(operations.iter().map(|_| None).collect());
+        let workers = options.concurrency.clamp(1, operations.len().max(1));
+        info!(
+            "Running {} operations with {} workers",
+            operations.len(),
+            workers
+        );
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(operation) = operations.get(index) else {
+                        break;
+                    };
+                    let result = self.run_with_retries(operation, options, &paused_until);
+                    results.lock().expect("batch results lock poisoned")[index] = Some(result);
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .expect("batch results lock poisoned")
+            .into_iter()
+            .map(|result| result.expect("every operation has a result"))
+            .collect()
+    }
+
+    fn run_with_retries(
+        &self,
+        operation: &BatchOperation,
+        options: &BatchOptions,
+        paused_until: &Mutex<Option<Instant>>,
+    ) -> Result<BatchOutcome> {
+        let mut retries = 0;
+        loop {
+            wait_for_pause(paused_until);
+            match self.run_operation(operation) {
+                Err(CheckvistError::RateLimitedError { retry_after })
+                    if retries < options.max_retries =>
+                {
+                    let wait = pause(retry_after, options.backoff, retries);
+                    warn!("Rate limited. Pausing for {:?}", wait);
+                    let until = Instant::now() + wait;
+                    let mut paused = paused_until.lock().expect("pause lock poisoned");
+                    *paused = Some(paused.map_or(until, |current| current.max(until)));
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn run_operation(&self, operation: &BatchOperation) -> Result<BatchOutcome> {
+        match operation {
+            BatchOperation::AddTask { list_id, task } => {
+                self.add_task(*list_id, task).map(BatchOutcome::Task)
+            }
+            BatchOperation::UpdateTask { list_id, task } => {
+                self.update_task(*list_id, task).map(BatchOutcome::Task)
+            }
+            BatchOperation::SetTaskStatus {
+                list_id,
+                task_id,
+                status,
+            } => self
+                .set_task_status(*list_id, *task_id, *status)
+                .map(|_| BatchOutcome::Done),
+            BatchOperation::DeleteTask { list_id, task_id } => self
+                .delete_task(*list_id, *task_id)
+                .map(|_| BatchOutcome::Done),
+            BatchOperation::AddNote {
+                list_id,
+                task_id,
+                comment,
+            } => self
+                .add_note(*list_id, *task_id, comment)
+                .map(BatchOutcome::Note),
+        }
+    }
+}
+
+/// How long to pause after being rate limited: as long as Checkvist's Retry-After
+/// says, or else the backoff. Either way no longer than `MAX_BACKOFF`
+fn pause(retry_after: Option<Duration>, initial: Duration, retries: u32) -> Duration {
+    retry_after.map_or_else(|| backoff(initial, retries), |wait| wait.min(MAX_BACKOFF))
+}
+
+/// `initial` doubled for each of `retries`, up to `MAX_BACKOFF`
+fn backoff(initial: Duration, retries: u32) -> Duration {
+    2u32.checked_pow(retries)
+        .map_or(MAX_BACKOFF, |factor| initial.saturating_mul(factor))
+        .min(MAX_BACKOFF)
+}
+
+fn wait_for_pause(paused_until: &Mutex<Option<Instant>>) {
+    let until = *paused_until.lock().expect("pause lock poisoned");
+    if let Some(until) = until {
+        let now = Instant::now();
+        if until > now {
+            thread::sleep(until - now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let initial = Duration::from_millis(500);
+
+        assert_eq!(backoff(initial, 0), initial);
+        assert_eq!(backoff(initial, 3), Duration::from_secs(4));
+        assert_eq!(backoff(initial, 10), MAX_BACKOFF);
+        assert_eq!(backoff(initial, 40), MAX_BACKOFF);
+        assert_eq!(backoff(Duration::MAX, 1), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn retry_after_is_used_up_to_the_maximum() {
+        let initial = Duration::from_millis(500);
+
+        assert_eq!(
+            pause(Some(Duration::from_secs(5)), initial, 3),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            pause(Some(Duration::from_secs(86400)), initial, 0),
+            MAX_BACKOFF
+        );
+        assert_eq!(pause(None, initial, 3), Duration::from_secs(4));
+    }
+}
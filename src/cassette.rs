@@ -0,0 +1,323 @@
+//! Recording and replaying the client's HTTP interactions ("cassettes").
+//!
+//! A client given a recording cassette (`CheckvistClient::with_cassette`) talks to
+//! Checkvist as usual, and writes each request and response to the cassette file.
+//! Given a replaying cassette, it makes no network requests at all: each request is
+//! answered with a matching recorded response, and a request with no match fails.
+//!
+//! Tokens and remote keys are scrubbed before anything is written, so cassettes can
+//! be committed alongside tests. Requests are matched on method, path and query, and
+//! JSON body (not on host, so a cassette can be replayed against any base url).
+//! Login (`CheckvistClient::get_token`) is not recorded, as it happens before there is
+//! a client.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+const SCRUBBED: &str = "[scrubbed]";
+const SECRET_FIELDS: [&str; 3] = ["token", "old_token", "remote_key"];
+/// Response headers not recorded: those describing how the body was sent, which no
+/// longer apply once it's been read (and maybe scrubbed), and cookies
+const UNRECORDED_HEADERS: [&str; 5] = [
+    "content-length",
+    "transfer-encoding",
+    "content-encoding",
+    "connection",
+    "set-cookie",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// One recorded request and its response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    /// path and query, eg. "/checklists/1/tasks.json?with_notes=true"
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<Value>,
+    pub status: u16,
+    pub status_text: String,
+    /// (name, value), in the order received, eg. ("retry-after", "30")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: String,
+}
+
+impl Interaction {
+    /// The recorded response, with its headers
+    fn response(&self) -> Result<ureq::Response, Box<ureq::Error>> {
+        let mut raw = format!("HTTP/1.1 {} {}\r\n", self.status, self.status_text);
+        for (name, value) in &self.response_headers {
+            raw.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        raw.push_str("\r\n");
+        raw.push_str(&self.response_body);
+        Ok(raw.parse()?)
+    }
+}
+
+#[derive(Debug)]
+pub struct Cassette {
+    mode: CassetteMode,
+    path: PathBuf,
+    /// (interaction, whether it has been replayed)
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// A cassette that records to `path`, replacing anything already there
+    pub fn record(path: impl AsRef<Path>) -> Self {
+        Self {
+            mode: CassetteMode::Record,
+            path: path.as_ref().to_path_buf(),
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// A cassette that replays the interactions recorded in `path`
+    pub fn replay(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let interactions: Vec<Interaction> =
+            serde_json::from_str(&fs::read_to_string(path.as_ref())?)?;
+
+        Ok(Self {
+            mode: CassetteMode::Replay,
+            path: path.as_ref().to_path_buf(),
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.lock().iter().map(|(i, _played)| i.clone()).collect()
+    }
+
+    /// Interactions not yet replayed. Useful for checking that a test made every
+    /// request that was recorded
+    pub fn unplayed(&self) -> Vec<Interaction> {
+        self.lock()
+            .iter()
+            .filter(|(_i, played)| !played)
+            .map(|(i, _played)| i.clone())
+            .collect()
+    }
+
+    /// Sends `request` with `body` (or, when replaying, pretends to), recording the
+    /// interaction when recording. Secrets are scrubbed from the recording, but sent
+    /// as they are
+    pub(crate) fn send(
+        &self,
+        request: ureq::Request,
+        body: Option<&Value>,
+    ) -> Result<ureq::Response, Box<ureq::Error>> {
+        let method = request.method().to_string();
+        let path = Self::path_of(request.url());
+        let scrubbed = body.map(scrub);
+
+        match self.mode {
+            CassetteMode::Replay => self.play(&method, &path, &scrubbed),
+            CassetteMode::Record => {
+                let result = match body {
+                    Some(body) => request.send_json(body),
+                    None => request.call(),
+                }
+                .map_err(Box::new);
+                self.record_result(method, path, scrubbed, result)
+            }
+        }
+    }
+
+    fn play(
+        &self,
+        method: &str,
+        path: &str,
+        body: &Option<Value>,
+    ) -> Result<ureq::Response, Box<ureq::Error>> {
+        let mut interactions = self.lock();
+        let Some((interaction, played)) = interactions.iter_mut().find(|(i, played)| {
+            !played && i.method == method && i.path == path && i.request_body == *body
+        }) else {
+            return Err(Box::new(ureq::Error::from(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Cassette {} has no recorded response for {} {}",
+                    self.path.display(),
+                    method,
+                    path
+                ),
+            ))));
+        };
+        *played = true;
+
+        let response = interaction.response()?;
+        if response.status() >= 400 {
+            Err(Box::new(ureq::Error::Status(response.status(), response)))
+        } else {
+            Ok(response)
+        }
+    }
+
+    fn record_result(
+        &self,
+        method: String,
+        path: String,
+        request_body: Option<Value>,
+        result: Result<ureq::Response, Box<ureq::Error>>,
+    ) -> Result<ureq::Response, Box<ureq::Error>> {
+        let (response, is_error) = match result.map_err(|err| *err) {
+            Ok(response) => (response, false),
+            Err(ureq::Error::Status(_status, response)) => (response, true),
+            // nothing came back, so there's nothing to replay
+            Err(err) => return Err(Box::new(err)),
+        };
+        let status = response.status();
+        let status_text = response.status_text().to_string();
+        let mut response_headers: Vec<(String, String)> = Vec::new();
+        // a name is listed once for each of its values, which `all` gets at once
+        for name in response.headers_names() {
+            if UNRECORDED_HEADERS.contains(&name.as_str())
+                || response_headers
+                    .iter()
+                    .any(|(recorded, _)| *recorded == name)
+            {
+                continue;
+            }
+            for value in response.all(&name) {
+                response_headers.push((name.clone(), value.to_string()));
+            }
+        }
+        let response_body = response.into_string().map_err(ureq::Error::from)?;
+        let interaction = Interaction {
+            method,
+            path,
+            request_body,
+            status,
+            status_text,
+            response_headers,
+            response_body: scrub_text(&response_body),
+        };
+        // the original response was consumed in recording it, so send on a copy
+        let response = Interaction {
+            response_body,
+            ..interaction.clone()
+        }
+        .response()?;
+        self.lock().push((interaction, false));
+        // written after every interaction, as there is no telling when the client is done
+        self.save().map_err(ureq::Error::from)?;
+
+        if is_error {
+            Err(Box::new(ureq::Error::Status(status, response)))
+        } else {
+            Ok(response)
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let interactions = self.lock();
+        let recorded: Vec<&Interaction> = interactions.iter().map(|(i, _)| i).collect();
+        let json = serde_json::to_string_pretty(&recorded).map_err(io::Error::from)?;
+        fs::write(&self.path, json)
+    }
+
+    fn path_of(url: &str) -> String {
+        let Ok(mut url) = Url::parse(url) else {
+            return url.to_string();
+        };
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| {
+                let value = if SECRET_FIELDS.contains(&key.as_ref()) {
+                    SCRUBBED.to_string()
+                } else {
+                    value.into_owned()
+                };
+                (key.into_owned(), value)
+            })
+            .collect();
+        if pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+
+        match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(Interaction, bool)>> {
+        self.interactions
+            .lock()
+            .expect("cassette lock poisoned by a panicking thread")
+    }
+}
+
+/// `value`, with the values of any secret fields (at any depth) replaced
+fn scrub(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if SECRET_FIELDS.contains(&key.as_str()) {
+                        Value::String(SCRUBBED.into())
+                    } else {
+                        scrub(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(scrub).collect()),
+        value => value.clone(),
+    }
+}
+
+/// Scrubs a response body, if it's JSON
+fn scrub_text(text: &str) -> String {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) => scrub(&value).to_string(),
+        Err(_) => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn secrets_are_scrubbed_at_any_depth() {
+        let body = json!({"old_token": "abc", "nested": [{"remote_key": "xyz", "name": "n"}]});
+
+        assert_eq!(
+            scrub(&body),
+            json!({"old_token": SCRUBBED, "nested": [{"remote_key": SCRUBBED, "name": "n"}]})
+        );
+    }
+
+    #[test]
+    fn path_excludes_host_and_scrubs_query_token() {
+        assert_eq!(
+            Cassette::path_of("http://localhost:1234/checklists.json?archived=true&token=abc"),
+            "/checklists.json?archived=true&token=%5Bscrubbed%5D"
+        );
+        assert_eq!(
+            Cassette::path_of("https://checkvist.com/checklists/1.json"),
+            "/checklists/1.json"
+        );
+    }
+}
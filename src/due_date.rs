@@ -0,0 +1,370 @@
+//! Resolves the relative and absolute due dates people write, eg. "tomorrow", "next friday",
+//! "in 3 days", "may 5", or "2023-05-01", into concrete dates.
+//!
+//! Resolution is always against an explicit "today" (see `DueDateResolver::at`), so
+//! results are deterministic for any given clock.
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, TimeZone, Weekday};
+
+use crate::{CheckvistError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DueDateResolver {
+    today: NaiveDate,
+}
+
+impl DueDateResolver {
+    /// Resolves dates relative to the date of `now` in its own time zone
+    pub fn at<Tz: TimeZone>(now: DateTime<Tz>) -> Self {
+        DueDateResolver {
+            today: now.date_naive(),
+        }
+    }
+
+    /// Resolves dates relative to `today`
+    pub fn for_date(today: NaiveDate) -> Self {
+        DueDateResolver { today }
+    }
+
+    /// Resolves dates relative to today in the system's local time zone
+    pub fn local() -> Self {
+        Self::at(chrono::Local::now())
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.today
+    }
+
+    /// Returns `CheckvistError::InvalidDueDateError` if `expression` isn't recognised.
+    ///
+    /// Recognised forms (case insensitive):
+    /// - `today`, `tomorrow`, `yesterday`
+    /// - a weekday (`friday`, `fri`, `this friday`): the next one after today
+    /// - `next friday`: the friday of next week (weeks start on Monday)
+    /// - `next week`, `next month`, `next year`: the first day of each
+    /// - `end of week`, `end of month`
+    /// - `in 3 days`, `3 weeks`, `+2`, `+2d`, `1w`, `2m`, `1y`
+    /// - `2023-05-01`, `2023/05/01`
+    /// - `may 5`, `5 may`, `5th may 2024`, `may 5, 2024`. Without a year, the next such date
+    ///   from today
+    pub fn resolve(&self, expression: &str) -> Result<NaiveDate> {
+        let normalised = expression.trim().to_lowercase().replace([',', '_'], " ");
+        let words: Vec<&str> = normalised.split_whitespace().collect();
+
+        self.resolve_words(&words)
+            .ok_or_else(|| CheckvistError::InvalidDueDateError {
+                expression: expression.to_string(),
+            })
+    }
+
+    fn resolve_words(&self, words: &[&str]) -> Option<NaiveDate> {
+        let today = self.today;
+        match words {
+            ["today" | "tod"] => Some(today),
+            ["tomorrow" | "tmr" | "tom"] => today.checked_add_days(Days::new(1)),
+            ["yesterday"] => today.checked_sub_days(Days::new(1)),
+            ["next", "week"] => self.start_of_week().checked_add_days(Days::new(7)),
+            ["next", "month"] => today.with_day(1)?.checked_add_months(Months::new(1)),
+            ["next", "year"] => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+            ["end", "of", "week"] => self.start_of_week().checked_add_days(Days::new(6)),
+            ["end", "of", "month"] => today
+                .with_day(1)?
+                .checked_add_months(Months::new(1))?
+                .pred_opt(),
+            ["next", weekday] => {
+                let weekday = parse_weekday(weekday)?;
+                self.start_of_week()
+                    .checked_add_days(Days::new(7 + weekday.num_days_from_monday() as u64))
+            }
+            ["this", weekday] | [weekday] if parse_weekday(weekday).is_some() => {
+                self.next_weekday(parse_weekday(weekday)?)
+            }
+            ["in", count, unit] => self.offset(count.parse().ok()?, unit),
+            [offset] => self
+                .resolve_compact_offset(offset)
+                .or_else(|| parse_iso_date(offset)),
+            [first, second] => first
+                .parse()
+                .ok()
+                .and_then(|count| self.offset(count, second))
+                .or_else(|| self.resolve_day_and_month(first, second, None)),
+            [first, second, year] => {
+                self.resolve_day_and_month(first, second, Some(year.parse().ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    /// +3, +3d, 3d, 2w, 1m, 1y
+    fn resolve_compact_offset(&self, offset: &str) -> Option<NaiveDate> {
+        let offset = offset.strip_prefix('+').unwrap_or(offset);
+        let split_at = offset
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(offset.len());
+        let (count, unit) = offset.split_at(split_at);
+        let count = count.parse().ok()?;
+        match unit {
+            "" => self.offset(count, "days"),
+            "d" | "w" | "m" | "y" => self.offset(count, unit),
+            _ => None,
+        }
+    }
+
+    fn offset(&self, count: u32, unit: &str) -> Option<NaiveDate> {
+        let today = self.today;
+        match unit {
+            "d" | "day" | "days" => today.checked_add_days(Days::new(count as u64)),
+            "w" | "week" | "weeks" => today.checked_add_days(Days::new(7 * count as u64)),
+            "m" | "month" | "months" => today.checked_add_months(Months::new(count)),
+            "y" | "year" | "years" => today.checked_add_months(Months::new(count.checked_mul(12)?)),
+            _ => None,
+        }
+    }
+
+    /// "may 5" or "5 may", with an optional year
+    fn resolve_day_and_month(
+        &self,
+        first: &str,
+        second: &str,
+        year: Option<i32>,
+    ) -> Option<NaiveDate> {
+        let (day, month) = match (parse_day(first), parse_month(second)) {
+            (Some(day), Some(month)) => (day, month),
+            _ => (parse_day(second)?, parse_month(first)?),
+        };
+        match year {
+            Some(year) => NaiveDate::from_ymd_opt(year, month, day),
+            None => {
+                let this_year = NaiveDate::from_ymd_opt(self.today.year(), month, day);
+                match this_year {
+                    Some(date) if date >= self.today => Some(date),
+                    _ => NaiveDate::from_ymd_opt(self.today.year() + 1, month, day),
+                }
+            }
+        }
+    }
+
+    fn start_of_week(&self) -> NaiveDate {
+        self.today - Days::new(self.today.weekday().num_days_from_monday() as u64)
+    }
+
+    /// The first `weekday` after today
+    fn next_weekday(&self, weekday: Weekday) -> Option<NaiveDate> {
+        let today = self.today.weekday().num_days_from_monday();
+        let target = weekday.num_days_from_monday();
+        let days_ahead = (target + 7 - today - 1) % 7 + 1;
+        self.today.checked_add_days(Days::new(days_ahead as u64))
+    }
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let weekday = match word {
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" | "tues" => Weekday::Tue,
+        "wednesday" | "wed" => Weekday::Wed,
+        "thursday" | "thu" | "thur" | "thurs" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" | "sat" => Weekday::Sat,
+        "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+fn parse_month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    if word.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(word))
+        .map(|index| index as u32 + 1)
+}
+
+/// "5", or "5th", "1st", etc
+fn parse_day(word: &str) -> Option<u32> {
+    let digits = word
+        .strip_suffix("st")
+        .or_else(|| word.strip_suffix("nd"))
+        .or_else(|| word.strip_suffix("rd"))
+        .or_else(|| word.strip_suffix("th"))
+        .unwrap_or(word);
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+fn parse_iso_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(word, "%Y/%m/%d"))
+        .ok()
+}
+
+/// Checkvist returns and accepts due dates as YYYY/MM/DD
+pub(crate) mod serde_format {
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde_json::Value;
+
+    const FORMAT: &str = "%Y/%m/%d";
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<NaiveDate>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_str(&date.format(FORMAT).to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Whether `value` is neither a due date nor empty
+    pub fn is_unrecognised(value: &Value) -> bool {
+        match value {
+            Value::Null => false,
+            Value::String(date) => !date.is_empty() && super::parse_iso_date(date).is_none(),
+            _ => true,
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<NaiveDate>, D::Error> {
+        let Some(date) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        if date.is_empty() {
+            return Ok(None);
+        }
+        super::parse_iso_date(&date)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid due date '{}'", date)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    // a Wednesday
+    fn resolver() -> DueDateResolver {
+        DueDateResolver::for_date(date(2023, 5, 10))
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn resolve(expression: &str) -> NaiveDate {
+        resolver().resolve(expression).unwrap()
+    }
+
+    #[test]
+    fn today_depends_on_time_zone() {
+        let utc_now = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2023, 5, 10, 23, 0, 0)
+            .unwrap();
+        let sydney_now = utc_now.with_timezone(&FixedOffset::east_opt(10 * 3600).unwrap());
+
+        assert_eq!(DueDateResolver::at(utc_now).today(), date(2023, 5, 10));
+        assert_eq!(DueDateResolver::at(sydney_now).today(), date(2023, 5, 11));
+    }
+
+    #[test]
+    fn resolves_relative_days() {
+        assert_eq!(resolve("today"), date(2023, 5, 10));
+        assert_eq!(resolve("Tomorrow"), date(2023, 5, 11));
+        assert_eq!(resolve("yesterday"), date(2023, 5, 9));
+    }
+
+    #[test]
+    fn resolves_weekdays() {
+        assert_eq!(resolve("friday"), date(2023, 5, 12));
+        assert_eq!(resolve("this fri"), date(2023, 5, 12));
+        assert_eq!(resolve("wed"), date(2023, 5, 17));
+        assert_eq!(resolve("monday"), date(2023, 5, 15));
+        assert_eq!(resolve("next friday"), date(2023, 5, 19));
+        assert_eq!(resolve("next monday"), date(2023, 5, 15));
+    }
+
+    #[test]
+    fn resolves_periods() {
+        assert_eq!(resolve("next week"), date(2023, 5, 15));
+        assert_eq!(resolve("next month"), date(2023, 6, 1));
+        assert_eq!(resolve("next year"), date(2024, 1, 1));
+        assert_eq!(resolve("end of week"), date(2023, 5, 14));
+        assert_eq!(resolve("end of month"), date(2023, 5, 31));
+    }
+
+    #[test]
+    fn resolves_offsets() {
+        assert_eq!(resolve("in 3 days"), date(2023, 5, 13));
+        assert_eq!(resolve("2 weeks"), date(2023, 5, 24));
+        assert_eq!(resolve("in 1 month"), date(2023, 6, 10));
+        assert_eq!(resolve("+2"), date(2023, 5, 12));
+        assert_eq!(resolve("1w"), date(2023, 5, 17));
+        assert_eq!(resolve("1y"), date(2024, 5, 10));
+    }
+
+    #[test]
+    fn month_offsets_clamp_to_month_end() {
+        let resolver = DueDateResolver::for_date(date(2023, 1, 31));
+
+        assert_eq!(resolver.resolve("in 1 month").unwrap(), date(2023, 2, 28));
+    }
+
+    #[test]
+    fn huge_offsets_are_errors() {
+        for expression in ["400000000y", "in 4000000000 years", "4000000000m"] {
+            assert!(
+                resolver().resolve(expression).is_err(),
+                "'{}' should not resolve",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn resolves_absolute_dates() {
+        assert_eq!(resolve("2023-06-01"), date(2023, 6, 1));
+        assert_eq!(resolve("2023/06/01"), date(2023, 6, 1));
+        assert_eq!(resolve("may 20"), date(2023, 5, 20));
+        assert_eq!(resolve("3rd Jan"), date(2024, 1, 3));
+        assert_eq!(resolve("May 5, 2025"), date(2025, 5, 5));
+        assert_eq!(resolve("5 may 2025"), date(2025, 5, 5));
+    }
+
+    #[test]
+    fn unrecognised_expressions_are_errors() {
+        for expression in [
+            "",
+            "someday",
+            "in three days",
+            "31 feb 2023",
+            "next blursday",
+        ] {
+            assert!(
+                matches!(
+                    resolver().resolve(expression),
+                    Err(CheckvistError::InvalidDueDateError { .. })
+                ),
+                "'{}' should not resolve",
+                expression
+            );
+        }
+    }
+}
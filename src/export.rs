@@ -0,0 +1,311 @@
+//! Rendering a list (or a subtree of one) as Markdown, OPML, indented text or JSON.
+//!
+//! All formats keep the order and nesting of tasks. Beyond that:
+//! - Markdown: closed tasks are checked, invalidated ones checked and struck through.
+//!   Tags and due dates are written as Checkvist smart syntax, and notes as
+//!   indented lines beneath their task
+//! - OPML: closed tasks have `_complete="true"` (as Workflowy does), notes are in
+//!   `_note`, and tags and due dates are as for Markdown
+//! - Text: content and tags only, indented two spaces per level
+//! - JSON: everything
+use std::fmt::Write;
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::markdown_sync::id_marker;
+use crate::outline::{Outline, OutlineItem};
+use crate::{CheckvistClient, CheckvistError, Result, TaskStatus};
+
+const INDENT: &str = "  ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Opml,
+    Text,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "opml" => Ok(Self::Opml),
+            "text" | "txt" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "'{}' isn't an export format. Use markdown, opml, text or json",
+                other
+            )),
+        }
+    }
+}
+
+impl CheckvistClient {
+    /// Renders the whole list in `format`
+    pub fn export_list(&self, list_id: u32, format: ExportFormat) -> Result<String> {
+        let list = self.get_list(list_id)?;
+        let tasks = self.get_tasks_with_notes(list_id)?;
+
+        Ok(export(&Outline::from_tasks(&list.name, &tasks), format))
+    }
+
+    /// Renders `task_id` and its descendants in `format`
+    pub fn export_subtree(
+        &self,
+        list_id: u32,
+        task_id: u32,
+        format: ExportFormat,
+    ) -> Result<String> {
+        let list = self.get_list(list_id)?;
+        let tasks = self.get_tasks_with_notes(list_id)?;
+        let outline = Outline::from_subtree(&list.name, &tasks, task_id)
+            .ok_or(CheckvistError::InvalidTaskError)?;
+
+        Ok(export(&outline, format))
+    }
+}
+
+pub fn export(outline: &Outline, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Markdown => to_markdown(outline),
+        ExportFormat::Opml => to_opml(outline),
+        ExportFormat::Text => to_text(outline),
+        ExportFormat::Json => to_json(outline),
+    }
+}
+
+pub fn to_markdown(outline: &Outline) -> String {
+    write_markdown(outline, false)
+}
+
+/// As `to_markdown`, but with each item's id in a hidden marker (see markdown_sync.rs)
+/// at the end of its first line. Used for files synced with a list
+pub fn to_markdown_with_ids(outline: &Outline) -> String {
+    write_markdown(outline, true)
+}
+
+fn write_markdown(outline: &Outline, with_ids: bool) -> String {
+    let mut markdown = format!("# {}\n\n", outline.title);
+    for (depth, item) in outline.walk() {
+        let indent = INDENT.repeat(depth);
+        let (checkbox, content) = match item.status {
+            TaskStatus::Open => (" ", content_with_smart_syntax(item)),
+            TaskStatus::Closed => ("x", content_with_smart_syntax(item)),
+            TaskStatus::Invalidated => ("x", format!("~~{}~~", content_with_smart_syntax(item))),
+        };
+        let mut lines = content.lines();
+        let marker = match item.id {
+            Some(id) if with_ids => format!(" {}", id_marker(id)),
+            _ => String::new(),
+        };
+        let _ = writeln!(
+            markdown,
+            "{}- [{}] {}{}",
+            indent,
+            checkbox,
+            lines.next().unwrap_or_default(),
+            marker
+        );
+        // continuation lines and notes are indented to the item's text
+        let continuation = format!("{}{}", indent, "      ");
+        for line in lines {
+            let _ = writeln!(markdown, "{}{}", continuation, line);
+        }
+        for note in &item.notes {
+            for line in note.lines() {
+                let _ = writeln!(markdown, "{}{}", continuation, line);
+            }
+        }
+    }
+
+    markdown
+}
+
+pub fn to_opml(outline: &Outline) -> String {
+    let mut opml =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    let _ = writeln!(
+        opml,
+        "{}<head>\n{}<title>{}</title>\n{}</head>\n{}<body>",
+        INDENT,
+        INDENT.repeat(2),
+        escape_xml(&outline.title),
+        INDENT,
+        INDENT
+    );
+    for item in &outline.items {
+        write_opml_item(&mut opml, item, 2);
+    }
+    let _ = writeln!(opml, "{}</body>\n</opml>", INDENT);
+
+    opml
+}
+
+fn write_opml_item(opml: &mut String, item: &OutlineItem, depth: usize) {
+    let indent = INDENT.repeat(depth);
+    let mut attributes = format!("text=\"{}\"", escape_xml(&content_with_smart_syntax(item)));
+    match item.status {
+        TaskStatus::Open => (),
+        TaskStatus::Closed => attributes.push_str(" _complete=\"true\""),
+        TaskStatus::Invalidated => {
+            attributes.push_str(" _complete=\"true\" _status=\"invalidated\"")
+        }
+    }
+    if !item.notes.is_empty() {
+        let _ = write!(
+            attributes,
+            " _note=\"{}\"",
+            escape_xml(&item.notes.join("\n\n"))
+        );
+    }
+
+    if item.children.is_empty() {
+        let _ = writeln!(opml, "{}<outline {}/>", indent, attributes);
+    } else {
+        let _ = writeln!(opml, "{}<outline {}>", indent, attributes);
+        for child in &item.children {
+            write_opml_item(opml, child, depth + 1);
+        }
+        let _ = writeln!(opml, "{}</outline>", indent);
+    }
+}
+
+pub fn to_text(outline: &Outline) -> String {
+    let mut text = String::new();
+    for (depth, item) in outline.walk() {
+        let mut content = item.content.replace('\n', " ");
+        for tag in item.tags.keys() {
+            let _ = write!(content, " #{}", tag);
+        }
+        let _ = writeln!(text, "{}{}", INDENT.repeat(depth), content);
+    }
+
+    text
+}
+
+pub fn to_json(outline: &Outline) -> String {
+    fn item_json(item: &OutlineItem) -> Value {
+        let status = match item.status {
+            TaskStatus::Open => "open",
+            TaskStatus::Closed => "closed",
+            TaskStatus::Invalidated => "invalidated",
+        };
+        json!({
+            "id": item.id,
+            "content": item.content,
+            "status": status,
+            "tags": item.tags.keys().collect::<Vec<_>>(),
+            "due": item.due.map(|due| due.format("%Y-%m-%d").to_string()),
+            "notes": item.notes,
+            "children": item.children.iter().map(item_json).collect::<Vec<_>>(),
+        })
+    }
+    let items: Vec<Value> = outline.items.iter().map(item_json).collect();
+
+    serde_json::to_string_pretty(&json!({ "title": outline.title, "tasks": items }))
+        .expect("outline JSON is always serialisable")
+}
+
+/// The content, with tags and due date appended as Checkvist smart syntax
+pub(crate) fn content_with_smart_syntax(item: &OutlineItem) -> String {
+    let mut content = item.content.clone();
+    for tag in item.tags.keys() {
+        let _ = write!(content, " #{}", tag);
+    }
+    if let Some(due) = item.due {
+        let _ = write!(content, " ^{}", due.format("%Y-%m-%d"));
+    }
+    content
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Tags;
+    use chrono::NaiveDate;
+
+    fn outline() -> Outline {
+        Outline {
+            title: "A & B".into(),
+            items: vec![
+                OutlineItem {
+                    content: "parent".into(),
+                    tags: Tags::from([("work".into(), false)]),
+                    notes: vec!["a note".into()],
+                    children: vec![OutlineItem {
+                        content: "done <child>".into(),
+                        status: TaskStatus::Closed,
+                        due: NaiveDate::from_ymd_opt(2023, 5, 1),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                OutlineItem {
+                    content: "dropped".into(),
+                    status: TaskStatus::Invalidated,
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn markdown_has_checkboxes_smart_syntax_and_notes() {
+        assert_eq!(
+            to_markdown(&outline()),
+            "# A & B\n\n\
+             - [ ] parent #work\n      a note\n\
+             \x20 - [x] done <child> ^2023-05-01\n\
+             - [x] ~~dropped~~\n"
+        );
+    }
+
+    #[test]
+    fn markdown_can_have_id_markers() {
+        let mut outline = outline();
+        outline.items[1].id = Some(42);
+
+        assert!(to_markdown_with_ids(&outline).ends_with("- [x] ~~dropped~~ <!-- cv:42 -->\n"));
+    }
+
+    #[test]
+    fn opml_is_escaped_and_nested() {
+        let opml = to_opml(&outline());
+
+        assert!(opml.contains("<title>A &amp; B</title>"));
+        assert!(opml.contains(
+            "    <outline text=\"parent #work\" _note=\"a note\">\n      <outline text=\"done &lt;child&gt; ^2023-05-01\" _complete=\"true\"/>\n    </outline>"
+        ));
+        assert!(opml.contains("_status=\"invalidated\""));
+    }
+
+    #[test]
+    fn text_is_indented() {
+        assert_eq!(
+            to_text(&outline()),
+            "parent #work\n  done <child>\ndropped\n"
+        );
+    }
+
+    #[test]
+    fn json_has_all_fields() {
+        let json: Value = serde_json::from_str(&to_json(&outline())).unwrap();
+
+        assert_eq!(json["tasks"][0]["notes"][0], "a note");
+        assert_eq!(json["tasks"][0]["children"][0]["status"], "closed");
+        assert_eq!(json["tasks"][0]["children"][0]["due"], "2023-05-01");
+        assert_eq!(json["tasks"][1]["status"], "invalidated");
+    }
+}
@@ -0,0 +1,141 @@
+//! Adding tasks so that retrying doesn't create duplicates.
+//!
+//! If the network fails after Checkvist has accepted a new task, the caller can't
+//! tell whether it was added, and a plain retry may add it twice. Adding with
+//! `CheckvistClient::add_task_idempotent` first looks for the task among the most
+//! recently created of its would-be siblings, and only adds it if it isn't there.
+use log::{info, warn};
+
+use crate::task_path::children_of;
+use crate::{CheckvistClient, CheckvistError, Result, Task};
+
+/// Tags marking a task with its idempotency key start with this
+pub const MARKER_TAG_PREFIX: &str = "idem-";
+/// How many of the newest siblings are checked for an existing copy of the task
+const RECENT_SIBLINGS: usize = 20;
+
+/// How to recognise a task that has already been added
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyKey {
+    /// The task is tagged with the key (prefixed with `MARKER_TAG_PREFIX`)
+    Marker(String),
+    /// The task's content is the key. No marker is added, but:
+    /// - a task can't be added if an identical one is among the parent's recent
+    ///   children, even if it's genuinely wanted twice (eg. a repeated errand)
+    /// - every add first fetches the whole list, with `get_tasks`
+    ///
+    /// So it suits retrying a request that may have reached Checkvist, but not
+    /// replaying what the user asked for (eg. captures queued while offline),
+    /// which should use a `Marker` generated when the task was first captured
+    Content,
+}
+
+impl IdempotencyKey {
+    /// The marker tag for the key, if any. Characters not allowed in tags are
+    /// replaced with '-'
+    pub fn marker_tag(&self) -> Option<String> {
+        let Self::Marker(key) = self else {
+            return None;
+        };
+        let key: String = key
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        Some(format!("{}{}", MARKER_TAG_PREFIX, key))
+    }
+
+    fn is_match(&self, existing: &Task, new: &Task) -> bool {
+        match self.marker_tag() {
+            Some(marker) => existing.tags.contains_key(&marker),
+            None => existing.content == new.content,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddOutcome {
+    Created(Task),
+    /// The task had already been added (by an earlier attempt)
+    AlreadyExisted(Task),
+}
+
+impl AddOutcome {
+    pub fn task(&self) -> &Task {
+        match self {
+            Self::Created(task) | Self::AlreadyExisted(task) => task,
+        }
+    }
+
+    pub fn was_created(&self) -> bool {
+        matches!(self, Self::Created(_))
+    }
+}
+
+impl CheckvistClient {
+    /// Adds `task` unless a task matching `key` is already among the recently
+    /// created children of its parent. If adding fails with a network error, the
+    /// task may have been added anyway, so it is looked for again before the error
+    /// is returned.
+    pub fn add_task_idempotent(
+        &self,
+        list_id: u32,
+        task: &Task,
+        key: &IdempotencyKey,
+    ) -> Result<AddOutcome> {
+        if let Some(existing) = self.find_added(list_id, task, key)? {
+            info!("Task with key {:?} already exists in list {}", key, list_id);
+            return Ok(AddOutcome::AlreadyExisted(existing));
+        }
+
+        let mut task = task.clone();
+        if let Some(marker) = key.marker_tag() {
+            task.tags.entry(marker).or_insert(false);
+        }
+        match self.add_task(list_id, &task) {
+            Ok(added) => Ok(AddOutcome::Created(added)),
+            Err(CheckvistError::NetworkError(err)) => {
+                warn!("Network error adding task, checking whether it was added");
+                match self.find_added(list_id, &task, key) {
+                    Ok(Some(existing)) => Ok(AddOutcome::Created(existing)),
+                    _ => Err(CheckvistError::NetworkError(err)),
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn find_added(&self, list_id: u32, task: &Task, key: &IdempotencyKey) -> Result<Option<Task>> {
+        let tasks = self.get_tasks(list_id)?;
+        let mut siblings = children_of(&tasks, task.parent_id.filter(|id| *id != 0));
+        // ids increase, so the highest are the most recently created
+        siblings.sort_by_key(|sibling| std::cmp::Reverse(sibling.id));
+
+        Ok(siblings
+            .into_iter()
+            .take(RECENT_SIBLINGS)
+            .find(|sibling| key.is_match(sibling, task))
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn marker_tag_is_a_valid_tag() {
+        let key = IdempotencyKey::Marker("capture 2023/05/01#3".into());
+
+        assert_eq!(
+            key.marker_tag(),
+            Some("idem-capture-2023-05-01-3".to_string())
+        );
+        assert_eq!(IdempotencyKey::Content.marker_tag(), None);
+    }
+}
@@ -0,0 +1,694 @@
+//! Importing outlines from OPML (eg. Workflowy exports) and Markdown nested lists
+//! (eg. Obsidian notes), and adding them to a Checkvist list.
+//!
+//! Markdown items may be bulleted with '-', '*', '+' or numbers, indented with
+//! spaces or tabs, and have `[ ]` or `[x]` checkboxes. Checked items are closed
+//! (and invalidated if also struck through with `~~`). Lines indented beneath an
+//! item's text are its notes. Headings become top level items, with any following
+//! list items nested beneath them, except for a first level heading at the very
+//! start, which is taken as the outline's title. An item ending with a task id
+//! marker (see markdown_sync.rs) has that id.
+//!
+//! Plain indented text (`parse_indented`) is more forgiving: every non-empty line
+//! is an item, nested by its indentation, with an optional `-` or `*` bullet.
+//!
+//! In Markdown and OPML, Checkvist smart syntax in the text (`#tag`, `^due`) is
+//! parsed into tags and due dates. In indented text, only if asked.
+use std::fmt::{self, Write};
+
+use log::info;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::due_date::DueDateResolver;
+use crate::export::content_with_smart_syntax;
+use crate::markdown_sync::strip_id_marker;
+use crate::outline::{Outline, OutlineItem};
+use crate::smart_syntax;
+use crate::task_path::children_of;
+use crate::{CheckvistClient, CheckvistError, CheckvistLocation, Result, Task, TaskStatus};
+
+const TAB_WIDTH: usize = 4;
+
+/// Parses a Markdown nested list, resolving due dates against `resolver`
+pub fn parse_markdown(markdown: &str, resolver: &DueDateResolver) -> Result<Outline> {
+    let mut outline = Outline::default();
+    // (indent, path of child indexes from the outline root) of each open item
+    let mut open: Vec<(usize, Vec<usize>)> = Vec::new();
+    // path to the latest heading, which list items that follow are nested beneath
+    let mut under_heading: Option<Vec<usize>> = None;
+    let mut seen_content = false;
+    let mut previous_was_blank = false;
+
+    for (index, line) in markdown.lines().enumerate() {
+        let line_number = index + 1;
+        let after_blank = std::mem::replace(&mut previous_was_blank, line.trim().is_empty());
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = indent_width(line);
+        let text = line.trim();
+
+        if let Some(heading) = heading_text(text) {
+            if !seen_content && text.starts_with("# ") && outline.title.is_empty() {
+                outline.title = heading.to_string();
+            } else {
+                outline
+                    .items
+                    .push(parse_item_text(heading, TaskStatus::Open, resolver));
+                under_heading = Some(vec![outline.items.len() - 1]);
+                open.clear();
+            }
+            seen_content = true;
+            continue;
+        }
+        seen_content = true;
+
+        let (text, id) = strip_id_marker(text);
+        let Some((status, item_text)) = list_item(text) else {
+            // a note, if indented beneath an item's text
+            match open.last() {
+                Some((item_indent, path)) if indent > *item_indent => {
+                    let item = item_at(&mut outline, path);
+                    match item.notes.last_mut() {
+                        Some(note) if !after_blank => {
+                            note.push('\n');
+                            note.push_str(text);
+                        }
+                        _ => item.notes.push(text.to_string()),
+                    }
+                    continue;
+                }
+                _ => {
+                    return Err(CheckvistError::ImportParseError {
+                        line: line_number,
+                        message: format!("expected a list item, found '{}'", text),
+                    })
+                }
+            }
+        };
+
+        while matches!(open.last(), Some((open_indent, _)) if *open_indent >= indent) {
+            open.pop();
+        }
+        let mut item = parse_item_text(item_text, status, resolver);
+        item.id = id;
+        let path = match (open.last(), &under_heading) {
+            (Some((_, parent)), _) => add_child(&mut outline, parent, item),
+            (None, Some(heading)) => add_child(&mut outline, heading, item),
+            (None, None) => {
+                outline.items.push(item);
+                vec![outline.items.len() - 1]
+            }
+        };
+        open.push((indent, path));
+    }
+
+    Ok(outline)
+}
+
+/// Parses indented lines, each an item beneath the nearest less indented line before it.
+/// Unlike `parse_markdown` there are no checkboxes, headings or notes, so any text parses.
+/// Smart syntax is parsed only if `smart`, otherwise each line is an item's content as written
+pub fn parse_indented(text: &str, smart: bool, resolver: &DueDateResolver) -> Outline {
+    let mut outline = Outline::default();
+    // (indent, path of child indexes from the outline root) of each open item
+    let mut open: Vec<(usize, Vec<usize>)> = Vec::new();
+
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let indent = indent_width(line);
+        let text = line.trim();
+        let text = text
+            .strip_prefix("- ")
+            .or_else(|| text.strip_prefix("* "))
+            .unwrap_or(text)
+            .trim_start();
+
+        while matches!(open.last(), Some((open_indent, _)) if *open_indent >= indent) {
+            open.pop();
+        }
+        let item = if smart {
+            parse_item_text(text, TaskStatus::Open, resolver)
+        } else {
+            OutlineItem::new(text)
+        };
+        let path = match open.last() {
+            Some((_, parent)) => add_child(&mut outline, parent, item),
+            None => {
+                outline.items.push(item);
+                vec![outline.items.len() - 1]
+            }
+        };
+        open.push((indent, path));
+    }
+
+    outline
+}
+
+/// Parses an OPML document, resolving due dates against `resolver`
+pub fn parse_opml(opml: &str, resolver: &DueDateResolver) -> Result<Outline> {
+    let mut reader = Reader::from_str(opml);
+    reader.trim_text(true);
+    let mut outline = Outline::default();
+    // path of child indexes to each open <outline>
+    let mut open: Vec<Vec<usize>> = Vec::new();
+    let mut in_title = false;
+    let mut in_body = false;
+
+    loop {
+        let event = reader.read_event();
+        // the end of the element (or wherever parsing failed)
+        let position = reader.buffer_position();
+        let error = |message: String| CheckvistError::ImportParseError {
+            line: line_at(opml, position),
+            message,
+        };
+        match event {
+            Err(err) => return Err(error(err.to_string())),
+            Ok(Event::Eof) => break,
+            Ok(Event::Start(element)) => match element.name().as_ref() {
+                b"title" => in_title = true,
+                b"body" => in_body = true,
+                b"outline" if in_body => {
+                    let item = opml_item(&element, resolver).map_err(error)?;
+                    let path = add_opml_item(&mut outline, &open, item);
+                    open.push(path);
+                }
+                b"outline" => return Err(error("<outline> outside <body>".into())),
+                _ => (),
+            },
+            Ok(Event::Empty(element)) => match element.name().as_ref() {
+                b"outline" if in_body => {
+                    let item = opml_item(&element, resolver).map_err(error)?;
+                    add_opml_item(&mut outline, &open, item);
+                }
+                b"outline" => return Err(error("<outline> outside <body>".into())),
+                _ => (),
+            },
+            Ok(Event::End(element)) => match element.name().as_ref() {
+                b"title" => in_title = false,
+                b"body" => in_body = false,
+                b"outline" => {
+                    open.pop();
+                }
+                _ => (),
+            },
+            Ok(Event::Text(text)) if in_title => {
+                outline.title = text
+                    .unescape()
+                    .map_err(|err| error(err.to_string()))?
+                    .to_string();
+            }
+            Ok(_) => (),
+        }
+    }
+
+    Ok(outline)
+}
+
+/// What an import added
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Ids of the new tasks, in outline order (depth first)
+    pub task_ids: Vec<u32>,
+    /// Whether all tasks were added with a single request
+    pub bulk: bool,
+}
+
+/// An import that failed partway
+#[derive(Debug)]
+pub struct ImportError {
+    pub error: CheckvistError,
+    /// Ids of the tasks known to have been added before the failure, in outline order
+    pub task_ids: Vec<u32>,
+    /// Whether any tasks may have been added, including any whose ids aren't known
+    /// (eg. if the response to the bulk request was lost)
+    pub may_have_added: bool,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Import failed after {} task(s) were added: {}",
+            self.task_ids.len(),
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl CheckvistClient {
+    /// Adds every item in `outline` at `location`, after anything already there.
+    /// Where possible (when no item has more than one line of content, or content Checkvist
+    /// would take as smart syntax) the whole outline is sent in one request. Statuses and
+    /// notes are then set item by item
+    pub fn import_outline(
+        &self,
+        outline: &Outline,
+        location: &CheckvistLocation,
+    ) -> std::result::Result<ImportReport, ImportError> {
+        let walked = outline.walk();
+        // a bulk import parses smart syntax, so would turn content such as "#42" into a tag
+        let bulk = walked.iter().all(|(_depth, item)| {
+            !item.content.contains('\n') && !smart_syntax::parse(&item.content).has_smart_syntax()
+        });
+        info!(
+            "Importing {} items to {:?} ({})",
+            walked.len(),
+            location,
+            if bulk { "in bulk" } else { "one by one" }
+        );
+        let mut task_ids = Vec::new();
+        let added = if bulk {
+            self.import_in_bulk(outline, location, &mut task_ids)
+        } else {
+            self.import_one_by_one(outline, location, &mut task_ids)
+        };
+        let added = added.and_then(|()| {
+            for ((_depth, item), task_id) in walked.iter().zip(&task_ids) {
+                if item.status != TaskStatus::Open {
+                    self.set_task_status(location.list_id, *task_id, item.status)?;
+                }
+                for note in &item.notes {
+                    self.add_note(location.list_id, *task_id, note)?;
+                }
+            }
+            Ok(())
+        });
+
+        match added {
+            Ok(()) => Ok(ImportReport { task_ids, bulk }),
+            Err(error) => Err(ImportError {
+                may_have_added: !task_ids.is_empty() || error.may_have_succeeded(),
+                error,
+                task_ids,
+            }),
+        }
+    }
+
+    fn import_in_bulk(
+        &self,
+        outline: &Outline,
+        location: &CheckvistLocation,
+        task_ids: &mut Vec<u32>,
+    ) -> Result<()> {
+        let mut content = String::new();
+        for (depth, item) in outline.walk() {
+            let _ = writeln!(
+                content,
+                "{}{}",
+                "  ".repeat(depth),
+                content_with_smart_syntax(item)
+            );
+        }
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &location.list_id.to_string(),
+                "/import.json",
+            ],
+        );
+        let response = self
+            .checkvist_post(
+                url,
+                serde_json::json!({
+                    "import_content": content,
+                    "parent_id": location.parent_task_id,
+                    "parse_tasks": true,
+                }),
+            )?
+            .into_json()?;
+        let created: Vec<Task> = self.to_results(response)?;
+        task_ids.extend(created.iter().filter_map(|task| task.id));
+
+        if task_ids.len() != outline.len() {
+            return Err(CheckvistError::UnknownError {
+                message: format!(
+                    "Imported {} items, but Checkvist created {} tasks",
+                    outline.len(),
+                    task_ids.len()
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    fn import_one_by_one(
+        &self,
+        outline: &Outline,
+        location: &CheckvistLocation,
+        task_ids: &mut Vec<u32>,
+    ) -> Result<()> {
+        let existing = self.get_tasks(location.list_id)?;
+        let first_position = children_of(&existing, location.parent_task_id).len() as u16 + 1;
+        self.add_items(
+            &outline.items,
+            location.list_id,
+            location.parent_task_id,
+            first_position,
+            task_ids,
+        )
+    }
+
+    fn add_items(
+        &self,
+        items: &[OutlineItem],
+        list_id: u32,
+        parent_id: Option<u32>,
+        first_position: u16,
+        task_ids: &mut Vec<u32>,
+    ) -> Result<()> {
+        for (index, item) in items.iter().enumerate() {
+            let task = item.to_task(first_position + index as u16, parent_id);
+            let task_id =
+                self.add_task(list_id, &task)?
+                    .id
+                    .ok_or(CheckvistError::UnknownError {
+                        message: "Checkvist returned a new task without an id".into(),
+                    })?;
+            task_ids.push(task_id);
+            self.add_items(&item.children, list_id, Some(task_id), 1, task_ids)?;
+        }
+        Ok(())
+    }
+}
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+fn heading_text(text: &str) -> Option<&str> {
+    let hashes = text.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&hashes) && text[hashes..].starts_with(' ') {
+        Some(text[hashes..].trim())
+    } else {
+        None
+    }
+}
+
+/// The status and text of a list item line (already trimmed), or None if it isn't one
+fn list_item(text: &str) -> Option<(TaskStatus, &str)> {
+    let rest = if let Some(rest) = text
+        .strip_prefix("- ")
+        .or_else(|| text.strip_prefix("* "))
+        .or_else(|| text.strip_prefix("+ "))
+    {
+        rest
+    } else {
+        let digits = text.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        text[digits..]
+            .strip_prefix(". ")
+            .or_else(|| text[digits..].strip_prefix(") "))?
+    };
+    let rest = rest.trim_start();
+
+    if let Some(checked) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        let checked = checked.trim();
+        match checked
+            .strip_prefix("~~")
+            .and_then(|text| text.strip_suffix("~~"))
+        {
+            Some(struck) => Some((TaskStatus::Invalidated, struck)),
+            None => Some((TaskStatus::Closed, checked)),
+        }
+    } else if let Some(open) = rest.strip_prefix("[ ]") {
+        Some((TaskStatus::Open, open.trim()))
+    } else {
+        Some((TaskStatus::Open, rest))
+    }
+}
+
+fn parse_item_text(text: &str, status: TaskStatus, resolver: &DueDateResolver) -> OutlineItem {
+    let task = smart_syntax::parse(text).to_task(1, None, resolver);
+    OutlineItem {
+        content: task.content,
+        status,
+        tags: task.tags,
+        due: task.due,
+        ..Default::default()
+    }
+}
+
+fn item_at<'a>(outline: &'a mut Outline, path: &[usize]) -> &'a mut OutlineItem {
+    let mut item = &mut outline.items[path[0]];
+    for index in &path[1..] {
+        item = &mut item.children[*index];
+    }
+    item
+}
+
+/// Adds `item` as the last child of the item at `parent`, returning its path
+fn add_child(outline: &mut Outline, parent: &[usize], item: OutlineItem) -> Vec<usize> {
+    let parent_item = item_at(outline, parent);
+    parent_item.children.push(item);
+    let mut path = parent.to_vec();
+    path.push(parent_item.children.len() - 1);
+    path
+}
+
+fn add_opml_item(outline: &mut Outline, open: &[Vec<usize>], item: OutlineItem) -> Vec<usize> {
+    match open.last() {
+        Some(parent) => add_child(outline, parent, item),
+        None => {
+            outline.items.push(item);
+            vec![outline.items.len() - 1]
+        }
+    }
+}
+
+fn opml_item(
+    element: &BytesStart,
+    resolver: &DueDateResolver,
+) -> std::result::Result<OutlineItem, String> {
+    let mut text = None;
+    let mut note = None;
+    let mut complete = false;
+    let mut invalidated = false;
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(|err| err.to_string())?;
+        let value = attribute.unescape_value().map_err(|err| err.to_string())?;
+        match attribute.key.as_ref() {
+            b"text" => text = Some(value.to_string()),
+            b"_note" => note = Some(value.to_string()),
+            b"_complete" => complete = value == "true",
+            b"_status" => invalidated = value == "invalidated",
+            _ => (),
+        }
+    }
+    let Some(text) = text else {
+        return Err("<outline> has no text attribute".into());
+    };
+    let status = match (complete, invalidated) {
+        (_, true) => TaskStatus::Invalidated,
+        (true, false) => TaskStatus::Closed,
+        (false, false) => TaskStatus::Open,
+    };
+
+    let mut item = parse_item_text(&text, status, resolver);
+    item.notes = note
+        .map(|note| {
+            note.split("\n\n")
+                .map(str::to_string)
+                .filter(|n| !n.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(item)
+}
+
+/// The (1 based) line number of the byte at `position`
+fn line_at(text: &str, position: usize) -> usize {
+    text.as_bytes()[..position.min(text.len())]
+        .iter()
+        .filter(|b| **b == b'\n')
+        .count()
+        + 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn resolver() -> DueDateResolver {
+        DueDateResolver::for_date(NaiveDate::from_ymd_opt(2023, 5, 10).unwrap())
+    }
+
+    fn contents(outline: &Outline) -> Vec<(usize, &str, TaskStatus)> {
+        outline
+            .walk()
+            .into_iter()
+            .map(|(depth, item)| (depth, item.content.as_str(), item.status))
+            .collect()
+    }
+
+    #[test]
+    fn parses_nested_markdown_with_checkboxes() {
+        let markdown = "# Project\n\n- [ ] one\n\t- [x] two #tag ^2023-05-01\n\t* three\n      with a note\n+ [X] ~~four~~\n1. five\n";
+
+        let outline = parse_markdown(markdown, &resolver()).unwrap();
+
+        assert_eq!(outline.title, "Project");
+        assert_eq!(
+            contents(&outline),
+            vec![
+                (0, "one", TaskStatus::Open),
+                (1, "two", TaskStatus::Closed),
+                (1, "three", TaskStatus::Open),
+                (0, "four", TaskStatus::Invalidated),
+                (0, "five", TaskStatus::Open),
+            ]
+        );
+        let two = &outline.items[0].children[0];
+        assert_eq!(two.tags.keys().collect::<Vec<_>>(), vec!["tag"]);
+        assert_eq!(two.due, NaiveDate::from_ymd_opt(2023, 5, 1));
+        assert_eq!(outline.items[0].children[1].notes, vec!["with a note"]);
+    }
+
+    #[test]
+    fn blank_lines_separate_notes() {
+        let markdown = "- item\n  first note\n  continued\n\n  second note\n";
+
+        let outline = parse_markdown(markdown, &resolver()).unwrap();
+
+        assert_eq!(
+            outline.items[0].notes,
+            vec!["first note\ncontinued", "second note"]
+        );
+    }
+
+    #[test]
+    fn headings_become_parents() {
+        let outline = parse_markdown("intro:\n", &resolver()).unwrap_err();
+        assert!(matches!(
+            outline,
+            CheckvistError::ImportParseError { line: 1, .. }
+        ));
+
+        let outline = parse_markdown("## Inbox\n- a\n  - b\n## Later\n- c\n", &resolver()).unwrap();
+
+        assert_eq!(outline.title, "");
+        assert_eq!(
+            contents(&outline),
+            vec![
+                (0, "Inbox", TaskStatus::Open),
+                (1, "a", TaskStatus::Open),
+                (2, "b", TaskStatus::Open),
+                (0, "Later", TaskStatus::Open),
+                (1, "c", TaskStatus::Open),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_errors_have_line_numbers() {
+        let result = parse_markdown("- one\n\nnot an item\n", &resolver());
+
+        let Err(CheckvistError::ImportParseError { line, message }) = result else {
+            panic!("expected a parse error, got {:?}", result);
+        };
+        assert_eq!(line, 3);
+        assert!(message.contains("not an item"));
+    }
+
+    #[test]
+    fn parses_indented_text() {
+        let text = "Meeting actions\n  - email Jo #work ^tomorrow\n  * book room\n      chairs\nfollow up\n\tlater\n  sooner\n";
+
+        let outline = parse_indented(text, true, &resolver());
+
+        assert_eq!(
+            contents(&outline),
+            vec![
+                (0, "Meeting actions", TaskStatus::Open),
+                (1, "email Jo", TaskStatus::Open),
+                (1, "book room", TaskStatus::Open),
+                (2, "chairs", TaskStatus::Open),
+                (0, "follow up", TaskStatus::Open),
+                (1, "later", TaskStatus::Open),
+                (1, "sooner", TaskStatus::Open),
+            ]
+        );
+        assert_eq!(outline.items[0].children[0].tags.keys().collect::<Vec<_>>(), vec!["work"]);
+        assert_eq!(
+            outline.items[0].children[0].due,
+            NaiveDate::from_ymd_opt(2023, 5, 11)
+        );
+    }
+
+    #[test]
+    fn indented_text_is_as_written_without_smart() {
+        let outline = parse_indented(
+            "email Jo #work ^tomorrow\n  - book room",
+            false,
+            &resolver(),
+        );
+
+        assert_eq!(outline.items[0].content, "email Jo #work ^tomorrow");
+        assert!(outline.items[0].tags.is_empty());
+        assert_eq!(outline.items[0].due, None);
+        assert_eq!(outline.items[0].children[0].content, "book room");
+    }
+
+    #[test]
+    fn parses_opml() {
+        let opml = r#"<?xml version="1.0"?>
+<opml version="2.0">
+  <head><title>Work &amp; play</title></head>
+  <body>
+    <outline text="one" _note="a note">
+      <outline text="two" _complete="true"/>
+    </outline>
+    <outline text="three"/>
+  </body>
+</opml>"#;
+
+        let outline = parse_opml(opml, &resolver()).unwrap();
+
+        assert_eq!(outline.title, "Work & play");
+        assert_eq!(
+            contents(&outline),
+            vec![
+                (0, "one", TaskStatus::Open),
+                (1, "two", TaskStatus::Closed),
+                (0, "three", TaskStatus::Open),
+            ]
+        );
+        assert_eq!(outline.items[0].notes, vec!["a note"]);
+    }
+
+    #[test]
+    fn opml_errors_have_line_numbers() {
+        let opml = "<opml>\n<body>\n<outline text=\"a\">\n<outline note=\"no text\"/>\n</outline>\n</body>\n</opml>";
+
+        let result = parse_opml(opml, &resolver());
+
+        assert!(matches!(
+            result,
+            Err(CheckvistError::ImportParseError { line: 4, .. })
+        ));
+        assert!(matches!(
+            parse_opml(
+                "<opml><body><outline text=\"a\"></body></opml>",
+                &resolver()
+            ),
+            Err(CheckvistError::ImportParseError { line: 1, .. })
+        ));
+    }
+}
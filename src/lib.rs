@@ -1,19 +1,48 @@
+pub mod batch;
+pub mod cassette;
+pub mod due_date;
+pub mod export;
+pub mod idempotent;
+pub mod import;
+pub mod markdown_sync;
+pub mod mirror;
+pub mod outline;
+pub mod search;
+pub mod smart_syntax;
+pub mod subtree;
+pub mod task_path;
+
 use core::fmt;
-use log::{error, info};
-use std::cell::RefCell;
+use log::{error, info, warn};
+use std::sync::Mutex;
 use std::collections::HashMap;
 use std::vec;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-#[derive(PartialEq, Eq, Debug, Deserialize, Serialize)]
+use cassette::Cassette;
+use due_date::DueDateResolver;
+
+/// Fields Checkvist returned that a model doesn't declare (including any added to
+/// the API after this version of cvapi). They're kept, and serialised along with
+/// the declared fields, so a model that's fetched, changed and sent back as an
+/// update loses nothing.
+pub type ExtraFields = serde_json::Map<String, serde_json::Value>;
+
+/// A task's tags: each tag's name, and whether it's private
+pub type Tags = std::collections::BTreeMap<String, bool>;
+
+#[derive(PartialEq, Eq, Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Checklist {
     pub id: u32,
     pub name: String,
     // TODO: convert to a date type of some sort when needed
     pub updated_at: String,
     pub task_count: u16,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Generic location of an item in a Checkvist list.
@@ -23,13 +52,170 @@ pub struct CheckvistLocation {
     pub parent_task_id: Option<u32>,
 }
 
-#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+// the derived impls are wrapped below, to check the due date first
+#[serde(remote = "Self")]
 pub struct Task {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<u32>,
     pub content: String,
     pub position: u16,
     pub parent_id: Option<u32>,
+    // Checkvist returns `due`, but expects `due_date` when adding or updating
+    #[serde(
+        rename = "due_date",
+        alias = "due",
+        default,
+        with = "due_date::serde_format",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub due: Option<NaiveDate>,
+    #[serde(
+        default,
+        deserialize_with = "tags_format::deserialize",
+        skip_serializing_if = "Tags::is_empty"
+    )]
+    pub tags: Tags,
+    /// ids of the users (see `CheckvistClient::get_collaborators`) the task is assigned to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assignee_ids: Vec<u32>,
+    // Checkvist ignores status when adding or updating tasks. Use close_task etc.
+    #[serde(default, skip_serializing_if = "TaskStatus::is_open")]
+    pub status: TaskStatus,
+    /// Only populated by `CheckvistClient::get_tasks_with_notes`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+impl Serialize for Task {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        Task::serialize(self, serializer)
+    }
+}
+
+/// A due date that can't be understood doesn't fail the whole task (and so the
+/// whole list). The task has no due date, and the value is kept in `extra`
+impl<'de> Deserialize<'de> for Task {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let mut fields = ExtraFields::deserialize(deserializer)?;
+        let unrecognised_due = ["due", "due_date"]
+            .into_iter()
+            .find(|key| {
+                fields
+                    .get(*key)
+                    .is_some_and(due_date::serde_format::is_unrecognised)
+            })
+            .and_then(|key| fields.remove_entry(key));
+        let mut task = Task::deserialize(serde_json::Value::Object(fields))
+            .map_err(serde::de::Error::custom)?;
+        if let Some((key, due)) = unrecognised_due {
+            warn!(
+                "Task {:?} has a due date that can't be understood: {}",
+                task.id, due
+            );
+            task.extra.insert(key, due);
+        }
+        Ok(task)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum TaskStatus {
+    #[default]
+    Open,
+    Closed,
+    Invalidated,
+}
+
+impl TaskStatus {
+    pub fn is_open(&self) -> bool {
+        *self == TaskStatus::Open
+    }
+}
+
+impl TryFrom<u8> for TaskStatus {
+    type Error = String;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TaskStatus::Open),
+            1 => Ok(TaskStatus::Closed),
+            2 => Ok(TaskStatus::Invalidated),
+            other => Err(format!("unknown task status {}", other)),
+        }
+    }
+}
+
+impl From<TaskStatus> for u8 {
+    fn from(status: TaskStatus) -> Self {
+        match status {
+            TaskStatus::Open => 0,
+            TaskStatus::Closed => 1,
+            TaskStatus::Invalidated => 2,
+        }
+    }
+}
+
+/// A note on a task. Checkvist's API calls these comments
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Note {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+    pub comment: String,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// Checkvist returns tags as a map of tag name to a boolean (whether the tag
+/// is private). They're sent back the same way, so privacy isn't lost when a
+/// fetched task is updated. A comma-separated string or a list of names is read
+/// as public tags.
+mod tags_format {
+    use serde::{Deserialize, Deserializer};
+
+    use super::Tags;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TagsInput {
+        Text(String),
+        Map(Tags),
+        List(Vec<String>),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Tags, D::Error> {
+        let tags = match Option::<TagsInput>::deserialize(deserializer)? {
+            None => Tags::new(),
+            Some(TagsInput::Text(text)) => text
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| (tag.to_string(), false))
+                .collect(),
+            Some(TagsInput::Map(map)) => map,
+            Some(TagsInput::List(list)) => list.into_iter().map(|tag| (tag, false)).collect(),
+        };
+        Ok(tags)
+    }
+}
+
+/// A Checkvist user, eg. a collaborator on a shared list
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct User {
+    pub id: u32,
+    pub email: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Deserialize)]
@@ -39,19 +225,38 @@ struct ApiToken {
 
 type Result<T> = std::result::Result<T, CheckvistError>;
 
-// TODO: check all the variant sizes - clippy complains this is too large
-//https://rust-lang.github.io/rust-clippy/master/index.html#result_large_err
-// clippy ` cargo clippy --workspace -- -A "clippy::result_large_err"` for now
 #[derive(Debug)]
 pub enum CheckvistError {
     InvalidParentIdError,
     InvalidListError,
     InvalidTaskError,
     UnknownError { message: String },
-    NetworkError(ureq::Error),
+    // boxed, as ureq's error is many times the size of the others, and every
+    // Result would be that size too
+    //https://rust-lang.github.io/rust-clippy/master/index.html#result_large_err
+    NetworkError(Box<ureq::Error>),
     // used by serde_json for decoding errors
     IoError(std::io::Error),
     TokenRefreshFailedError,
+    // local mirror database errors
+    DatabaseError(rusqlite::Error),
+    InvalidDueDateError { expression: String },
+    /// The list or task exists, but the user isn't allowed to do this to it
+    PermissionDeniedError { message: String },
+    /// Checkvist has no user matching the one requested (eg. when sharing a list)
+    UnknownUserError { message: String },
+    /// A search query that can't be used, eg. an invalid regex
+    InvalidQueryError { message: String },
+    /// A path of list and task names (see task_path.rs) that doesn't lead anywhere
+    InvalidPathError { path: String, message: String },
+    /// A name in a path matches more than one list or task
+    AmbiguousPathError { path: String, name: String, count: usize },
+    /// Checkvist is refusing requests for now. Retry after the given time, if any
+    RateLimitedError { retry_after: Option<std::time::Duration> },
+    /// An outline (see import.rs) that can't be parsed
+    ImportParseError { line: usize, message: String },
+    /// A task can't be moved beneath itself or one of its descendants
+    MoveIntoSubtreeError { task_id: u32 },
 }
 
 impl fmt::Display for CheckvistError {
@@ -64,6 +269,16 @@ impl fmt::Display for CheckvistError {
             Self::InvalidParentIdError => write!(f, "You  tried to add a task to a parent task that can't be found"),
             Self::UnknownError { ref message } => write!(f, "{}", message),
             Self::TokenRefreshFailedError => write!(f, "Could not refresh token"),
+            Self::DatabaseError(ref err) => write!(f, "Local mirror database error: {}", err),
+            Self::InvalidDueDateError { ref expression } => write!(f, "'{}' isn't a due date that can be understood", expression),
+            Self::PermissionDeniedError { ref message } => write!(f, "You don't have permission to do that: {}", message),
+            Self::UnknownUserError { ref message } => write!(f, "Checkvist couldn't find that user: {}", message),
+            Self::InvalidQueryError { ref message } => write!(f, "Invalid search: {}", message),
+            Self::InvalidPathError { ref path, ref message } => write!(f, "Invalid path '{}': {}", path, message),
+            Self::RateLimitedError { .. } => write!(f, "Checkvist is receiving too many requests. Try again later"),
+            Self::AmbiguousPathError { ref path, ref name, count } => write!(f, "'{}' in path '{}' matches {} items. Rename them to make it unique", name, path, count),
+            Self::ImportParseError { line, ref message } => write!(f, "Couldn't import line {}: {}", line, message),
+            Self::MoveIntoSubtreeError { task_id } => write!(f, "Task {} can't be moved beneath itself or one of its descendants", task_id),
         }
     }
 }
@@ -72,16 +287,42 @@ impl std::error::Error for CheckvistError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             Self::IoError(ref err) => Some(err),
-            Self::NetworkError(ref err) => Some(err),
+            Self::NetworkError(ref err) => Some(err.as_ref()),
+            Self::DatabaseError(ref err) => Some(err),
             Self::TokenRefreshFailedError => None,
             _ => None,
         }
     }
 }
 
+impl CheckvistError {
+    /// Whether Checkvist may have carried out the failed request anyway: a network
+    /// error after the request could have been sent (eg. the connection dropped
+    /// while waiting for the response), rather than one connecting
+    pub fn may_have_succeeded(&self) -> bool {
+        match self {
+            Self::NetworkError(err) => !matches!(
+                err.kind(),
+                ureq::ErrorKind::Dns
+                    | ureq::ErrorKind::ConnectionFailed
+                    | ureq::ErrorKind::ProxyConnect
+                    | ureq::ErrorKind::InvalidUrl
+                    | ureq::ErrorKind::UnknownScheme
+            ),
+            _ => false,
+        }
+    }
+}
+
 impl From<ureq::Error> for CheckvistError {
     fn from(err: ureq::Error) -> Self {
         match err {
+            ureq::Error::Status(429, response) => CheckvistError::RateLimitedError {
+                retry_after: response
+                    .header("Retry-After")
+                    .and_then(|seconds| seconds.trim().parse().ok())
+                    .map(std::time::Duration::from_secs),
+            },
             ureq::Error::Status(status, response) => {
                 let Ok(response_json) = response.into_json::<HashMap<String, String>>() else {
                     return CheckvistError::UnknownError {
@@ -90,25 +331,57 @@ impl From<ureq::Error> for CheckvistError {
                 };
                 let default_msg = String::new();
                 let message = response_json.get("message").unwrap_or(&default_msg);
-                if status == 403
-                    && message.contains("The list doesn't exist or is not available to you")
-                {
-                    CheckvistError::InvalidListError
-                } else if status == 400 && message.contains("Invalid parent_id") {
-                    CheckvistError::InvalidParentIdError
-                } else {
-                    // would prefer to include the ureq::Error in a NetworkError, but into_json
-                    // consumes it
-                    CheckvistError::UnknownError {
-                        message: format!(
-                            "Unexpected network error received from ureq. Status: {}",
-                            status
-                        ),
-                    }
-                }
+                CheckvistError::from_status(status, message)
             }
             // ureq::Errror::Transport
-            _ => CheckvistError::NetworkError(err),
+            _ => CheckvistError::NetworkError(Box::new(err)),
+        }
+    }
+}
+
+const LIST_UNAVAILABLE_MESSAGE: &str = "The list doesn't exist or is not available to you";
+
+impl CheckvistError {
+    /// The error for a response with this (unsuccessful) status and message
+    fn from_status(status: u16, message: &str) -> Self {
+        if status == 403 && message.contains(LIST_UNAVAILABLE_MESSAGE) {
+            CheckvistError::InvalidListError
+        } else if status == 400 && message.contains("Invalid parent_id") {
+            CheckvistError::InvalidParentIdError
+        } else if status == 404 && message.to_lowercase().contains("task") {
+            CheckvistError::InvalidTaskError
+        } else {
+            // would prefer to include the ureq::Error in a NetworkError, but into_json
+            // consumes it
+            CheckvistError::UnknownError {
+                message: format!(
+                    "Unexpected network error received from ureq. Status: {}",
+                    status
+                ),
+            }
+        }
+    }
+
+    /// As `From<ureq::Error>`, but for sharing and assignment, where a refusal is
+    /// most likely a user that can't be found, or not being allowed to share
+    fn from_sharing_error(err: ureq::Error) -> Self {
+        let ureq::Error::Status(status @ (403 | 404 | 422), response) = err else {
+            return err.into();
+        };
+        // a refusal without a JSON message is still a refusal
+        let message = response
+            .into_json::<HashMap<String, String>>()
+            .ok()
+            .and_then(|mut json| json.remove("message"))
+            .unwrap_or_default();
+        match status {
+            403 if !message.contains(LIST_UNAVAILABLE_MESSAGE) => {
+                CheckvistError::PermissionDeniedError { message }
+            }
+            404 | 422 if message.to_lowercase().contains("user") => {
+                CheckvistError::UnknownUserError { message }
+            }
+            _ => CheckvistError::from_status(status, &message),
         }
     }
 }
@@ -119,6 +392,13 @@ impl From<std::io::Error> for CheckvistError {
     }
 }
 
+// decoding errors are reported as IoErrors, as ureq's `into_json` does
+impl From<serde_json::Error> for CheckvistError {
+    fn from(err: serde_json::Error) -> Self {
+        CheckvistError::IoError(err.into())
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
 enum ApiResponse<T> {
@@ -129,9 +409,13 @@ enum ApiResponse<T> {
 
 pub struct CheckvistClient {
     base_url: Url,
-    api_token: RefCell<String>,
+    api_token: Mutex<String>,
     // should we need multiple callbacks, replace this with a vec of trait objects
-    token_refresh_callback: Box<dyn Fn(&str)>,
+    token_refresh_callback: Box<dyn Fn(&str) + Send + Sync>,
+    // held while refreshing, so that concurrent requests refresh the token only once
+    refresh_lock: Mutex<()>,
+    // when set, all requests go through the cassette (see cassette.rs)
+    cassette: Option<Cassette>,
 }
 
 impl fmt::Debug for CheckvistClient {
@@ -139,19 +423,36 @@ impl fmt::Debug for CheckvistClient {
         f.debug_struct("CheckvistClient")
             .field("base_url", &self.base_url)
             .field("api_token", &self.api_token)
+            .field("cassette", &self.cassette)
             .finish_non_exhaustive()
     }
 }
 
 impl CheckvistClient {
-    pub fn new(base_url: &str, api_token: &str, on_token_refresh: Box<dyn Fn(&str)>) -> Self {
+    pub fn new(
+        base_url: &str,
+        api_token: &str,
+        on_token_refresh: Box<dyn Fn(&str) + Send + Sync>,
+    ) -> Self {
         Self {
             base_url: Url::parse(base_url).expect("Bad base url supplied"),
-            api_token: RefCell::new(api_token.into()),
+            api_token: Mutex::new(api_token.into()),
             token_refresh_callback: on_token_refresh,
+            refresh_lock: Mutex::new(()),
+            cassette: None,
         }
     }
 
+    /// Records all requests to, or replays them from, `cassette`
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    pub fn cassette(&self) -> Option<&Cassette> {
+        self.cassette.as_ref()
+    }
+
     pub fn get_token(base_url: &str, username: &str, remote_key: &str) -> Result<String> {
         let url = CheckvistClient::build_endpoint(
             &Url::parse(base_url).expect("Bad base URL supplied"),
@@ -172,13 +473,14 @@ impl CheckvistClient {
         );
 
         info!("Refreshing api token");
-        let response: ApiToken = ureq::post(url.as_str())
-            .send_json(ureq::json!({"old_token": self.api_token.borrow().clone()}))
+        let payload = serde_json::json!({"old_token": self.token()});
+        let response: ApiToken = self
+            .send(ureq::post(url.as_str()), Some(&payload))
             // *any* error here means the token refresh failed
             .map_err(|_| CheckvistError::TokenRefreshFailedError)?
             .into_json()?;
 
-        *self.api_token.borrow_mut() = response.token.clone();
+        *self.api_token.lock().expect("token lock poisoned") = response.token.clone();
         info!("Refreshed api token");
         (self.token_refresh_callback)(&response.token);
 
@@ -200,15 +502,15 @@ impl CheckvistClient {
                     }
                 }
             },
+            // a task in a list that's gone, or no longer shared, can't be found either
             Some(parent_task_id) => match self.get_task(location.list_id, parent_task_id) {
                 Ok(_) => Ok(true),
-                Err(e) => {
-                    if let CheckvistError::InvalidTaskError = e {
-                        Ok(false)
-                    } else {
-                        Err(e)
-                    }
-                }
+                Err(
+                    CheckvistError::InvalidTaskError
+                    | CheckvistError::InvalidListError
+                    | CheckvistError::InvalidParentIdError,
+                ) => Ok(false),
+                Err(e) => Err(e),
             },
         }
     }
@@ -221,6 +523,18 @@ impl CheckvistClient {
         self.to_results(response)
     }
 
+    /// Archived lists, which `get_lists` doesn't include
+    pub fn get_archived_lists(&self) -> Result<Vec<Checklist>> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec!["/checklists.json?archived=true"],
+        );
+
+        let response = self.checkvist_get(url)?.into_json()?;
+
+        self.to_results(response)
+    }
+
     pub fn get_list(&self, list_id: u32) -> Result<Checklist> {
         let url = CheckvistClient::build_endpoint(
             &self.base_url,
@@ -271,6 +585,116 @@ impl CheckvistClient {
         self.to_results(response)
     }
 
+    /// As `get_tasks`, with each task's notes included
+    pub fn get_tasks_with_notes(&self, list_id: u32) -> Result<Vec<Task>> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks.json?with_notes=true",
+            ],
+        );
+
+        let response = self.checkvist_get(url)?.into_json()?;
+
+        self.to_results(response)
+    }
+
+    /// Deletes the task and all of its children
+    pub fn delete_task(&self, list_id: u32, task_id: u32) -> Result<()> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks/",
+                &task_id.to_string(),
+                ".json",
+            ],
+        );
+
+        self.checkvist_send("DELETE", url, HashMap::<&str, &str>::new())?;
+
+        Ok(())
+    }
+
+    pub fn close_task(&self, list_id: u32, task_id: u32) -> Result<()> {
+        self.change_task_status(list_id, task_id, "close")
+    }
+
+    pub fn reopen_task(&self, list_id: u32, task_id: u32) -> Result<()> {
+        self.change_task_status(list_id, task_id, "reopen")
+    }
+
+    pub fn invalidate_task(&self, list_id: u32, task_id: u32) -> Result<()> {
+        self.change_task_status(list_id, task_id, "invalidate")
+    }
+
+    /// Sets the status with close_task, reopen_task or invalidate_task as appropriate
+    pub fn set_task_status(&self, list_id: u32, task_id: u32, status: TaskStatus) -> Result<()> {
+        match status {
+            TaskStatus::Open => self.reopen_task(list_id, task_id),
+            TaskStatus::Closed => self.close_task(list_id, task_id),
+            TaskStatus::Invalidated => self.invalidate_task(list_id, task_id),
+        }
+    }
+
+    fn change_task_status(&self, list_id: u32, task_id: u32, action: &str) -> Result<()> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks/",
+                &task_id.to_string(),
+                "/",
+                action,
+                ".json",
+            ],
+        );
+
+        self.checkvist_post(url, HashMap::<&str, &str>::new())?;
+
+        Ok(())
+    }
+
+    pub fn get_notes(&self, list_id: u32, task_id: u32) -> Result<Vec<Note>> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks/",
+                &task_id.to_string(),
+                "/comments.json",
+            ],
+        );
+
+        let response = self.checkvist_get(url)?.into_json()?;
+
+        self.to_results(response)
+    }
+
+    pub fn add_note(&self, list_id: u32, task_id: u32, comment: &str) -> Result<Note> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks/",
+                &task_id.to_string(),
+                "/comments.json",
+            ],
+        );
+
+        let response = self
+            .checkvist_post(url, ureq::json!({ "comment": { "comment": comment } }))?
+            .into_json()?;
+
+        self.to_result(response)
+    }
+
     pub fn add_task(&self, list_id: u32, task: &Task) -> Result<Task> {
         let url = CheckvistClient::build_endpoint(
             &self.base_url,
@@ -283,62 +707,226 @@ impl CheckvistClient {
         self.to_result(response)
     }
 
-    // TODO: - REFACTOR: combine get & post methods
-    fn checkvist_post<T: serde::Serialize>(&self, url: Url, payload: T) -> Result<ureq::Response> {
-        let request =
-            ureq::post(url.as_str()).set("X-Client-token", &self.api_token.borrow().clone());
-        let response = request.send_json(&payload).or_else(|err| {
-            match err {
-                ureq::Error::Status(401, _) => {
-                    match self.refresh_token() {
-                        // we have a new token. Try the request again
-                        Ok(_) => {
-                            // Self has a new token, so we must rebuild the request
-                            let request = ureq::post(url.as_str())
-                                .set("X-Client-token", &self.api_token.borrow().clone());
-                            Ok(request.send_json(&payload)?)
-                        }
+    /// Updates the task with id `task.id`. Fields that are None or empty are left
+    /// unchanged by Checkvist
+    pub fn update_task(&self, list_id: u32, task: &Task) -> Result<Task> {
+        let Some(task_id) = task.id else {
+            return Err(CheckvistError::InvalidTaskError);
+        };
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks/",
+                &task_id.to_string(),
+                ".json",
+            ],
+        );
 
-                        // CheckvistError::TokenRefreshFailedError
-                        Err(err) => Err(err),
-                    }
-                }
-                // let CheckvistError From handle
-                err => Err(err)?,
-            }
-        })?;
+        let response = self.checkvist_send("PUT", url, task)?.into_json()?;
 
-        Ok(response)
+        self.to_result(response)
+    }
+
+    /// Sets a task's due date from an expression such as "tomorrow" or "next friday",
+    /// resolved against `resolver`'s idea of today (see `due_date::DueDateResolver::resolve`)
+    pub fn set_due_date(
+        &self,
+        list_id: u32,
+        task_id: u32,
+        expression: &str,
+        resolver: &DueDateResolver,
+    ) -> Result<Task> {
+        let due = resolver.resolve(expression)?;
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks/",
+                &task_id.to_string(),
+                ".json",
+            ],
+        );
+
+        let response = self
+            .checkvist_send(
+                "PUT",
+                url,
+                HashMap::from([("due_date", due.format("%Y/%m/%d").to_string())]),
+            )?
+            .into_json()?;
+
+        self.to_result(response)
+    }
+
+    /// The users a list is shared with, including its owner
+    pub fn get_collaborators(&self, list_id: u32) -> Result<Vec<User>> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec!["/checklists/", &list_id.to_string(), "/collaborators.json"],
+        );
+
+        let response = self.checkvist_get(url)?.into_json()?;
+
+        self.to_results(response)
+    }
+
+    /// Shares a list with the Checkvist user with this email address.
+    /// Returns `CheckvistError::UnknownUserError` if there is no such user, or
+    /// `CheckvistError::PermissionDeniedError` if the list can't be shared by this user
+    pub fn share_list(&self, list_id: u32, email: &str) -> Result<User> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec!["/checklists/", &list_id.to_string(), "/collaborators.json"],
+        );
+
+        let payload = serde_json::to_value(HashMap::from([("email", email)]))?;
+        let response = self
+            .send_with_token(
+                "POST",
+                &url,
+                Some(&payload),
+                CheckvistError::from_sharing_error,
+            )?
+            .into_json()?;
+
+        self.to_result(response)
+    }
+
+    /// Adds `user_id` to the task's assignees. Other assignees are kept.
+    /// Returns `CheckvistError::PermissionDeniedError` if Checkvist won't allow it
+    pub fn assign_task(&self, list_id: u32, task_id: u32, user_id: u32) -> Result<Task> {
+        let mut assignee_ids = self.get_assignee_ids(list_id, task_id)?;
+        if !assignee_ids.contains(&user_id) {
+            assignee_ids.push(user_id);
+        }
+
+        self.set_assignee_ids(list_id, task_id, assignee_ids)
+    }
+
+    /// Removes `user_id` from the task's assignees. Other assignees are kept
+    pub fn unassign_task(&self, list_id: u32, task_id: u32, user_id: u32) -> Result<Task> {
+        let mut assignee_ids = self.get_assignee_ids(list_id, task_id)?;
+        assignee_ids.retain(|id| *id != user_id);
+
+        self.set_assignee_ids(list_id, task_id, assignee_ids)
+    }
+
+    fn get_assignee_ids(&self, list_id: u32, task_id: u32) -> Result<Vec<u32>> {
+        // get_task returns the task's parents too
+        self.get_task(list_id, task_id)?
+            .into_iter()
+            .find(|task| task.id == Some(task_id))
+            .map(|task| task.assignee_ids)
+            .ok_or(CheckvistError::InvalidTaskError)
+    }
+
+    fn set_assignee_ids(&self, list_id: u32, task_id: u32, assignee_ids: Vec<u32>) -> Result<Task> {
+        let url = CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks/",
+                &task_id.to_string(),
+                ".json",
+            ],
+        );
+
+        // sent explicitly, as Task doesn't serialise an empty assignee list
+        let payload = serde_json::to_value(HashMap::from([("assignee_ids", assignee_ids)]))?;
+        let response = self
+            .send_with_token(
+                "PUT",
+                &url,
+                Some(&payload),
+                CheckvistError::from_sharing_error,
+            )?
+            .into_json()?;
+
+        self.to_result(response)
+    }
+
+    fn checkvist_post<T: serde::Serialize>(&self, url: Url, payload: T) -> Result<ureq::Response> {
+        self.checkvist_send("POST", url, payload)
+    }
+
+    fn checkvist_send<T: serde::Serialize>(
+        &self,
+        method: &str,
+        url: Url,
+        payload: T,
+    ) -> Result<ureq::Response> {
+        let payload = serde_json::to_value(payload)?;
+        self.send_with_token(method, &url, Some(&payload), CheckvistError::from)
     }
 
-    // TODO: - REFACTOR: combine get & post methods
     fn checkvist_get(&self, url: Url) -> Result<ureq::Response> {
-        let request =
-            ureq::get(url.as_str()).set("X-Client-token", &self.api_token.borrow().clone());
-        let response = request.call().or_else(|err| {
-            match err {
+        self.send_with_token("GET", &url, None, CheckvistError::from)
+    }
+
+    /// Sends an authenticated request, refreshing the token and retrying if it has expired.
+    /// Unsuccessful responses are turned into errors by `to_error`
+    fn send_with_token(
+        &self,
+        method: &str,
+        url: &Url,
+        payload: Option<&serde_json::Value>,
+        to_error: fn(ureq::Error) -> CheckvistError,
+    ) -> Result<ureq::Response> {
+        let token = self.token();
+        let request = ureq::request(method, url.as_str()).set("X-Client-token", &token);
+        let response = self.send(request, payload).or_else(|err| {
+            match *err {
                 ureq::Error::Status(401, _) => {
-                    match self.refresh_token() {
+                    match self.refresh_token_once(&token) {
                         // we have a new token. Try the request again
                         Ok(_) => {
                             // Self has a new token, so we must rebuild the request
-                            let request = ureq::get(url.as_str())
-                                .set("X-Client-token", &self.api_token.borrow().clone());
-                            Ok(request.call()?)
+                            let request = ureq::request(method, url.as_str())
+                                .set("X-Client-token", &self.token());
+                            self.send(request, payload).map_err(|err| to_error(*err))
                         }
 
                         // CheckvistError::TokenRefreshFailedError
                         Err(err) => Err(err),
                     }
                 }
-
-                err => Err(err)?,
+                err => Err(to_error(err)),
             }
         })?;
 
         Ok(response)
     }
 
+    /// Refreshes the token, unless another thread has already replaced `expired_token`
+    fn refresh_token_once(&self, expired_token: &str) -> Result<()> {
+        let _refreshing = self.refresh_lock.lock().expect("refresh lock poisoned");
+        if self.token() != expired_token {
+            return Ok(());
+        }
+        self.refresh_token()
+    }
+
+    fn token(&self) -> String {
+        self.api_token.lock().expect("token lock poisoned").clone()
+    }
+
+    /// All requests (but login) go through here, so they can be recorded or replayed
+    fn send(
+        &self,
+        request: ureq::Request,
+        payload: Option<&serde_json::Value>,
+    ) -> std::result::Result<ureq::Response, Box<ureq::Error>> {
+        match (&self.cassette, payload) {
+            (Some(cassette), payload) => cassette.send(request, payload),
+            (None, Some(payload)) => request.send_json(payload).map_err(Box::new),
+            (None, None) => request.call().map_err(Box::new),
+        }
+    }
+
     // Utility Methods
 
     // TODO:  RESEARCH NEEDED:
@@ -0,0 +1,783 @@
+//! Two way sync between a Checkvist list and a Markdown file, so the list can be
+//! edited in any text editor.
+//!
+//! The file is a nested list as written by `export::to_markdown_with_ids`: each
+//! item's task id is in a marker (an HTML comment, so hidden when the Markdown is
+//! rendered) at the end of its first line. A state file beside it holds the list as
+//! it was after the last sync, so that each sync can tell what changed on each side
+//! since then. Changes are then made to the other side:
+//! - items without a marker are added as tasks, and new tasks are added to the file
+//! - edits to content, tags or due date, status changes (checking an item closes
+//!   its task) and moves (to a new parent, or among siblings)
+//! - deleting an item deletes its task (and its descendants), and vice versa
+//!
+//! Where both sides changed the same thing differently, Checkvist's version is kept
+//! and the conflict is reported. An edit beats a deletion: a task deleted from the
+//! file but edited in Checkvist is kept, and an item edited in the file but deleted
+//! from Checkvist is added again as a new task.
+//!
+//! Notes aren't synced (or written to the file), and the file's heading is always
+//! the list's name. A missing file is written afresh from the list. If a sync fails
+//! partway, the file is given the markers of the tasks added so far, and the next
+//! sync makes the rest of its changes.
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::due_date::DueDateResolver;
+use crate::export::{content_with_smart_syntax, to_markdown_with_ids};
+use crate::import::parse_markdown;
+use crate::outline::{Outline, OutlineItem};
+use crate::smart_syntax;
+use crate::{CheckvistClient, CheckvistError, Result, Tags, Task, TaskStatus};
+
+const MARKER_PREFIX: &str = "<!-- cv:";
+const MARKER_SUFFIX: &str = " -->";
+
+/// The marker recording task `id` in a synced file
+pub(crate) fn id_marker(id: u32) -> String {
+    format!("{}{}{}", MARKER_PREFIX, id, MARKER_SUFFIX)
+}
+
+/// Splits a trailing id marker from `text`
+pub(crate) fn strip_id_marker(text: &str) -> (&str, Option<u32>) {
+    text.strip_suffix(MARKER_SUFFIX)
+        .and_then(|rest| rest.rsplit_once(MARKER_PREFIX))
+        .and_then(|(before, id)| id.parse().ok().map(|id| (before.trim_end(), Some(id))))
+        .unwrap_or((text, None))
+}
+
+/// A list synced with a Markdown file
+pub struct MarkdownSync {
+    list_id: u32,
+    path: PathBuf,
+    state_path: PathBuf,
+}
+
+/// The changes made to one side during a sync, as task ids
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncChanges {
+    pub created: Vec<u32>,
+    /// Content, tags or due date changed
+    pub updated: Vec<u32>,
+    pub moved: Vec<u32>,
+    /// Closed, invalidated or reopened
+    pub status_changed: Vec<u32>,
+    pub deleted: Vec<u32>,
+}
+
+impl SyncChanges {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.updated.is_empty()
+            && self.moved.is_empty()
+            && self.status_changed.is_empty()
+            && self.deleted.is_empty()
+    }
+}
+
+/// A task changed on both sides. `local` and `remote` are its content (with smart
+/// syntax) in the file and in Checkvist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncConflict {
+    /// Changed differently in each. Checkvist's version was kept
+    BothChanged {
+        task_id: u32,
+        local: String,
+        remote: String,
+    },
+    /// Deleted from the file, but changed in Checkvist, so kept
+    DeletedLocally { task_id: u32, remote: String },
+    /// Changed in the file, but deleted from Checkvist, so added again as `new_task_id`
+    DeletedRemotely {
+        task_id: u32,
+        local: String,
+        new_task_id: u32,
+    },
+}
+
+/// What one call to `MarkdownSync::sync` did
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MarkdownSyncReport {
+    /// Changes made to the list, from the file
+    pub to_checkvist: SyncChanges,
+    /// Changes made to the file, from the list
+    pub to_file: SyncChanges,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SyncState {
+    list_id: u32,
+    tasks: Vec<Task>,
+}
+
+impl MarkdownSync {
+    /// Syncs `list_id` with the file at `path`. State is kept in a hidden file
+    /// beside it (`.<file name>.cvsync`)
+    pub fn new(list_id: u32, path: &Path) -> Self {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self {
+            list_id,
+            path: path.to_path_buf(),
+            state_path: path.with_file_name(format!(".{}.cvsync", file_name)),
+        }
+    }
+
+    pub fn state_path(&self) -> &Path {
+        &self.state_path
+    }
+
+    /// Makes the changes on each side since the last sync to the other side, then
+    /// rewrites the file from the list. Due dates in the file are resolved against
+    /// `resolver`
+    pub fn sync(
+        &self,
+        client: &CheckvistClient,
+        resolver: &DueDateResolver,
+    ) -> Result<MarkdownSyncReport> {
+        let list = client.get_list(self.list_id)?;
+        let remote_outline = Outline::from_tasks(&list.name, &client.get_tasks(self.list_id)?);
+        let remote = Version::new(&remote_outline);
+        let mut report = MarkdownSyncReport::default();
+
+        let Some(local_outline) = self.read_file(resolver)? else {
+            info!("Writing list {} to new file {:?}", self.list_id, self.path);
+            report.to_file.created = remote.ids().collect();
+            self.write(client, &list.name)?;
+            return Ok(report);
+        };
+        let local = Version::new(&local_outline);
+        let base = match self.read_state()? {
+            Some(state) if state.list_id == self.list_id => {
+                Version::new(&Outline::from_tasks(&list.name, &state.tasks))
+            }
+            // a first sync of an existing file. Take the tasks in it to have been
+            // as they are in Checkvist, and any others not to have been added yet
+            _ => {
+                let mut base = Version::new(&remote_outline);
+                base.by_id.retain(|id, _| local.by_id.contains_key(id));
+                base
+            }
+        };
+
+        let merge = Merge::new(&base, &local, &remote, &mut report);
+        let mut task_ids = vec![None; local.nodes.len()];
+        if let Err(err) = merge.apply(client, self.list_id, &mut report, &mut task_ids) {
+            // so that the tasks added before the failure aren't added again next time
+            self.write_added(local_outline, &list.name, &task_ids)?;
+            return Err(err);
+        }
+        self.write(client, &list.name)?;
+        info!(
+            "Synced list {} with {:?}: {} conflicts",
+            self.list_id,
+            self.path,
+            report.conflicts.len()
+        );
+
+        Ok(report)
+    }
+
+    fn read_file(&self, resolver: &DueDateResolver) -> Result<Option<Outline>> {
+        let markdown = match fs::read_to_string(&self.path) {
+            Ok(markdown) => markdown,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let mut outline = parse_markdown(&markdown, resolver)?;
+        for item in &mut outline.items {
+            join_continuation_lines(item, resolver);
+        }
+        Ok(Some(outline))
+    }
+
+    fn read_state(&self) -> Result<Option<SyncState>> {
+        match fs::read_to_string(&self.state_path) {
+            Ok(state) => Ok(Some(serde_json::from_str(&state)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Rewrites the file as it was read, but with the ids of any of its items that have
+    /// been added as tasks (`task_ids`, in depth first order). The state is left as it
+    /// was, so the next sync finds the rest of the file's changes still to make
+    fn write_added(
+        &self,
+        mut outline: Outline,
+        list_name: &str,
+        task_ids: &[Option<u32>],
+    ) -> Result<()> {
+        fn set_ids<'a>(items: &mut [OutlineItem], ids: &mut impl Iterator<Item = &'a Option<u32>>) {
+            for item in items {
+                if let Some(Some(id)) = ids.next() {
+                    item.id = Some(*id);
+                }
+                set_ids(&mut item.children, ids);
+            }
+        }
+        set_ids(&mut outline.items, &mut task_ids.iter());
+        outline.title = list_name.to_string();
+        fs::write(&self.path, to_markdown_with_ids(&outline))?;
+        Ok(())
+    }
+
+    /// Writes the file and state from the list as it is now
+    fn write(&self, client: &CheckvistClient, list_name: &str) -> Result<()> {
+        let tasks = client.get_tasks(self.list_id)?;
+        fs::write(
+            &self.path,
+            to_markdown_with_ids(&Outline::from_tasks(list_name, &tasks)),
+        )?;
+        let state = SyncState {
+            list_id: self.list_id,
+            tasks,
+        };
+        fs::write(&self.state_path, serde_json::to_string(&state)?)?;
+        Ok(())
+    }
+}
+
+/// The file has no notes, so any lines beneath an item's first are the rest of its
+/// content (which may include smart syntax)
+fn join_continuation_lines(item: &mut OutlineItem, resolver: &DueDateResolver) {
+    if !item.notes.is_empty() {
+        let content = format!(
+            "{}\n{}",
+            content_with_smart_syntax(item),
+            item.notes.join("\n\n")
+        );
+        let task = smart_syntax::parse(&content).to_task(1, None, resolver);
+        item.content = task.content;
+        item.tags = task.tags;
+        item.due = task.due;
+        item.notes.clear();
+    }
+    for child in &mut item.children {
+        join_continuation_lines(child, resolver);
+    }
+}
+
+/// Where a task's parent is, when comparing placements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Parent {
+    Top,
+    Task(u32),
+    /// An item added to the file
+    New,
+}
+
+/// A task in one version of the list (the file, Checkvist, or as at the last sync)
+struct Node {
+    id: Option<u32>,
+    /// The index of the parent node
+    parent: Option<usize>,
+    /// Without children
+    item: OutlineItem,
+}
+
+impl Node {
+    fn text_differs(&self, other: &Node) -> bool {
+        self.item.content != other.item.content
+            // the file can't say whether a tag is private
+            || !self.item.tags.keys().eq(other.item.tags.keys())
+            || self.item.due != other.item.due
+    }
+
+    fn summary(&self) -> String {
+        content_with_smart_syntax(&self.item)
+    }
+}
+
+/// A version of the list's tasks, depth first
+struct Version {
+    nodes: Vec<Node>,
+    by_id: HashMap<u32, usize>,
+}
+
+impl Version {
+    fn new(outline: &Outline) -> Self {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut by_id = HashMap::new();
+        // the latest node at each depth
+        let mut ancestors: Vec<usize> = Vec::new();
+        for (depth, item) in outline.walk() {
+            ancestors.truncate(depth);
+            // a copied marker makes a new task
+            let id = item.id.filter(|id| !by_id.contains_key(id));
+            if let Some(id) = id {
+                by_id.insert(id, nodes.len());
+            }
+            let item = OutlineItem {
+                children: Vec::new(),
+                ..item.clone()
+            };
+            nodes.push(Node {
+                id,
+                parent: ancestors.last().copied(),
+                item,
+            });
+            ancestors.push(nodes.len() - 1);
+        }
+        Self { nodes, by_id }
+    }
+
+    fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.nodes.iter().filter_map(|node| node.id)
+    }
+
+    fn get(&self, id: u32) -> Option<&Node> {
+        self.by_id.get(&id).map(|index| &self.nodes[*index])
+    }
+
+    fn parent(&self, index: usize) -> Parent {
+        match self.nodes[index].parent {
+            None => Parent::Top,
+            Some(parent) => self.nodes[parent].id.map_or(Parent::New, Parent::Task),
+        }
+    }
+
+    /// The parent, and the previous sibling of those in `survivors`
+    fn placement(&self, id: u32, survivors: &HashSet<u32>) -> (Parent, Option<u32>) {
+        let index = self.by_id[&id];
+        let parent = self.nodes[index].parent;
+        let previous = self.nodes[..index]
+            .iter()
+            .rev()
+            .filter(|node| node.parent == parent)
+            .find_map(|node| node.id.filter(|id| survivors.contains(id)));
+        (self.parent(index), previous)
+    }
+
+    fn ancestor_ids(&self, id: u32) -> Vec<u32> {
+        let mut ancestors = Vec::new();
+        let mut parent = self.get(id).and_then(|node| node.parent);
+        while let Some(index) = parent {
+            ancestors.extend(self.nodes[index].id);
+            parent = self.nodes[index].parent;
+        }
+        ancestors
+    }
+
+    /// Whether each node, or any of its descendants, is marked in `changed`
+    fn subtree_changed(&self, changed: impl Fn(usize) -> bool) -> Vec<bool> {
+        let mut subtree_changed: Vec<bool> = (0..self.nodes.len()).map(changed).collect();
+        for index in (0..self.nodes.len()).rev() {
+            if let Some(parent) = self.nodes[index].parent {
+                subtree_changed[parent] |= subtree_changed[index];
+            }
+        }
+        subtree_changed
+    }
+}
+
+/// How one aspect of a task changed between the last sync and now
+#[derive(Debug, PartialEq, Eq)]
+enum AspectChange {
+    Unchanged,
+    /// Changed only in the file, so to be changed in Checkvist
+    Push,
+    /// Changed only in Checkvist, so to be changed in the file
+    Pull,
+    Conflict,
+}
+
+impl AspectChange {
+    fn of<T: PartialEq>(base: T, local: T, remote: T) -> Self {
+        match (local != base, remote != base, local != remote) {
+            (_, _, false) => AspectChange::Unchanged,
+            (true, true, true) => AspectChange::Conflict,
+            (true, false, true) => AspectChange::Push,
+            (false, _, true) => AspectChange::Pull,
+        }
+    }
+}
+
+/// The changes to make to Checkvist
+struct Merge<'a> {
+    base: &'a Version,
+    local: &'a Version,
+    remote: &'a Version,
+    updates: Vec<u32>,
+    statuses: Vec<u32>,
+    moves: HashSet<u32>,
+    deletes: Vec<u32>,
+    /// Tasks deleted from Checkvist but changed in the file
+    changed_but_deleted: HashSet<u32>,
+}
+
+impl<'a> Merge<'a> {
+    fn new(
+        base: &'a Version,
+        local: &'a Version,
+        remote: &'a Version,
+        report: &mut MarkdownSyncReport,
+    ) -> Self {
+        let mut merge = Self {
+            base,
+            local,
+            remote,
+            updates: Vec::new(),
+            statuses: Vec::new(),
+            moves: HashSet::new(),
+            deletes: Vec::new(),
+            changed_but_deleted: HashSet::new(),
+        };
+        let ids: BTreeSet<u32> = base
+            .by_id
+            .keys()
+            .chain(local.by_id.keys())
+            .chain(remote.by_id.keys())
+            .copied()
+            .collect();
+        let survivors: HashSet<u32> = ids
+            .iter()
+            .copied()
+            .filter(|id| {
+                base.by_id.contains_key(id)
+                    && local.by_id.contains_key(id)
+                    && remote.by_id.contains_key(id)
+            })
+            .collect();
+        // tasks added, or changed, in Checkvist (or with such a descendant)
+        let remote_changed = remote.subtree_changed(|index| {
+            let node = &remote.nodes[index];
+            match node.id.and_then(|id| base.get(id)) {
+                Some(before) => node.text_differs(before) || node.item.status != before.item.status,
+                None => true,
+            }
+        });
+
+        for id in ids {
+            match (base.get(id), local.get(id), remote.get(id)) {
+                (Some(before), Some(here), Some(there)) => {
+                    let text =
+                        AspectChange::of(&before.summary(), &here.summary(), &there.summary());
+                    let status =
+                        AspectChange::of(before.item.status, here.item.status, there.item.status);
+                    let placement = AspectChange::of(
+                        base.placement(id, &survivors),
+                        local.placement(id, &survivors),
+                        remote.placement(id, &survivors),
+                    );
+                    match text {
+                        AspectChange::Push => merge.updates.push(id),
+                        AspectChange::Pull => report.to_file.updated.push(id),
+                        _ => (),
+                    }
+                    match status {
+                        AspectChange::Push => merge.statuses.push(id),
+                        AspectChange::Pull => report.to_file.status_changed.push(id),
+                        _ => (),
+                    }
+                    match placement {
+                        AspectChange::Push => {
+                            merge.moves.insert(id);
+                        }
+                        AspectChange::Pull => report.to_file.moved.push(id),
+                        _ => (),
+                    }
+                    if [text, status, placement].contains(&AspectChange::Conflict) {
+                        report.conflicts.push(SyncConflict::BothChanged {
+                            task_id: id,
+                            local: here.summary(),
+                            remote: there.summary(),
+                        });
+                    }
+                }
+                (Some(before), Some(here), None) => {
+                    if here.text_differs(before)
+                        || here.item.status != before.item.status
+                        || base.parent(base.by_id[&id]) != local.parent(local.by_id[&id])
+                    {
+                        merge.changed_but_deleted.insert(id);
+                    }
+                }
+                (Some(_), None, Some(there)) => {
+                    if remote_changed[remote.by_id[&id]] {
+                        report.conflicts.push(SyncConflict::DeletedLocally {
+                            task_id: id,
+                            remote: there.summary(),
+                        });
+                    } else {
+                        merge.deletes.push(id);
+                    }
+                }
+                (None, _, Some(_)) => report.to_file.created.push(id),
+                // deleted from both, or in the file with the marker of an unknown
+                // task, and so added as a new one
+                (Some(_), None, None) | (None, _, None) => (),
+            }
+        }
+
+        merge
+    }
+
+    /// Makes the changes to Checkvist. `task_ids` is filled in with each file item's
+    /// task in Checkvist, once it has one, so what was added is known even after an error
+    fn apply(
+        &self,
+        client: &CheckvistClient,
+        list_id: u32,
+        report: &mut MarkdownSyncReport,
+        task_ids: &mut [Option<u32>],
+    ) -> Result<()> {
+        let local = self.local;
+        let mut siblings = Siblings::new(self.remote);
+        let mut recreated = vec![false; local.nodes.len()];
+        let changed = local.subtree_changed(|index| match local.nodes[index].id {
+            Some(id) if self.base.by_id.contains_key(&id) => {
+                self.changed_but_deleted.contains(&id)
+                    || self.updates.contains(&id)
+                    || self.statuses.contains(&id)
+                    || self.moves.contains(&id)
+            }
+            _ => true,
+        });
+
+        // add and move tasks in file order, so parents and previous siblings are
+        // always in place first
+        for (index, node) in local.nodes.iter().enumerate() {
+            let parent_recreated = node.parent.is_some_and(|parent| recreated[parent]);
+            match node.id {
+                Some(id) if self.remote.by_id.contains_key(&id) => {
+                    task_ids[index] = Some(id);
+                    if self.moves.contains(&id) {
+                        let (parent_id, position) =
+                            self.position(&mut siblings, task_ids, index, id);
+                        put_task(
+                            client,
+                            list_id,
+                            id,
+                            json!({ "parent_id": parent_id.unwrap_or(0), "position": position }),
+                        )?;
+                        report.to_checkvist.moved.push(id);
+                    }
+                }
+                Some(id) if self.base.by_id.contains_key(&id) => {
+                    // deleted from Checkvist
+                    if !changed[index] && !parent_recreated {
+                        report.to_file.deleted.push(id);
+                        continue;
+                    }
+                    let new_id = self.add(client, list_id, &mut siblings, task_ids, index)?;
+                    recreated[index] = true;
+                    report.to_checkvist.created.push(new_id);
+                    if !parent_recreated {
+                        report.conflicts.push(SyncConflict::DeletedRemotely {
+                            task_id: id,
+                            local: node.summary(),
+                            new_task_id: new_id,
+                        });
+                    }
+                }
+                _ => {
+                    let new_id = self.add(client, list_id, &mut siblings, task_ids, index)?;
+                    report.to_checkvist.created.push(new_id);
+                }
+            }
+        }
+
+        for id in &self.updates {
+            let item = &local.get(*id).expect("updated tasks are in the file").item;
+            // tags already on the task keep their privacy
+            let remote_tags = self.remote.get(*id).map(|node| &node.item.tags);
+            let tags: Tags = item
+                .tags
+                .keys()
+                .map(|tag| {
+                    let private = remote_tags.and_then(|tags| tags.get(tag)).copied();
+                    (tag.clone(), private.unwrap_or(false))
+                })
+                .collect();
+            put_task(
+                client,
+                list_id,
+                *id,
+                json!({
+                    "content": item.content,
+                    "tags": tags,
+                    "due_date": item.due.map(|due| due.format("%Y/%m/%d").to_string()).unwrap_or_default(),
+                }),
+            )?;
+            report.to_checkvist.updated.push(*id);
+        }
+        for id in &self.statuses {
+            let status = local
+                .get(*id)
+                .expect("changed tasks are in the file")
+                .item
+                .status;
+            client.set_task_status(list_id, *id, status)?;
+            report.to_checkvist.status_changed.push(*id);
+        }
+        for id in &self.deletes {
+            // deleting a task deletes its descendants
+            if !self
+                .remote
+                .ancestor_ids(*id)
+                .iter()
+                .any(|ancestor| self.deletes.contains(ancestor))
+            {
+                client.delete_task(list_id, *id)?;
+            }
+            report.to_checkvist.deleted.push(*id);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the file item at `index` as a new task, recording its id in `task_ids`
+    /// (before anything else can fail), and returning it
+    fn add(
+        &self,
+        client: &CheckvistClient,
+        list_id: u32,
+        siblings: &mut Siblings,
+        task_ids: &mut [Option<u32>],
+        index: usize,
+    ) -> Result<u32> {
+        let item = &self.local.nodes[index].item;
+        let (parent_id, position) = self.position(siblings, task_ids, index, 0);
+        let task = client.add_task(list_id, &item.to_task(position, parent_id))?;
+        let id = task.id.ok_or(CheckvistError::UnknownError {
+            message: "Checkvist returned a new task without an id".into(),
+        })?;
+        task_ids[index] = Some(id);
+        siblings.replace(0, id);
+        if item.status != TaskStatus::Open {
+            client.set_task_status(list_id, id, item.status)?;
+        }
+        Ok(id)
+    }
+
+    /// Places `id` (0 for a task not yet added) in `siblings` where the file item at
+    /// `index` is, returning the parent id and (1 based) position to give Checkvist
+    fn position(
+        &self,
+        siblings: &mut Siblings,
+        task_ids: &[Option<u32>],
+        index: usize,
+        id: u32,
+    ) -> (Option<u32>, u16) {
+        let node = &self.local.nodes[index];
+        let parent_id = node.parent.and_then(|parent| task_ids[parent]);
+        let previous: Vec<u32> = (0..index)
+            .rev()
+            .filter(|i| self.local.nodes[*i].parent == node.parent)
+            .filter_map(|i| task_ids[i])
+            .collect();
+        (parent_id, siblings.place(id, parent_id, &previous))
+    }
+}
+
+/// The order of each task's children (by parent id, None for top level) in
+/// Checkvist, kept up to date as tasks are added and moved
+struct Siblings(HashMap<Option<u32>, Vec<u32>>);
+
+impl Siblings {
+    fn new(remote: &Version) -> Self {
+        let mut siblings: HashMap<Option<u32>, Vec<u32>> = HashMap::new();
+        for (index, node) in remote.nodes.iter().enumerate() {
+            let parent_id = match remote.parent(index) {
+                Parent::Task(id) => Some(id),
+                _ => None,
+            };
+            siblings.entry(parent_id).or_default().extend(node.id);
+        }
+        Self(siblings)
+    }
+
+    /// Moves `id` beneath `parent_id`, after the first of `previous` already there
+    /// (or first if none are), returning its position
+    fn place(&mut self, id: u32, parent_id: Option<u32>, previous: &[u32]) -> u16 {
+        for children in self.0.values_mut() {
+            children.retain(|child| *child != id);
+        }
+        let children = self.0.entry(parent_id).or_default();
+        let index = previous
+            .iter()
+            .find_map(|previous| children.iter().position(|child| child == previous))
+            .map_or(0, |index| index + 1);
+        children.insert(index, id);
+        index as u16 + 1
+    }
+
+    fn replace(&mut self, old_id: u32, new_id: u32) {
+        for child in self.0.values_mut().flat_map(|children| children.iter_mut()) {
+            if *child == old_id {
+                *child = new_id;
+            }
+        }
+    }
+}
+
+/// Updates just the given fields of a task
+fn put_task(client: &CheckvistClient, list_id: u32, task_id: u32, fields: Value) -> Result<()> {
+    let url = CheckvistClient::build_endpoint(
+        &client.base_url,
+        vec![
+            "/checklists/",
+            &list_id.to_string(),
+            "/tasks/",
+            &task_id.to_string(),
+            ".json",
+        ],
+    );
+    client.checkvist_send("PUT", url, fields)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn markers_round_trip() {
+        let line = format!("Buy milk #shop {}", id_marker(42));
+
+        assert_eq!(strip_id_marker(&line), ("Buy milk #shop", Some(42)));
+        assert_eq!(strip_id_marker("no marker"), ("no marker", None));
+        assert_eq!(
+            strip_id_marker("<!-- cv:abc -->"),
+            ("<!-- cv:abc -->", None)
+        );
+    }
+
+    #[test]
+    fn aspect_changes() {
+        assert_eq!(AspectChange::of(1, 1, 1), AspectChange::Unchanged);
+        assert_eq!(AspectChange::of(1, 2, 1), AspectChange::Push);
+        assert_eq!(AspectChange::of(1, 1, 2), AspectChange::Pull);
+        assert_eq!(AspectChange::of(1, 2, 2), AspectChange::Unchanged);
+        assert_eq!(AspectChange::of(1, 2, 3), AspectChange::Conflict);
+    }
+
+    #[test]
+    fn continuation_lines_are_content() {
+        let resolver = DueDateResolver::for_date(NaiveDate::from_ymd_opt(2023, 5, 10).unwrap());
+        let outline = parse_markdown(
+            "- [ ] first line <!-- cv:1 -->\n      second #tag\n",
+            &resolver,
+        )
+        .unwrap();
+        let mut item = outline.items[0].clone();
+
+        join_continuation_lines(&mut item, &resolver);
+
+        assert_eq!(item.id, Some(1));
+        assert_eq!(item.content, "first line\nsecond");
+        assert_eq!(item.tags.keys().collect::<Vec<_>>(), vec!["tag"]);
+        assert!(item.notes.is_empty());
+    }
+}
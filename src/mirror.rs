@@ -0,0 +1,290 @@
+//! A local mirror of selected Checkvist lists, held in an embedded SQLite database.
+//!
+//! `Mirror::sync` uses each list's `updated_at` to decide whether its tasks need
+//! re-fetching, and reports the tasks added, changed and removed since the previous sync.
+//! The remaining methods query the local copy, so need no network access.
+use std::path::Path;
+
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{Checklist, CheckvistClient, CheckvistError, Result, Task};
+
+const SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS checklists (
+        id          INTEGER PRIMARY KEY,
+        name        TEXT NOT NULL,
+        updated_at  TEXT NOT NULL,
+        data        TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        id          INTEGER NOT NULL,
+        list_id     INTEGER NOT NULL,
+        parent_id   INTEGER,
+        position    INTEGER NOT NULL,
+        content     TEXT NOT NULL,
+        data        TEXT NOT NULL,
+        PRIMARY KEY (list_id, id)
+    );
+"#;
+
+pub struct Mirror {
+    conn: Connection,
+}
+
+/// What changed in the mirror during one call to `Mirror::sync`
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncReport {
+    pub lists: Vec<ListSyncReport>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ListSyncReport {
+    pub list_id: u32,
+    pub name: String,
+    /// false if the list was unchanged since the last sync, so its tasks weren't fetched
+    pub fetched: bool,
+    /// true if the list is no longer available from Checkvist, and was dropped from the mirror
+    pub removed: bool,
+    pub added_tasks: Vec<Task>,
+    pub changed_tasks: Vec<TaskChange>,
+    pub removed_tasks: Vec<Task>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TaskChange {
+    pub before: Task,
+    pub after: Task,
+}
+
+impl SyncReport {
+    /// true if no task was added, changed, or removed in any list
+    pub fn is_empty(&self) -> bool {
+        self.lists.iter().all(|list| {
+            list.added_tasks.is_empty()
+                && list.changed_tasks.is_empty()
+                && list.removed_tasks.is_empty()
+        })
+    }
+}
+
+impl From<rusqlite::Error> for CheckvistError {
+    fn from(err: rusqlite::Error) -> Self {
+        CheckvistError::DatabaseError(err)
+    }
+}
+
+impl Mirror {
+    /// Opens (creating if necessary) the mirror database at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A mirror that lasts only as long as the returned value
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Mirror { conn })
+    }
+
+    /// Brings the mirrored copies of `list_ids` up to date with Checkvist.
+    /// Tasks are only fetched for lists whose `updated_at` differs from the mirrored copy.
+    /// Lists that were previously mirrored but aren't in `list_ids` are left untouched,
+    /// and those Checkvist says no longer exist (or aren't shared) are removed.
+    pub fn sync(&mut self, client: &CheckvistClient, list_ids: &[u32]) -> Result<SyncReport> {
+        let remote_lists = client.get_lists()?;
+        let mut report = SyncReport::default();
+
+        for list_id in list_ids {
+            let local = self.list(*list_id)?;
+            let remote = match remote_lists.iter().find(|list| list.id == *list_id) {
+                Some(remote) => Some(remote.clone()),
+                // archived lists aren't returned by get_lists, so ask for it directly
+                None => match client.get_list(*list_id) {
+                    Ok(list) => Some(list),
+                    Err(CheckvistError::InvalidListError) => None,
+                    Err(e) => return Err(e),
+                },
+            };
+            let list_report = match (local, remote) {
+                (Some(local), Some(remote)) if local.updated_at == remote.updated_at => {
+                    ListSyncReport::unchanged(&remote)
+                }
+                (_, Some(remote)) => self.sync_list(client, &remote)?,
+                (Some(local), None) => self.remove_list(&local)?,
+                // never mirrored, and now unavailable
+                (None, None) => return Err(CheckvistError::InvalidListError),
+            };
+            report.lists.push(list_report);
+        }
+
+        Ok(report)
+    }
+
+    /// All mirrored lists, in name order
+    pub fn lists(&self) -> Result<Vec<Checklist>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT data FROM checklists ORDER BY name, id")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+
+        rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+    }
+
+    pub fn list(&self, list_id: u32) -> Result<Option<Checklist>> {
+        let data: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT data FROM checklists WHERE id = ?1",
+                params![list_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        data.map(|data| Ok(serde_json::from_str(&data)?))
+            .transpose()
+    }
+
+    /// All mirrored tasks in a list, ordered by parent and position
+    pub fn tasks(&self, list_id: u32) -> Result<Vec<Task>> {
+        self.query_tasks(
+            "SELECT data FROM tasks WHERE list_id = ?1 ORDER BY parent_id, position",
+            params![list_id],
+        )
+    }
+
+    pub fn task(&self, list_id: u32, task_id: u32) -> Result<Option<Task>> {
+        Ok(self
+            .query_tasks(
+                "SELECT data FROM tasks WHERE list_id = ?1 AND id = ?2",
+                params![list_id, task_id],
+            )?
+            .pop())
+    }
+
+    /// Direct children of `parent_id`, or the top-level tasks of the list if None
+    pub fn children(&self, list_id: u32, parent_id: Option<u32>) -> Result<Vec<Task>> {
+        self.query_tasks(
+            "SELECT data FROM tasks
+             WHERE list_id = ?1 AND IFNULL(parent_id, 0) = ?2
+             ORDER BY position",
+            params![list_id, parent_id.unwrap_or(0)],
+        )
+    }
+
+    /// Tasks in any mirrored list whose content contains `text` (case insensitive for ASCII).
+    /// Returns (list_id, task) pairs
+    pub fn find_tasks(&self, text: &str) -> Result<Vec<(u32, Task)>> {
+        let pattern = format!(
+            "%{}%",
+            text.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let mut statement = self.conn.prepare(
+            "SELECT list_id, data FROM tasks
+             WHERE content LIKE ?1 ESCAPE '\\'
+             ORDER BY list_id, parent_id, position",
+        )?;
+        let rows = statement.query_map(params![pattern], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        rows.map(|row| {
+            let (list_id, data) = row?;
+            Ok((list_id, serde_json::from_str(&data)?))
+        })
+        .collect()
+    }
+
+    fn query_tasks<P: rusqlite::Params>(&self, sql: &str, params: P) -> Result<Vec<Task>> {
+        let mut statement = self.conn.prepare(sql)?;
+        let rows = statement.query_map(params, |row| row.get::<_, String>(0))?;
+
+        rows.map(|data| Ok(serde_json::from_str(&data?)?)).collect()
+    }
+
+    fn sync_list(&mut self, client: &CheckvistClient, list: &Checklist) -> Result<ListSyncReport> {
+        info!("Mirroring list {}", list.id);
+        let remote_tasks = client.get_tasks(list.id)?;
+        let local_tasks = self.tasks(list.id)?;
+        let mut report = ListSyncReport::unchanged(list);
+        report.fetched = true;
+
+        for remote in &remote_tasks {
+            match local_tasks.iter().find(|local| local.id == remote.id) {
+                None => report.added_tasks.push(remote.clone()),
+                Some(local) if local != remote => report.changed_tasks.push(TaskChange {
+                    before: local.clone(),
+                    after: remote.clone(),
+                }),
+                Some(_) => (),
+            }
+        }
+        report.removed_tasks = local_tasks
+            .into_iter()
+            .filter(|local| !remote_tasks.iter().any(|remote| remote.id == local.id))
+            .collect();
+
+        let transaction = self.conn.transaction()?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO checklists (id, name, updated_at, data) VALUES (?1, ?2, ?3, ?4)",
+            params![list.id, list.name, list.updated_at, serde_json::to_string(list)?],
+        )?;
+        transaction.execute("DELETE FROM tasks WHERE list_id = ?1", params![list.id])?;
+        for task in &remote_tasks {
+            let Some(task_id) = task.id else {
+                continue;
+            };
+            transaction.execute(
+                "INSERT INTO tasks (id, list_id, parent_id, position, content, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    task_id,
+                    list.id,
+                    task.parent_id,
+                    task.position,
+                    task.content,
+                    serde_json::to_string(task)?
+                ],
+            )?;
+        }
+        transaction.commit()?;
+
+        Ok(report)
+    }
+
+    fn remove_list(&mut self, list: &Checklist) -> Result<ListSyncReport> {
+        info!(
+            "List {} is no longer available. Removing from mirror",
+            list.id
+        );
+        let mut report = ListSyncReport::unchanged(list);
+        report.removed = true;
+        report.removed_tasks = self.tasks(list.id)?;
+
+        let transaction = self.conn.transaction()?;
+        transaction.execute("DELETE FROM tasks WHERE list_id = ?1", params![list.id])?;
+        transaction.execute("DELETE FROM checklists WHERE id = ?1", params![list.id])?;
+        transaction.commit()?;
+
+        Ok(report)
+    }
+}
+
+impl ListSyncReport {
+    fn unchanged(list: &Checklist) -> Self {
+        ListSyncReport {
+            list_id: list.id,
+            name: list.name.clone(),
+            fetched: false,
+            removed: false,
+            added_tasks: Vec::new(),
+            changed_tasks: Vec::new(),
+            removed_tasks: Vec::new(),
+        }
+    }
+}
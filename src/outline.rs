@@ -0,0 +1,209 @@
+//! A list's tasks as a tree, rather than the flat vector Checkvist returns.
+//!
+//! Used for exporting (export.rs) and importing (import.rs), and anywhere else the
+//! shape of a list matters more than its ids.
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{due_date, Tags, Task, TaskStatus};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OutlineItem {
+    /// The Checkvist task id, if the item came from (or has been added to) Checkvist
+    pub id: Option<u32>,
+    pub content: String,
+    pub status: TaskStatus,
+    #[serde(default, deserialize_with = "crate::tags_format::deserialize")]
+    pub tags: Tags,
+    #[serde(default, with = "due_date::serde_format")]
+    pub due: Option<NaiveDate>,
+    pub notes: Vec<String>,
+    pub children: Vec<OutlineItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Outline {
+    pub title: String,
+    pub items: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    pub fn new(content: &str) -> Self {
+        Self {
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn from_task(task: &Task) -> Self {
+        Self {
+            id: task.id,
+            content: task.content.clone(),
+            status: task.status,
+            tags: task.tags.clone(),
+            due: task.due,
+            notes: task.notes.iter().map(|note| note.comment.clone()).collect(),
+            children: Vec::new(),
+        }
+    }
+
+    /// A new task with the item's fields (but not its id, status, notes or children,
+    /// which Checkvist can't set when adding a task)
+    pub fn to_task(&self, position: u16, parent_id: Option<u32>) -> Task {
+        Task {
+            content: self.content.clone(),
+            position,
+            parent_id,
+            due: self.due,
+            tags: self.tags.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// The number of items in this item's subtree, including itself
+    pub fn count(&self) -> usize {
+        1 + self.children.iter().map(OutlineItem::count).sum::<usize>()
+    }
+}
+
+impl Outline {
+    /// Builds the tree of all of `tasks`
+    pub fn from_tasks(title: &str, tasks: &[Task]) -> Self {
+        let children = children_by_parent(tasks);
+        Self {
+            title: title.to_string(),
+            items: build(&children, None),
+        }
+    }
+
+    /// Builds the tree of `root_id` and its descendants, or None if there's no
+    /// task with that id
+    pub fn from_subtree(title: &str, tasks: &[Task], root_id: u32) -> Option<Self> {
+        let root = tasks.iter().find(|task| task.id == Some(root_id))?;
+        let children = children_by_parent(tasks);
+        let mut item = OutlineItem::from_task(root);
+        item.children = build(&children, Some(root_id));
+
+        Some(Self {
+            title: title.to_string(),
+            items: vec![item],
+        })
+    }
+
+    /// The number of items in the outline, at every level
+    pub fn len(&self) -> usize {
+        self.items.iter().map(OutlineItem::count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Every item, depth first, with its depth (0 for top level items)
+    pub fn walk(&self) -> Vec<(usize, &OutlineItem)> {
+        fn walk_items<'a>(
+            items: &'a [OutlineItem],
+            depth: usize,
+            walked: &mut Vec<(usize, &'a OutlineItem)>,
+        ) {
+            for item in items {
+                walked.push((depth, item));
+                walk_items(&item.children, depth + 1, walked);
+            }
+        }
+        let mut walked = Vec::new();
+        walk_items(&self.items, 0, &mut walked);
+        walked
+    }
+}
+
+/// Tasks grouped by parent id (None for top level tasks), in position order
+fn children_by_parent(tasks: &[Task]) -> HashMap<Option<u32>, Vec<&Task>> {
+    let ids: HashSet<u32> = tasks.iter().filter_map(|task| task.id).collect();
+    let mut children: HashMap<Option<u32>, Vec<&Task>> = HashMap::new();
+    for task in tasks {
+        // top level tasks have parent 0 (or none)
+        let parent_id = task.parent_id.filter(|id| ids.contains(id));
+        children.entry(parent_id).or_default().push(task);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by_key(|task| task.position);
+    }
+    children
+}
+
+fn build(children: &HashMap<Option<u32>, Vec<&Task>>, parent_id: Option<u32>) -> Vec<OutlineItem> {
+    children
+        .get(&parent_id)
+        .map(|tasks| {
+            tasks
+                .iter()
+                .map(|task| {
+                    let mut item = OutlineItem::from_task(task);
+                    if let Some(id) = task.id {
+                        item.children = build(children, Some(id));
+                    }
+                    item
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn task(id: u32, position: u16, parent_id: u32) -> Task {
+        Task {
+            id: Some(id),
+            content: id.to_string(),
+            position,
+            parent_id: Some(parent_id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn builds_tree_in_position_order() {
+        let tasks = vec![task(1, 2, 0), task(2, 1, 0), task(3, 1, 1), task(4, 2, 1)];
+
+        let outline = Outline::from_tasks("list", &tasks);
+
+        let walked: Vec<(usize, &str)> = outline
+            .walk()
+            .into_iter()
+            .map(|(depth, item)| (depth, item.content.as_str()))
+            .collect();
+        assert_eq!(walked, vec![(0, "2"), (0, "1"), (1, "3"), (1, "4")]);
+        assert_eq!(outline.len(), 4);
+    }
+
+    #[test]
+    fn builds_subtree() {
+        let tasks = vec![task(1, 1, 0), task(2, 1, 1), task(3, 1, 2), task(4, 2, 0)];
+
+        let outline = Outline::from_subtree("list", &tasks, 2).unwrap();
+
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline.items[0].children[0].content, "3");
+        assert!(Outline::from_subtree("list", &tasks, 99).is_none());
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut item = OutlineItem::new("parent");
+        item.due = NaiveDate::from_ymd_opt(2023, 5, 1);
+        item.children = vec![OutlineItem::new("child")];
+        let outline = Outline {
+            title: "list".into(),
+            items: vec![item],
+        };
+
+        let json = serde_json::to_string(&outline).unwrap();
+
+        assert_eq!(serde_json::from_str::<Outline>(&json).unwrap(), outline);
+    }
+}
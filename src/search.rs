@@ -0,0 +1,230 @@
+//! Searching for tasks across lists.
+//!
+//! Checkvist's API has no search, so lists are fetched (concurrently) and searched
+//! here. Each match comes with its list, the path of ancestor tasks leading to it,
+//! and a permalink.
+use std::collections::HashMap;
+use std::thread;
+
+use log::info;
+use regex::{Regex, RegexBuilder};
+
+use crate::{Checklist, CheckvistClient, CheckvistError, Result, Task};
+
+/// How many lists are fetched at once
+const MAX_CONCURRENT_FETCHES: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum SearchQuery {
+    /// Case insensitive substring of the task content
+    Text(String),
+    Regex(Regex),
+    /// Case insensitive tag name, with or without a leading '#'
+    Tag(String),
+}
+
+impl SearchQuery {
+    /// Builds a query from user input: "/pattern/" is a (case insensitive) regex,
+    /// "#tag" is a tag, and anything else is text
+    pub fn parse(query: &str) -> Result<Self> {
+        let query = query.trim();
+        if let Some(pattern) = query
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            return Self::regex(pattern, false);
+        }
+        if let Some(tag) = query.strip_prefix('#') {
+            if !tag.is_empty() {
+                return Ok(Self::Tag(tag.to_string()));
+            }
+        }
+
+        Ok(Self::Text(query.to_string()))
+    }
+
+    pub fn regex(pattern: &str, case_sensitive: bool) -> Result<Self> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map(Self::Regex)
+            .map_err(|err| CheckvistError::InvalidQueryError {
+                message: err.to_string(),
+            })
+    }
+
+    pub fn is_match(&self, task: &Task) -> bool {
+        match self {
+            Self::Text(text) => task.content.to_lowercase().contains(&text.to_lowercase()),
+            Self::Regex(regex) => regex.is_match(&task.content),
+            Self::Tag(tag) => {
+                let tag = tag.trim_start_matches('#');
+                task.tags.keys().any(|t| t.eq_ignore_ascii_case(tag))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    pub list: Checklist,
+    pub task: Task,
+    /// The content of the task's ancestors, outermost first
+    pub path: Vec<String>,
+    pub permalink: String,
+}
+
+impl CheckvistClient {
+    /// Finds tasks matching `query` in the lists with `list_ids`, or in all of the
+    /// user's lists if None. Matches are ordered by list, then as the tasks appear
+    /// in the list (depth first).
+    pub fn search(
+        &self,
+        query: &SearchQuery,
+        list_ids: Option<&[u32]>,
+    ) -> Result<Vec<SearchMatch>> {
+        let mut lists = self.get_lists()?;
+        if let Some(list_ids) = list_ids {
+            if let Some(missing) = list_ids
+                .iter()
+                .find(|id| !lists.iter().any(|list| list.id == **id))
+            {
+                info!("List {} isn't one of the user's lists", missing);
+                return Err(CheckvistError::InvalidListError);
+            }
+            lists.retain(|list| list_ids.contains(&list.id));
+        }
+
+        let mut matches = Vec::new();
+        for chunk in lists.chunks(MAX_CONCURRENT_FETCHES) {
+            let fetched: Vec<Result<Vec<Task>>> = thread::scope(|scope| {
+                let handles: Vec<_> = chunk
+                    .iter()
+                    .map(|list| scope.spawn(|| self.get_tasks(list.id)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("list fetching thread panicked"))
+                    .collect()
+            });
+            for (list, tasks) in chunk.iter().zip(fetched) {
+                matches.extend(self.matches_in_list(query, list, tasks?));
+            }
+        }
+
+        Ok(matches)
+    }
+
+    /// The url of the list in the Checkvist web app
+    pub fn list_permalink(&self, list_id: u32) -> String {
+        CheckvistClient::build_endpoint(&self.base_url, vec!["/checklists/", &list_id.to_string()])
+            .to_string()
+    }
+
+    /// The url of the task in the Checkvist web app
+    pub fn task_permalink(&self, list_id: u32, task_id: u32) -> String {
+        CheckvistClient::build_endpoint(
+            &self.base_url,
+            vec![
+                "/checklists/",
+                &list_id.to_string(),
+                "/tasks/",
+                &task_id.to_string(),
+            ],
+        )
+        .to_string()
+    }
+
+    fn matches_in_list(
+        &self,
+        query: &SearchQuery,
+        list: &Checklist,
+        tasks: Vec<Task>,
+    ) -> Vec<SearchMatch> {
+        let by_id: HashMap<u32, &Task> = tasks
+            .iter()
+            .filter_map(|task| task.id.map(|id| (id, task)))
+            .collect();
+        let mut children: HashMap<Option<u32>, Vec<&Task>> = HashMap::new();
+        for task in &tasks {
+            // top level tasks have parent 0, or none
+            let parent_id = task.parent_id.filter(|id| by_id.contains_key(id));
+            children.entry(parent_id).or_default().push(task);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by_key(|task| task.position);
+        }
+
+        let mut matches = Vec::new();
+        // (task, path to it), depth first
+        let mut pending: Vec<(&Task, Vec<String>)> = children
+            .get(&None)
+            .map(|top| top.iter().rev().map(|task| (*task, Vec::new())).collect())
+            .unwrap_or_default();
+        while let Some((task, path)) = pending.pop() {
+            if let Some(task_children) = task.id.and_then(|id| children.get(&Some(id))) {
+                let mut child_path = path.clone();
+                child_path.push(task.content.clone());
+                for child in task_children.iter().rev() {
+                    pending.push((child, child_path.clone()));
+                }
+            }
+            if query.is_match(task) {
+                matches.push(SearchMatch {
+                    list: list.clone(),
+                    task: task.clone(),
+                    path,
+                    permalink: self.task_permalink(list.id, task.id.unwrap_or_default()),
+                });
+            }
+        }
+
+        matches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn task(content: &str, tags: &[&str]) -> Task {
+        Task {
+            content: content.into(),
+            tags: tags.iter().map(|tag| (tag.to_string(), false)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_query_types() {
+        assert!(matches!(
+            SearchQuery::parse("/a.c/"),
+            Ok(SearchQuery::Regex(_))
+        ));
+        assert!(matches!(SearchQuery::parse("#tag"), Ok(SearchQuery::Tag(tag)) if tag == "tag"));
+        assert!(
+            matches!(SearchQuery::parse(" words "), Ok(SearchQuery::Text(text)) if text == "words")
+        );
+        assert!(matches!(
+            SearchQuery::parse("/(/"),
+            Err(CheckvistError::InvalidQueryError { .. })
+        ));
+    }
+
+    #[test]
+    fn text_and_regex_ignore_case() {
+        let task = task("Fix the Bug", &[]);
+
+        assert!(SearchQuery::parse("the bug").unwrap().is_match(&task));
+        assert!(SearchQuery::parse("/^fix.*bug$/").unwrap().is_match(&task));
+        assert!(!SearchQuery::regex("^fix", true).unwrap().is_match(&task));
+    }
+
+    #[test]
+    fn tag_matches_whole_tag() {
+        let task = task("task", &["Work"]);
+
+        assert!(SearchQuery::parse("#work").unwrap().is_match(&task));
+        assert!(!SearchQuery::parse("#wor").unwrap().is_match(&task));
+    }
+}
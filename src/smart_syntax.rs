@@ -0,0 +1,260 @@
+//! Parses the Checkvist "smart syntax" embedded in task content:
+//! `^due` (or `^"multi word due"`), `#tag`, `!priority` (1-9) and `@assignee`.
+//!
+//! This lets callers preview what Checkvist will make of some content, and send
+//! the due date and tags as explicit task fields rather than leaving them in the text.
+use std::str::SplitWhitespace;
+
+use chrono::NaiveDate;
+
+use crate::due_date::DueDateResolver;
+use crate::Task;
+
+/// Task content split into plain text and the smart syntax found in it
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SmartContent {
+    /// The content with all recognised smart syntax removed
+    pub text: String,
+    /// The due date as written, eg. "tomorrow", or "2023-05-01"
+    pub due: Option<String>,
+    pub tags: Vec<String>,
+    pub priority: Option<u8>,
+    pub assignee: Option<String>,
+}
+
+/// Splits `content` into plain text and smart syntax.
+/// Where a due date, priority or assignee appears more than once, the first is
+/// used, and any later ones are left in the text. Lines without smart syntax are
+/// left untouched; on other lines, whitespace between the remaining words is
+/// collapsed to a single space.
+pub fn parse(content: &str) -> SmartContent {
+    let mut parsed = SmartContent::default();
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| parse_line(line, &mut parsed))
+        .collect();
+    parsed.text = lines.join("\n").trim().to_string();
+
+    parsed
+}
+
+fn parse_line(line: &str, parsed: &mut SmartContent) -> String {
+    let mut words: Vec<&str> = Vec::new();
+    let mut found_smart_syntax = false;
+    let mut tokens = line.split_whitespace();
+
+    while let Some(token) = tokens.next() {
+        if let Some(due) = token.strip_prefix("^\"") {
+            if parsed.due.is_none() {
+                if let Some((due, rest)) = quoted_due(due, &tokens) {
+                    parsed.due = Some(due);
+                    tokens = rest;
+                    found_smart_syntax = true;
+                    continue;
+                }
+            }
+        } else if let Some(due) = token.strip_prefix('^') {
+            if parsed.due.is_none() && !due.is_empty() {
+                parsed.due = Some(due.to_string());
+                found_smart_syntax = true;
+                continue;
+            }
+        } else if let Some(tag) = token.strip_prefix('#') {
+            if is_tag(tag) {
+                if !parsed.tags.iter().any(|t| t == tag) {
+                    parsed.tags.push(tag.to_string());
+                }
+                found_smart_syntax = true;
+                continue;
+            }
+        } else if let Some(priority) = token.strip_prefix('!') {
+            if parsed.priority.is_none() {
+                if let Some(priority) = parse_priority(priority) {
+                    parsed.priority = Some(priority);
+                    found_smart_syntax = true;
+                    continue;
+                }
+            }
+        } else if let Some(assignee) = token.strip_prefix('@') {
+            if parsed.assignee.is_none() && !assignee.is_empty() {
+                parsed.assignee = Some(assignee.to_string());
+                found_smart_syntax = true;
+                continue;
+            }
+        }
+        words.push(token);
+    }
+
+    if !found_smart_syntax {
+        return line.to_string();
+    }
+    let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+
+    format!("{}{}", indent, words.join(" "))
+}
+
+/// The due date in a `^"quoted due"` starting with `first` (the token after `^"`),
+/// and the tokens following the closing quote. None if the quote is never closed,
+/// so that the text is left as it is
+fn quoted_due<'a>(
+    first: &'a str,
+    tokens: &SplitWhitespace<'a>,
+) -> Option<(String, SplitWhitespace<'a>)> {
+    let mut tokens = tokens.clone();
+    let mut due_words = Vec::new();
+    let mut word = first;
+    loop {
+        if let Some(last) = word.strip_suffix('"') {
+            due_words.push(last);
+            let due = due_words.join(" ").trim().to_string();
+            return (!due.is_empty()).then_some((due, tokens));
+        }
+        due_words.push(word);
+        word = tokens.next()?;
+    }
+}
+
+fn is_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '/')
+}
+
+fn parse_priority(priority: &str) -> Option<u8> {
+    match priority.as_bytes() {
+        [digit @ b'1'..=b'9'] => Some(digit - b'0'),
+        _ => None,
+    }
+}
+
+impl SmartContent {
+    pub fn has_smart_syntax(&self) -> bool {
+        self.due.is_some()
+            || !self.tags.is_empty()
+            || self.priority.is_some()
+            || self.assignee.is_some()
+    }
+
+    /// The due date, resolved against `resolver`. None if there is no due date,
+    /// or it isn't one that `resolver` understands
+    pub fn resolved_due(&self, resolver: &DueDateResolver) -> Option<NaiveDate> {
+        self.due.as_ref().and_then(|due| resolver.resolve(due).ok())
+    }
+
+    /// Builds a task with the due date and tags as explicit fields.
+    /// The Checkvist API has no fields for priority or assignee, so these are
+    /// returned to the content as smart syntax for Checkvist to interpret. So is
+    /// any due date that `resolver` can't resolve.
+    pub fn to_task(
+        &self,
+        position: u16,
+        parent_id: Option<u32>,
+        resolver: &DueDateResolver,
+    ) -> Task {
+        let mut content = self.text.clone();
+        let due = self.resolved_due(resolver);
+        if let (None, Some(unresolved)) = (due, &self.due) {
+            content.push_str(&format!(r#" ^"{}""#, unresolved));
+        }
+        if let Some(priority) = self.priority {
+            content.push_str(&format!(" !{}", priority));
+        }
+        if let Some(ref assignee) = self.assignee {
+            content.push_str(&format!(" @{}", assignee));
+        }
+
+        Task {
+            id: None,
+            content,
+            position,
+            parent_id,
+            due,
+            tags: self.tags.iter().map(|tag| (tag.clone(), false)).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn content_without_smart_syntax_is_unchanged() {
+        let parsed = parse("just  some text\n  and more");
+
+        assert_eq!(parsed.text, "just  some text\n  and more");
+        assert!(!parsed.has_smart_syntax());
+    }
+
+    #[test]
+    fn parses_all_smart_syntax() {
+        let parsed = parse("fix the #bug in #cvcap ^tomorrow !1 @crispin");
+
+        assert_eq!(parsed.text, "fix the in");
+        assert_eq!(parsed.tags, vec!["bug", "cvcap"]);
+        assert_eq!(parsed.due, Some("tomorrow".into()));
+        assert_eq!(parsed.priority, Some(1));
+        assert_eq!(parsed.assignee, Some("crispin".into()));
+    }
+
+    #[test]
+    fn parses_quoted_due_date() {
+        let parsed = parse(r#"call mum ^"next friday" #family"#);
+
+        assert_eq!(parsed.text, "call mum");
+        assert_eq!(parsed.due, Some("next friday".into()));
+    }
+
+    #[test]
+    fn lookalikes_are_left_in_text() {
+        let parsed = parse("# heading !important !10 me@example.com ^ done");
+
+        assert_eq!(
+            parsed.text,
+            "# heading !important !10 me@example.com ^ done"
+        );
+        assert!(!parsed.has_smart_syntax());
+    }
+
+    #[test]
+    fn unclosed_quoted_due_date_is_left_in_text() {
+        let parsed = parse(r#"call mum ^"next friday #family"#);
+
+        assert_eq!(parsed.text, r#"call mum ^"next friday"#);
+        assert_eq!(parsed.due, None);
+        assert_eq!(parsed.tags, vec!["family"]);
+    }
+
+    #[test]
+    fn only_first_due_date_is_used() {
+        let parsed = parse("a ^today ^tomorrow");
+
+        assert_eq!(parsed.due, Some("today".into()));
+        assert_eq!(parsed.text, "a ^tomorrow");
+    }
+
+    #[test]
+    fn task_has_explicit_fields() {
+        let today = NaiveDate::from_ymd_opt(2023, 5, 10).unwrap();
+        let resolver = DueDateResolver::for_date(today);
+
+        let task = parse("a task #tag ^today !2").to_task(1, Some(3), &resolver);
+
+        assert_eq!(task.content, "a task !2");
+        assert_eq!(task.tags.keys().collect::<Vec<_>>(), vec!["tag"]);
+        assert_eq!(task.due, Some(today));
+        assert_eq!(task.parent_id, Some(3));
+    }
+
+    #[test]
+    fn unresolved_due_date_is_left_in_content() {
+        let resolver = DueDateResolver::for_date(NaiveDate::from_ymd_opt(2023, 5, 10).unwrap());
+
+        let task = parse("a task ^someday").to_task(1, None, &resolver);
+
+        assert_eq!(task.content, r#"a task ^"someday""#);
+        assert_eq!(task.due, None);
+    }
+}
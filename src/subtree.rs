@@ -0,0 +1,229 @@
+//! Copying and moving a task, with all of its descendants, to another location.
+//!
+//! Checkvist has no single operation for this, so the subtree is recreated task by task.
+//! A copy that fails partway returns a `SubtreeCopyError` holding the tasks created so
+//! far, which can be removed with `CheckvistClient::rollback_copy`.
+use std::collections::HashMap;
+use std::fmt;
+
+use log::{info, warn};
+
+use crate::{CheckvistClient, CheckvistError, CheckvistLocation, Task, TaskStatus};
+
+/// The result of a successful copy (or move)
+#[derive(Debug, PartialEq)]
+pub struct SubtreeCopy {
+    pub list_id: u32,
+    /// id of the copy of the subtree's root task
+    pub root_task_id: u32,
+    /// (original task id, copied task id), in the order the copies were created
+    pub id_map: Vec<(u32, u32)>,
+}
+
+impl SubtreeCopy {
+    /// The id of the copy of `original_id`, if it was copied
+    pub fn new_id(&self, original_id: u32) -> Option<u32> {
+        self.id_map
+            .iter()
+            .find(|(original, _copy)| *original == original_id)
+            .map(|(_original, copy)| *copy)
+    }
+}
+
+/// A copy or move that failed partway.
+/// `created` holds whatever had been copied before the failure
+#[derive(Debug)]
+pub struct SubtreeCopyError {
+    pub error: CheckvistError,
+    pub created: SubtreeCopy,
+}
+
+impl fmt::Display for SubtreeCopyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Copy failed after {} task(s) were copied: {}",
+            self.created.id_map.len(),
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for SubtreeCopyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+type CopyResult<T> = std::result::Result<T, SubtreeCopyError>;
+
+impl CheckvistClient {
+    /// Copies `task_id`, and all of its descendants, to `destination`.
+    /// The copy of `task_id` becomes the destination's first child. Relative order,
+    /// status, notes, tags, due dates and assignees are preserved.
+    pub fn copy_subtree(
+        &self,
+        list_id: u32,
+        task_id: u32,
+        destination: &CheckvistLocation,
+    ) -> CopyResult<SubtreeCopy> {
+        let mut copy = SubtreeCopy {
+            list_id: destination.list_id,
+            root_task_id: 0,
+            id_map: Vec::new(),
+        };
+        let tasks = match self.get_tasks_with_notes(list_id) {
+            Ok(tasks) => tasks,
+            Err(error) => {
+                return Err(SubtreeCopyError {
+                    error,
+                    created: copy,
+                })
+            }
+        };
+        let Some(root) = tasks.iter().find(|task| task.id == Some(task_id)) else {
+            return Err(SubtreeCopyError {
+                error: CheckvistError::InvalidTaskError,
+                created: copy,
+            });
+        };
+
+        info!(
+            "Copying task {} from list {} to {:?}",
+            task_id, list_id, destination
+        );
+        let mut children = HashMap::new();
+        for task in &tasks {
+            if let Some(parent_id) = task.parent_id {
+                children
+                    .entry(parent_id)
+                    .or_insert_with(Vec::new)
+                    .push(task);
+            }
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by_key(|task| task.position);
+        }
+
+        // depth first, so each parent is created before its children
+        let mut pending = vec![(root, destination.parent_task_id, 1)];
+        while let Some((task, new_parent_id, position)) = pending.pop() {
+            let new_task = Self::copy_of(task, new_parent_id, position);
+            let new_id = match self.add_task(destination.list_id, &new_task) {
+                Ok(Task {
+                    id: Some(new_id), ..
+                }) => new_id,
+                Ok(_) => {
+                    return Err(SubtreeCopyError {
+                        error: CheckvistError::UnknownError {
+                            message: "Checkvist returned a new task without an id".into(),
+                        },
+                        created: copy,
+                    })
+                }
+                Err(error) => {
+                    return Err(SubtreeCopyError {
+                        error,
+                        created: copy,
+                    })
+                }
+            };
+            if copy.id_map.is_empty() {
+                copy.root_task_id = new_id;
+            }
+            copy.id_map.push((task.id.unwrap_or_default(), new_id));
+            if let Err(error) = self.copy_notes_and_status(destination.list_id, new_id, task) {
+                return Err(SubtreeCopyError {
+                    error,
+                    created: copy,
+                });
+            }
+
+            if let Some(task_children) = task.id.and_then(|id| children.get(&id)) {
+                // reversed so that pop() takes them in order
+                for (index, child) in task_children.iter().enumerate().rev() {
+                    pending.push((child, Some(new_id), index as u16 + 1));
+                }
+            }
+        }
+
+        Ok(copy)
+    }
+
+    /// Copies the subtree as `copy_subtree`, then deletes the original.
+    /// A destination within the subtree is refused before anything is copied, as
+    /// deleting the original would delete the copy too.
+    /// If the delete fails, the returned error holds the complete copy, which
+    /// can be kept, or removed with `rollback_copy`
+    pub fn move_subtree(
+        &self,
+        list_id: u32,
+        task_id: u32,
+        destination: &CheckvistLocation,
+    ) -> CopyResult<SubtreeCopy> {
+        let refused = |error| SubtreeCopyError {
+            error,
+            created: SubtreeCopy {
+                list_id: destination.list_id,
+                root_task_id: 0,
+                id_map: Vec::new(),
+            },
+        };
+        if let (true, Some(parent_id)) =
+            (destination.list_id == list_id, destination.parent_task_id)
+        {
+            // the destination parent, and its ancestors
+            let ancestors = self.get_task(list_id, parent_id).map_err(refused)?;
+            if ancestors.iter().any(|task| task.id == Some(task_id)) {
+                return Err(refused(CheckvistError::MoveIntoSubtreeError { task_id }));
+            }
+        }
+        let copy = self.copy_subtree(list_id, task_id, destination)?;
+        match self.delete_task(list_id, task_id) {
+            Ok(()) => Ok(copy),
+            Err(error) => Err(SubtreeCopyError {
+                error,
+                created: copy,
+            }),
+        }
+    }
+
+    /// Deletes the tasks created by a failed copy or move
+    pub fn rollback_copy(&self, failed: &SubtreeCopyError) -> crate::Result<()> {
+        if failed.created.id_map.is_empty() {
+            return Ok(());
+        }
+        warn!(
+            "Rolling back copy of {} task(s)",
+            failed.created.id_map.len()
+        );
+        // deleting the root deletes all of its descendants
+        self.delete_task(failed.created.list_id, failed.created.root_task_id)
+    }
+
+    /// A new task with `task`'s content, but none of its children or notes
+    fn copy_of(task: &Task, parent_id: Option<u32>, position: u16) -> Task {
+        Task {
+            id: None,
+            content: task.content.clone(),
+            position,
+            parent_id,
+            due: task.due,
+            tags: task.tags.clone(),
+            assignee_ids: task.assignee_ids.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Status and notes can't be set when adding a task, so are copied afterwards
+    fn copy_notes_and_status(&self, list_id: u32, new_id: u32, task: &Task) -> crate::Result<()> {
+        for note in &task.notes {
+            self.add_note(list_id, new_id, &note.comment)?;
+        }
+        if task.status != TaskStatus::Open {
+            self.set_task_status(list_id, new_id, task.status)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! Resolving a path of names, like "Work > Inbox > Bugs", to a `CheckvistLocation`.
+//!
+//! The first segment names a list, and each following segment a child task of the
+//! one before. Names are matched ignoring case and surrounding whitespace, though an
+//! exact match is preferred when a name matches more than one list or task.
+//! A literal '>' in a name can be written as "\>".
+use std::fmt;
+
+use log::info;
+
+use crate::{CheckvistClient, CheckvistError, CheckvistLocation, Result, Task};
+
+const SEPARATOR: char = '>';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskPath {
+    segments: Vec<String>,
+}
+
+impl TaskPath {
+    pub fn parse(path: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut segment = String::new();
+        let mut chars = path.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&SEPARATOR) => {
+                    segment.push(SEPARATOR);
+                    chars.next();
+                }
+                SEPARATOR => segments.push(std::mem::take(&mut segment)),
+                c => segment.push(c),
+            }
+        }
+        segments.push(segment);
+        let segments: Vec<String> = segments.iter().map(|s| s.trim().to_string()).collect();
+
+        if segments.iter().any(String::is_empty) {
+            return Err(CheckvistError::InvalidPathError {
+                path: path.to_string(),
+                message: "names in a path can't be empty".into(),
+            });
+        }
+
+        Ok(Self { segments })
+    }
+
+    pub fn list_name(&self) -> &str {
+        &self.segments[0]
+    }
+
+    /// The names of the tasks, outermost first
+    pub fn task_names(&self) -> &[String] {
+        &self.segments[1..]
+    }
+}
+
+impl fmt::Display for TaskPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let escaped: Vec<String> = self
+            .segments
+            .iter()
+            .map(|s| s.replace(SEPARATOR, "\\>"))
+            .collect();
+        write!(f, "{}", escaped.join(" > "))
+    }
+}
+
+impl CheckvistClient {
+    /// Finds the location named by `path`. Errors if any name in it is missing
+    /// or ambiguous
+    pub fn resolve_path(&self, path: &TaskPath) -> Result<CheckvistLocation> {
+        self.resolve(path, false)
+    }
+
+    /// As `resolve_path`, but creates any missing list or tasks (each new task
+    /// being added as the last child of its parent)
+    pub fn resolve_or_create_path(&self, path: &TaskPath) -> Result<CheckvistLocation> {
+        self.resolve(path, true)
+    }
+
+    fn resolve(&self, path: &TaskPath, create: bool) -> Result<CheckvistLocation> {
+        let lists = self.get_lists()?;
+        let list_id = match find_by_name(path, path.list_name(), &lists, |list| &list.name)? {
+            Some(list) => list.id,
+            None if create => {
+                info!("Creating list '{}'", path.list_name());
+                self.add_list(path.list_name())?.id
+            }
+            None => return Err(not_found(path, path.list_name())),
+        };
+        let mut location = CheckvistLocation {
+            list_id,
+            parent_task_id: None,
+        };
+        if path.task_names().is_empty() {
+            return Ok(location);
+        }
+
+        let tasks = self.get_tasks(list_id)?;
+        let mut created_one = false;
+        for name in path.task_names() {
+            let siblings: Vec<&Task> = if created_one {
+                // the parent was only just created, so can't have children
+                Vec::new()
+            } else {
+                children_of(&tasks, location.parent_task_id)
+            };
+            let task_id = match find_by_name(path, name, &siblings, |task| &task.content)? {
+                Some(task) => task.id.unwrap_or_default(),
+                None if create => {
+                    info!("Creating task '{}' in list {}", name, list_id);
+                    created_one = true;
+                    let new_task = Task {
+                        content: name.clone(),
+                        position: siblings.len() as u16 + 1,
+                        parent_id: location.parent_task_id,
+                        ..Default::default()
+                    };
+                    self.add_task(list_id, &new_task)?
+                        .id
+                        .ok_or(CheckvistError::UnknownError {
+                            message: "Checkvist returned a new task without an id".into(),
+                        })?
+                }
+                None => return Err(not_found(path, name)),
+            };
+            location.parent_task_id = Some(task_id);
+        }
+
+        Ok(location)
+    }
+}
+
+/// The children of `parent_id` (or top level tasks if None)
+pub(crate) fn children_of(tasks: &[Task], parent_id: Option<u32>) -> Vec<&Task> {
+    let is_top_level = |task: &Task| match task.parent_id {
+        None | Some(0) => true,
+        Some(id) => !tasks.iter().any(|t| t.id == Some(id)),
+    };
+    tasks
+        .iter()
+        .filter(|task| match parent_id {
+            Some(parent_id) => task.parent_id == Some(parent_id),
+            None => is_top_level(task),
+        })
+        .collect()
+}
+
+/// The single item named `name`, or None. Errors if there's more than one
+fn find_by_name<'a, T>(
+    path: &TaskPath,
+    name: &str,
+    items: &'a [T],
+    item_name: impl Fn(&T) -> &String,
+) -> Result<Option<&'a T>> {
+    let matching: Vec<&T> = items
+        .iter()
+        .filter(|item| item_name(item).trim().eq_ignore_ascii_case(name))
+        .collect();
+    let exact: Vec<&T> = matching
+        .iter()
+        .copied()
+        .filter(|item| item_name(item).trim() == name)
+        .collect();
+
+    match (matching.as_slice(), exact.as_slice()) {
+        ([], _) => Ok(None),
+        ([only], _) | (_, [only]) => Ok(Some(only)),
+        (all, _) => Err(CheckvistError::AmbiguousPathError {
+            path: path.to_string(),
+            name: name.to_string(),
+            count: all.len(),
+        }),
+    }
+}
+
+fn not_found(path: &TaskPath, name: &str) -> CheckvistError {
+    CheckvistError::InvalidPathError {
+        path: path.to_string(),
+        message: format!("couldn't find '{}'", name),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_segments() {
+        let path = TaskPath::parse(" Work >Inbox > a \\> b").unwrap();
+
+        assert_eq!(path.list_name(), "Work");
+        assert_eq!(path.task_names(), ["Inbox", "a > b"]);
+        assert_eq!(path.to_string(), "Work > Inbox > a \\> b");
+    }
+
+    #[test]
+    fn empty_segments_are_invalid() {
+        assert!(matches!(
+            TaskPath::parse("Work >  > Bugs"),
+            Err(CheckvistError::InvalidPathError { .. })
+        ));
+        assert!(TaskPath::parse("").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use cvapi::batch::{BatchOperation, BatchOptions, BatchOutcome};
+use cvapi::{CheckvistClient, CheckvistError, Task, TaskStatus};
+use cvfake::FakeCheckvist;
+
+fn add(list_id: u32, content: &str) -> BatchOperation {
+    BatchOperation::AddTask {
+        list_id,
+        task: Task {
+            content: content.into(),
+            position: 1,
+            ..Default::default()
+        },
+    }
+}
+
+#[test]
+fn results_are_in_input_order_with_typed_errors() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let existing = server.add_task(list_id, "existing", None);
+    let client = CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()));
+    let mut operations: Vec<BatchOperation> = (0..10)
+        .map(|i| add(list_id, &format!("task {}", i)))
+        .collect();
+    operations.insert(3, add(999, "in a missing list"));
+    operations.push(BatchOperation::SetTaskStatus {
+        list_id,
+        task_id: existing,
+        status: TaskStatus::Closed,
+    });
+
+    let results = client.run_batch(&operations, &BatchOptions::default());
+
+    assert_eq!(results.len(), 12);
+    assert!(matches!(results[3], Err(CheckvistError::InvalidListError)));
+    let Ok(BatchOutcome::Task(ref task)) = results[4] else {
+        panic!("expected a task, got {:?}", results[4]);
+    };
+    assert_eq!(task.content, "task 3");
+    assert!(matches!(results[11], Ok(BatchOutcome::Done)));
+    assert_eq!(server.tasks(list_id).len(), 11);
+    assert_eq!(server.task(existing).unwrap().status, 1);
+}
+
+#[test]
+fn concurrency_is_bounded() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    server.set_latency(Duration::from_millis(50));
+    let client = CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()));
+    let operations: Vec<BatchOperation> = (0..12).map(|i| add(list_id, &i.to_string())).collect();
+
+    let results = client.run_batch(
+        &operations,
+        &BatchOptions {
+            concurrency: 3,
+            ..Default::default()
+        },
+    );
+
+    assert!(results.iter().all(Result::is_ok));
+    assert!(server.max_concurrent_requests() <= 3);
+    assert!(server.max_concurrent_requests() > 1);
+}
+
+#[test]
+fn expired_token_is_refreshed_once_for_all_workers() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&refreshes);
+    let client = CheckvistClient::new(
+        &server.uri(),
+        &server.token(),
+        Box::new(move |_token| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+    server.expire_tokens();
+    let operations: Vec<BatchOperation> = (0..8).map(|i| add(list_id, &i.to_string())).collect();
+
+    let results = client.run_batch(&operations, &BatchOptions::default());
+
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn rate_limited_operations_are_retried() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let client = CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()));
+    server.rate_limit(3);
+    let operations: Vec<BatchOperation> = (0..5).map(|i| add(list_id, &i.to_string())).collect();
+
+    let results = client.run_batch(&operations, &BatchOptions::default());
+
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(server.tasks(list_id).len(), 5);
+}
+
+#[test]
+fn rate_limit_error_is_returned_when_retries_run_out() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let client = CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()));
+    server.rate_limit(10);
+
+    let results = client.run_batch(
+        &[add(list_id, "task")],
+        &BatchOptions {
+            max_retries: 2,
+            ..Default::default()
+        },
+    );
+
+    assert!(matches!(
+        results[0],
+        Err(CheckvistError::RateLimitedError {
+            retry_after: Some(_)
+        })
+    ));
+}
@@ -0,0 +1,117 @@
+use std::fs;
+use std::time::Duration;
+
+use temp_dir::TempDir;
+
+use cvapi::cassette::{Cassette, Interaction};
+use cvapi::{CheckvistClient, CheckvistError, Task};
+use cvfake::FakeCheckvist;
+
+/// Records a session against the fake server, which is stopped before returning
+fn record_session(cassette_path: &std::path::Path) -> (u32, Vec<Task>) {
+    let server = FakeCheckvist::start();
+    server.add_user("user", "key");
+    let token = CheckvistClient::get_token(&server.uri(), "user", "key").unwrap();
+    let list_id = server.add_list("list");
+    server.add_task(list_id, "a task", None);
+    let client = CheckvistClient::new(&server.uri(), &token, Box::new(|_token| ()))
+        .with_cassette(Cassette::record(cassette_path));
+
+    client.get_lists().unwrap();
+    server.expire_tokens();
+    client
+        .add_task(
+            list_id,
+            &Task {
+                content: "another task".into(),
+                position: 2,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let tasks = client.get_tasks(list_id).unwrap();
+
+    (list_id, tasks)
+}
+
+#[test]
+fn replay_returns_recorded_responses_without_network() {
+    let temp_dir = TempDir::new().unwrap();
+    let cassette_path = temp_dir.child("session.json");
+    let (list_id, recorded_tasks) = record_session(&cassette_path);
+
+    // nothing listens here, so any real request would fail
+    let client = CheckvistClient::new("http://127.0.0.1:9", "any token", Box::new(|_token| ()))
+        .with_cassette(Cassette::replay(&cassette_path).unwrap());
+
+    assert_eq!(client.get_lists().unwrap()[0].name, "list");
+    client
+        .add_task(
+            list_id,
+            &Task {
+                content: "another task".into(),
+                position: 2,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(client.get_tasks(list_id).unwrap(), recorded_tasks);
+    assert!(client.cassette().unwrap().unplayed().is_empty());
+}
+
+#[test]
+fn recorded_tokens_are_scrubbed() {
+    let temp_dir = TempDir::new().unwrap();
+    let cassette_path = temp_dir.child("session.json");
+    record_session(&cassette_path);
+
+    let recording = fs::read_to_string(&cassette_path).unwrap();
+
+    assert!(recording.contains("/auth/refresh_token.json"));
+    assert!(!recording.contains("fake-token"));
+}
+
+#[test]
+fn replay_fails_on_unmatched_request() {
+    let temp_dir = TempDir::new().unwrap();
+    let cassette_path = temp_dir.child("session.json");
+    let (list_id, _tasks) = record_session(&cassette_path);
+    let client = CheckvistClient::new("http://127.0.0.1:9", "any token", Box::new(|_token| ()))
+        .with_cassette(Cassette::replay(&cassette_path).unwrap());
+
+    let result = client.get_list(list_id);
+
+    let Err(CheckvistError::NetworkError(err)) = result else {
+        panic!("expected a network error, got {:?}", result);
+    };
+    assert!(err.to_string().contains("no recorded response"));
+}
+
+#[test]
+fn replayed_responses_keep_their_headers() {
+    let temp_dir = TempDir::new().unwrap();
+    let cassette_path = temp_dir.child("rate_limited.json");
+    let limited = Interaction {
+        method: "GET".into(),
+        path: "/checklists.json".into(),
+        request_body: None,
+        status: 429,
+        status_text: "Too Many Requests".into(),
+        response_headers: vec![("retry-after".into(), "30".into())],
+        response_body: String::new(),
+    };
+    fs::write(
+        &cassette_path,
+        serde_json::to_string(&vec![limited]).unwrap(),
+    )
+    .unwrap();
+    let client = CheckvistClient::new("http://127.0.0.1:9", "any token", Box::new(|_token| ()))
+        .with_cassette(Cassette::replay(&cassette_path).unwrap());
+
+    let result = client.get_lists();
+
+    assert!(matches!(
+        result,
+        Err(CheckvistError::RateLimitedError { retry_after: Some(wait) }) if wait == Duration::from_secs(30)
+    ));
+}
@@ -0,0 +1,96 @@
+use cvapi::export::ExportFormat;
+use cvapi::{CheckvistClient, CheckvistError};
+use cvfake::FakeCheckvist;
+
+fn client(server: &FakeCheckvist) -> CheckvistClient {
+    CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()))
+}
+
+/// Shopping
+///   Food
+///     Bread (closed, with a note)
+///     Milk
+///   Soap
+fn shopping_list(server: &FakeCheckvist) -> (u32, u32) {
+    let list_id = server.add_list("Shopping");
+    let food = server.add_task(list_id, "Food", None);
+    let bread = server.add_task(list_id, "Bread", Some(food));
+    server.add_task(list_id, "Milk", Some(food));
+    server.add_task(list_id, "Soap", None);
+    let client = client(server);
+    client.close_task(list_id, bread).unwrap();
+    client.add_note(list_id, bread, "wholemeal").unwrap();
+    (list_id, food)
+}
+
+#[test]
+fn exports_whole_list_as_markdown() {
+    let server = FakeCheckvist::start();
+    let (list_id, _food) = shopping_list(&server);
+
+    let markdown = client(&server)
+        .export_list(list_id, ExportFormat::Markdown)
+        .unwrap();
+
+    assert_eq!(
+        markdown,
+        "# Shopping\n\n\
+         - [ ] Food\n\
+         \x20 - [x] Bread\n\
+         \x20       wholemeal\n\
+         \x20 - [ ] Milk\n\
+         - [ ] Soap\n"
+    );
+}
+
+#[test]
+fn exports_subtree_as_text_and_json() {
+    let server = FakeCheckvist::start();
+    let (list_id, food) = shopping_list(&server);
+    let client = client(&server);
+
+    let text = client
+        .export_subtree(list_id, food, ExportFormat::Text)
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_str(
+        &client
+            .export_subtree(list_id, food, ExportFormat::Json)
+            .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(text, "Food\n  Bread\n  Milk\n");
+    assert_eq!(json["title"], "Shopping");
+    assert_eq!(json["tasks"][0]["children"][0]["status"], "closed");
+    assert_eq!(json["tasks"][0]["children"][0]["notes"][0], "wholemeal");
+}
+
+#[test]
+fn exports_opml() {
+    let server = FakeCheckvist::start();
+    let (list_id, _food) = shopping_list(&server);
+
+    let opml = client(&server)
+        .export_list(list_id, ExportFormat::Opml)
+        .unwrap();
+
+    assert!(opml.starts_with("<?xml"));
+    assert!(opml.contains("<outline text=\"Bread\" _complete=\"true\" _note=\"wholemeal\"/>"));
+}
+
+#[test]
+fn export_of_missing_subtree_errors() {
+    let server = FakeCheckvist::start();
+    let (list_id, _food) = shopping_list(&server);
+
+    let result = client(&server).export_subtree(list_id, 999, ExportFormat::Markdown);
+
+    assert!(matches!(result, Err(CheckvistError::InvalidTaskError)));
+}
+
+#[test]
+fn parses_format_names() {
+    assert_eq!("MD".parse(), Ok(ExportFormat::Markdown));
+    assert_eq!("opml".parse(), Ok(ExportFormat::Opml));
+    assert!("pdf".parse::<ExportFormat>().is_err());
+}
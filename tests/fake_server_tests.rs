@@ -0,0 +1,300 @@
+//! End to end tests against the fake Checkvist server, which (unlike a mock) keeps
+//! state between requests
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use cvapi::{CheckvistClient, CheckvistError, CheckvistLocation, Task, TaskStatus};
+use cvfake::FakeCheckvist;
+
+fn logged_in_client(server: &FakeCheckvist) -> CheckvistClient {
+    server.add_user("user", "key");
+    let token = CheckvistClient::get_token(&server.uri(), "user", "key").unwrap();
+    CheckvistClient::new(&server.uri(), &token, Box::new(|_token| ()))
+}
+
+fn new_task(content: &str, position: u16, parent_id: Option<u32>) -> Task {
+    Task {
+        content: content.into(),
+        position,
+        parent_id,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn login_with_wrong_key_fails() {
+    let server = FakeCheckvist::start();
+    server.add_user("user", "key");
+
+    let result = CheckvistClient::get_token(&server.uri(), "user", "wrong key");
+
+    assert!(matches!(result, Err(CheckvistError::UnknownError { .. })));
+}
+
+#[test]
+fn added_lists_and_tasks_are_returned() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+
+    let list = client.add_list("A list").unwrap();
+    let parent = client
+        .add_task(list.id, &new_task("parent", 1, None))
+        .unwrap();
+    client
+        .add_task(list.id, &new_task("child", 1, parent.id))
+        .unwrap();
+
+    assert_eq!(
+        client.get_lists().unwrap(),
+        vec![client.get_list(list.id).unwrap()]
+    );
+    let tasks = client.get_tasks(list.id).unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[1].content, "child");
+    assert_eq!(tasks[1].parent_id, parent.id);
+    assert_eq!(client.get_list(list.id).unwrap().task_count, 2);
+}
+
+#[test]
+fn positions_shift_as_siblings_are_added_and_deleted() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+    let first = server.add_task(list_id, "first", None);
+    let second = server.add_task(list_id, "second", None);
+
+    let inserted = client
+        .add_task(list_id, &new_task("inserted", 1, None))
+        .unwrap();
+    assert_eq!(server.task(first).unwrap().position, 2);
+
+    client.delete_task(list_id, inserted.id.unwrap()).unwrap();
+    assert_eq!(server.task(first).unwrap().position, 1);
+    assert_eq!(server.task(second).unwrap().position, 2);
+}
+
+#[test]
+fn deleting_a_task_deletes_its_descendants() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+    let parent = server.add_task(list_id, "parent", None);
+    let child = server.add_task(list_id, "child", Some(parent));
+    server.add_task(list_id, "grandchild", Some(child));
+
+    client.delete_task(list_id, parent).unwrap();
+
+    assert!(server.tasks(list_id).is_empty());
+}
+
+#[test]
+fn adding_to_missing_parent_fails() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+
+    let result = client.add_task(list_id, &new_task("orphan", 1, Some(999)));
+
+    assert!(matches!(result, Err(CheckvistError::InvalidParentIdError)));
+}
+
+#[test]
+fn get_task_returns_task_and_its_parents() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+    let parent = server.add_task(list_id, "parent", None);
+    let child = server.add_task(list_id, "child", Some(parent));
+
+    let tasks = client.get_task(list_id, child).unwrap();
+
+    let ids: Vec<Option<u32>> = tasks.iter().map(|task| task.id).collect();
+    assert_eq!(ids, vec![Some(child), Some(parent)]);
+    assert!(client
+        .is_location_valid(&CheckvistLocation {
+            list_id,
+            parent_task_id: Some(child)
+        })
+        .unwrap());
+}
+
+#[test]
+fn missing_task_is_an_invalid_location() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+
+    let result = client.get_task(list_id, 999);
+
+    assert!(matches!(result, Err(CheckvistError::InvalidTaskError)));
+    assert!(!client
+        .is_location_valid(&CheckvistLocation {
+            list_id,
+            parent_task_id: Some(999)
+        })
+        .unwrap());
+}
+
+#[test]
+fn notes_and_status_are_kept() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+    let task_id = server.add_task(list_id, "task", None);
+
+    client.add_note(list_id, task_id, "a note").unwrap();
+    client.close_task(list_id, task_id).unwrap();
+
+    let tasks = client.get_tasks_with_notes(list_id).unwrap();
+    assert_eq!(tasks[0].notes[0].comment, "a note");
+    assert_eq!(tasks[0].status, TaskStatus::Closed);
+}
+
+#[test]
+fn expired_token_is_refreshed() {
+    let server = FakeCheckvist::start();
+    server.add_user("user", "key");
+    let token = CheckvistClient::get_token(&server.uri(), "user", "key").unwrap();
+    let list_id = server.add_list("list");
+    let refreshed_token = Arc::new(Mutex::new(String::new()));
+    let callback_token = Arc::clone(&refreshed_token);
+    let client = CheckvistClient::new(
+        &server.uri(),
+        &token,
+        Box::new(move |token| *callback_token.lock().unwrap() = token.to_string()),
+    );
+
+    server.expire_tokens();
+    let list = client.get_list(list_id).unwrap();
+
+    assert_eq!(list.name, "list");
+    assert!(!refreshed_token.lock().unwrap().is_empty());
+    assert_ne!(*refreshed_token.lock().unwrap(), token);
+}
+
+#[test]
+fn failed_token_refresh_is_reported() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    server.expire_tokens();
+    server.fail_token_refresh(true);
+
+    let result = client.get_lists();
+
+    assert!(matches!(
+        result,
+        Err(CheckvistError::TokenRefreshFailedError)
+    ));
+}
+
+#[test]
+fn forbidden_list_is_invalid() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+    server.forbid_list(list_id);
+
+    let result = client.add_task(list_id, &new_task("task", 1, None));
+
+    assert!(matches!(result, Err(CheckvistError::InvalidListError)));
+    assert!(!client
+        .is_location_valid(&CheckvistLocation {
+            list_id,
+            parent_task_id: None
+        })
+        .unwrap());
+    assert!(client.get_lists().unwrap().is_empty());
+}
+
+#[test]
+fn task_in_forbidden_list_is_invalid() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+    let task_id = server.add_task(list_id, "task", None);
+    server.forbid_list(list_id);
+
+    assert!(!client
+        .is_location_valid(&CheckvistLocation {
+            list_id,
+            parent_task_id: Some(task_id)
+        })
+        .unwrap());
+}
+
+#[test]
+fn responses_can_be_slowed() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    server.set_latency(Duration::from_millis(200));
+
+    let start = Instant::now();
+    client.get_lists().unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[test]
+fn subtree_copy_round_trip() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let source = server.add_list("source");
+    let destination = server.add_list("destination");
+    let root = server.add_task(source, "root", None);
+    server.add_task(source, "first", Some(root));
+    server.add_task(source, "second", Some(root));
+
+    client
+        .move_subtree(
+            source,
+            root,
+            &CheckvistLocation {
+                list_id: destination,
+                parent_task_id: None,
+            },
+        )
+        .unwrap();
+
+    assert!(server.tasks(source).is_empty());
+    let copied: Vec<(String, u16)> = server
+        .tasks(destination)
+        .into_iter()
+        .map(|task| (task.content, task.position))
+        .collect();
+    assert_eq!(
+        copied,
+        vec![
+            ("root".to_string(), 1),
+            ("first".to_string(), 1),
+            ("second".to_string(), 2)
+        ]
+    );
+}
+
+#[test]
+fn subtree_cannot_be_moved_into_itself() {
+    let server = FakeCheckvist::start();
+    let client = logged_in_client(&server);
+    let list_id = server.add_list("list");
+    let root = server.add_task(list_id, "root", None);
+    let child = server.add_task(list_id, "child", Some(root));
+
+    for parent_task_id in [root, child] {
+        let result = client.move_subtree(
+            list_id,
+            root,
+            &CheckvistLocation {
+                list_id,
+                parent_task_id: Some(parent_task_id),
+            },
+        );
+
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error.error,
+            CheckvistError::MoveIntoSubtreeError { task_id } if task_id == root
+        ));
+        assert!(error.created.id_map.is_empty());
+    }
+    assert_eq!(server.tasks(list_id).len(), 2);
+}
@@ -0,0 +1,120 @@
+use cvapi::idempotent::{AddOutcome, IdempotencyKey};
+use cvapi::{CheckvistClient, Task};
+use cvfake::FakeCheckvist;
+
+fn client(server: &FakeCheckvist) -> CheckvistClient {
+    CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()))
+}
+
+fn task(content: &str, parent_id: Option<u32>) -> Task {
+    Task {
+        content: content.into(),
+        position: 1,
+        parent_id,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn retry_with_same_marker_finds_existing_task() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let client = client(&server);
+    let key = IdempotencyKey::Marker("capture-1".into());
+
+    let first = client
+        .add_task_idempotent(list_id, &task("a task", None), &key)
+        .unwrap();
+    let second = client
+        .add_task_idempotent(list_id, &task("a task", None), &key)
+        .unwrap();
+
+    assert!(first.was_created());
+    assert!(matches!(second, AddOutcome::AlreadyExisted(_)));
+    assert_eq!(first.task().id, second.task().id);
+    assert_eq!(server.tasks(list_id).len(), 1);
+    assert_eq!(
+        server.tasks(list_id)[0].tags_as_text,
+        key.marker_tag().unwrap()
+    );
+}
+
+#[test]
+fn different_markers_allow_identical_content() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let client = client(&server);
+
+    for key in ["one", "two"] {
+        client
+            .add_task_idempotent(
+                list_id,
+                &task("same", None),
+                &IdempotencyKey::Marker(key.into()),
+            )
+            .unwrap();
+    }
+
+    assert_eq!(server.tasks(list_id).len(), 2);
+}
+
+#[test]
+fn content_key_only_checks_siblings() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let parent = server.add_task(list_id, "parent", None);
+    server.add_task(list_id, "a task", None);
+    let client = client(&server);
+
+    let outcome = client
+        .add_task_idempotent(
+            list_id,
+            &task("a task", Some(parent)),
+            &IdempotencyKey::Content,
+        )
+        .unwrap();
+    let retried = client
+        .add_task_idempotent(
+            list_id,
+            &task("a task", Some(parent)),
+            &IdempotencyKey::Content,
+        )
+        .unwrap();
+
+    assert!(outcome.was_created());
+    assert!(!retried.was_created());
+    assert_eq!(server.tasks(list_id).len(), 3);
+}
+
+#[test]
+fn lost_response_to_accepted_task_is_recovered() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let client = client(&server);
+    server.lose_responses(1);
+
+    let outcome = client
+        .add_task_idempotent(
+            list_id,
+            &task("a task", None),
+            &IdempotencyKey::Marker("key".into()),
+        )
+        .unwrap();
+
+    assert!(outcome.was_created());
+    assert_eq!(outcome.task().content, "a task");
+    assert_eq!(server.tasks(list_id).len(), 1);
+}
+
+#[test]
+fn plain_add_duplicates_after_lost_response() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("list");
+    let client = client(&server);
+    server.lose_responses(1);
+
+    assert!(client.add_task(list_id, &task("a task", None)).is_err());
+    client.add_task(list_id, &task("a task", None)).unwrap();
+
+    assert_eq!(server.tasks(list_id).len(), 2);
+}
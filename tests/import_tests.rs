@@ -0,0 +1,149 @@
+use cvapi::due_date::DueDateResolver;
+use cvapi::export::ExportFormat;
+use cvapi::import::{parse_indented, parse_markdown, parse_opml};
+use cvapi::{CheckvistClient, CheckvistError, CheckvistLocation, TaskStatus};
+use cvfake::FakeCheckvist;
+
+fn client(server: &FakeCheckvist) -> CheckvistClient {
+    CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()))
+}
+
+const MARKDOWN: &str = "# Shopping
+
+- [ ] Food #errands
+  - [x] Bread
+        wholemeal
+  - [ ] Milk ^2023-05-01
+- [ ] Soap
+";
+
+#[test]
+fn imports_markdown_in_bulk() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("Shopping");
+    let client = client(&server);
+    let outline = parse_markdown(MARKDOWN, &DueDateResolver::local()).unwrap();
+
+    let report = client
+        .import_outline(
+            &outline,
+            &CheckvistLocation {
+                list_id,
+                parent_task_id: None,
+            },
+        )
+        .unwrap();
+
+    assert!(report.bulk);
+    assert_eq!(report.task_ids.len(), 4);
+    let bread = client.get_task(list_id, report.task_ids[1]).unwrap();
+    assert_eq!(bread[0].status, TaskStatus::Closed);
+    assert_eq!(
+        client.export_list(list_id, ExportFormat::Markdown).unwrap(),
+        MARKDOWN
+    );
+}
+
+#[test]
+fn imports_opml_beneath_a_task_after_its_children() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("Inbox");
+    let parent = server.add_task(list_id, "Projects", None);
+    server.add_task(list_id, "Existing", Some(parent));
+    let opml = r#"<opml version="2.0"><head><title>Work</title></head><body>
+<outline text="Write report" _note="by Friday"><outline text="Draft"/></outline>
+</body></opml>"#;
+
+    let report = client(&server)
+        .import_outline(
+            &parse_opml(opml, &DueDateResolver::local()).unwrap(),
+            &CheckvistLocation {
+                list_id,
+                parent_task_id: Some(parent),
+            },
+        )
+        .unwrap();
+
+    let report_task = server.task(report.task_ids[0]).unwrap();
+    assert_eq!(report_task.parent_id, parent);
+    assert_eq!(report_task.position, 2);
+    assert_eq!(report_task.notes.len(), 1);
+    assert_eq!(
+        server.task(report.task_ids[1]).unwrap().parent_id,
+        report.task_ids[0]
+    );
+}
+
+#[test]
+fn imports_multiline_items_one_by_one() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("Inbox");
+    server.add_task(list_id, "Existing", None);
+    let mut outline =
+        parse_markdown("- first\n  - child\n- last", &DueDateResolver::local()).unwrap();
+    outline.items[0].content.push_str("\nsecond line");
+
+    let report = client(&server)
+        .import_outline(
+            &outline,
+            &CheckvistLocation {
+                list_id,
+                parent_task_id: None,
+            },
+        )
+        .unwrap();
+
+    assert!(!report.bulk);
+    let first = server.task(report.task_ids[0]).unwrap();
+    assert_eq!(first.content, "first\nsecond line");
+    assert_eq!(first.position, 2);
+    assert_eq!(server.task(report.task_ids[1]).unwrap().parent_id, first.id);
+    assert_eq!(server.task(report.task_ids[2]).unwrap().position, 3);
+}
+
+#[test]
+fn imports_text_like_smart_syntax_as_written() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("Inbox");
+    let outline = parse_indented("Fix #42\n  ask ^mum", false, &DueDateResolver::local());
+
+    let report = client(&server)
+        .import_outline(
+            &outline,
+            &CheckvistLocation {
+                list_id,
+                parent_task_id: None,
+            },
+        )
+        .unwrap();
+
+    assert!(!report.bulk);
+    let fix = server.task(report.task_ids[0]).unwrap();
+    assert_eq!(fix.content, "Fix #42");
+    assert!(fix.tags.is_empty());
+    assert_eq!(server.task(report.task_ids[1]).unwrap().content, "ask ^mum");
+}
+
+#[test]
+fn failed_import_reports_whether_tasks_may_have_been_added() {
+    let server = FakeCheckvist::start();
+    let list_id = server.add_list("Shopping");
+    server.forbid_list(list_id);
+    let outline = parse_markdown(MARKDOWN, &DueDateResolver::local()).unwrap();
+    let location = CheckvistLocation {
+        list_id,
+        parent_task_id: None,
+    };
+    let unreachable = CheckvistClient::new("http://127.0.0.1:1", "token", Box::new(|_token| ()));
+
+    let refused = client(&server)
+        .import_outline(&outline, &location)
+        .unwrap_err();
+    let unsent = unreachable.import_outline(&outline, &location).unwrap_err();
+
+    assert!(matches!(refused.error, CheckvistError::InvalidListError));
+    assert!(!refused.may_have_added);
+    assert!(matches!(unsent.error, CheckvistError::NetworkError(_)));
+    assert!(!unsent.may_have_added);
+    assert!(unsent.task_ids.is_empty());
+}
@@ -1,16 +1,18 @@
 #[allow(unused)]
 use std::collections::HashMap;
 
+use chrono::NaiveDate;
 use serde_json::json;
 use wiremock::matchers::{body_partial_json, header, method, path, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
-use cvapi::{Checklist, CheckvistClient, CheckvistError, Task};
+use cvapi::due_date::DueDateResolver;
+use cvapi::{Checklist, CheckvistClient, CheckvistError, Tags, Task, User};
 
 #[test]
 #[should_panic]
 fn client_creation_should_panic_with_invalid_url() {
-    let _client = CheckvistClient::new("".into(), "token".into(), Box::new(|_token| ()));
+    let _client = CheckvistClient::new("", "token", Box::new(|_token| ()));
 }
 
 #[tokio::test]
@@ -33,7 +35,7 @@ async fn get_auth_token() {
         .await;
 
     let returned_token =
-        CheckvistClient::get_token(&mock_server.uri(), username.into(), remote_key.into()).unwrap();
+        CheckvistClient::get_token(&mock_server.uri(), username, remote_key).unwrap();
 
     assert_eq!(token, returned_token);
 }
@@ -48,6 +50,7 @@ async fn authentication_failure_results_in_token_refresh_attempt_then_redo() {
         name: list_name.into(),
         updated_at: "a date".to_string(),
         task_count: 1,
+        ..Default::default()
     };
     let mock_server = MockServer::start().await;
     Mock::given(method("GET"))
@@ -73,7 +76,7 @@ async fn authentication_failure_results_in_token_refresh_attempt_then_redo() {
 
     let client = CheckvistClient::new(
         &mock_server.uri(),
-        old_token.into(),
+        old_token,
         Box::new(|token| {
             assert_eq!(
                 token, "token",
@@ -124,28 +127,68 @@ async fn http404_response_returns_error() {
         .respond_with(ResponseTemplate::new(404))
         .mount(&mock_server)
         .await;
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
 
     let _returned_error = client.get_list(1).unwrap_err();
 }
 
+#[tokio::test]
+async fn missing_task_404_returns_invalid_task_error() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks/2.json"))
+        .respond_with(
+            ResponseTemplate::new(404).set_body_json(json!({"message": "Task not found"})),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks/3.json"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({"message": "Not found"})))
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    assert!(matches!(
+        client.get_task(1, 2),
+        Err(CheckvistError::InvalidTaskError)
+    ));
+    assert!(matches!(
+        client.get_task(1, 3),
+        Err(CheckvistError::UnknownError { .. })
+    ));
+}
+
 #[test]
 fn network_error_results_in_ureq_transport_error() {
-    let client = CheckvistClient::new(
-        "http://localhost".into(),
-        "token".into(),
-        Box::new(|_token| ()),
-    );
+    let client = CheckvistClient::new("http://localhost", "token", Box::new(|_token| ()));
     let returned_error = client.get_tasks(1).unwrap_err();
 
     match returned_error {
-        CheckvistError::NetworkError(ureq::Error::Transport(transport)) => {
-            assert_eq!(transport.kind(), ureq::ErrorKind::ConnectionFailed)
-        }
+        CheckvistError::NetworkError(err) => match *err {
+            ureq::Error::Transport(transport) => {
+                assert_eq!(transport.kind(), ureq::ErrorKind::ConnectionFailed)
+            }
+            err => panic!("Wrong error type: {:?}", err),
+        },
         _ => panic!("Wrong error type: {:?}", returned_error),
     }
 }
 
+#[test]
+fn only_errors_after_connecting_may_have_succeeded() {
+    let client = CheckvistClient::new("http://localhost", "token", Box::new(|_token| ()));
+    let refused = client.get_tasks(1).unwrap_err();
+    let dropped = CheckvistError::NetworkError(Box::new(ureq::Error::from(std::io::Error::new(
+        std::io::ErrorKind::ConnectionReset,
+        "connection reset",
+    ))));
+
+    assert!(!refused.may_have_succeeded());
+    assert!(dropped.may_have_succeeded());
+    assert!(!CheckvistError::InvalidListError.may_have_succeeded());
+}
+
 #[tokio::test]
 async fn get_json_decoding_error_from_server_gibberish() {
     let mock_server = MockServer::start().await;
@@ -155,7 +198,7 @@ async fn get_json_decoding_error_from_server_gibberish() {
         .expect(1)
         .mount(&mock_server)
         .await;
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
 
     let returned_error = client.get_list(1).unwrap_err();
 
@@ -172,6 +215,7 @@ async fn get_list() {
         name: "list1".to_string(),
         updated_at: "a date".to_string(),
         task_count: 1,
+        ..Default::default()
     };
     let mock_server = MockServer::start().await;
     Mock::given(method("GET"))
@@ -181,7 +225,7 @@ async fn get_list() {
         .mount(&mock_server)
         .await;
 
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
     let result = client.get_list(1).unwrap();
 
     assert_eq!(expected, result);
@@ -195,6 +239,7 @@ async fn add_list() {
         name: new_list.into(),
         updated_at: "a date".to_string(),
         task_count: 0,
+        ..Default::default()
     };
 
     let request_body = HashMap::from([("name", new_list)]);
@@ -206,13 +251,37 @@ async fn add_list() {
         .respond_with(ResponseTemplate::new(200).set_body_json(json!(expected)))
         .mount(&mock_server)
         .await;
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
 
     let result = client.add_list(new_list).unwrap();
 
     assert_eq!(result, expected);
 }
 
+#[tokio::test]
+async fn get_archived_lists() {
+    let archived = vec![Checklist {
+        id: 2,
+        name: "old list".into(),
+        updated_at: "a date".to_string(),
+        task_count: 3,
+        ..Default::default()
+    }];
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists.json"))
+        .and(query_param("archived", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(archived)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let result = client.get_archived_lists().unwrap();
+
+    assert_eq!(result, archived);
+}
+
 #[tokio::test]
 // /checklists/checklist_id/tasks/task_id.(json|xml)
 async fn get_task() {
@@ -221,6 +290,7 @@ async fn get_task() {
         position: 1,
         content: "content".to_string(),
         parent_id: None,
+        ..Default::default()
     }];
     let mock_server = MockServer::start().await;
     Mock::given(method("GET"))
@@ -230,7 +300,7 @@ async fn get_task() {
         .mount(&mock_server)
         .await;
 
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
     let returned_tasks = client.get_task(1, 1).unwrap();
 
     assert_eq!(tasks, returned_tasks);
@@ -243,6 +313,7 @@ async fn get_tasks() {
         position: 1,
         content: "content".to_string(),
         parent_id: None,
+        ..Default::default()
     }];
     let mock_server = MockServer::start().await;
     Mock::given(method("GET"))
@@ -252,12 +323,192 @@ async fn get_tasks() {
         .mount(&mock_server)
         .await;
 
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
     let returned_tasks = client.get_tasks(1).unwrap();
 
     assert_eq!(tasks, returned_tasks);
 }
 
+#[tokio::test]
+async fn tasks_decode_checkvist_due_date_and_tags() {
+    let response = json!([{
+        "id": 1,
+        "content": "content",
+        "position": 1,
+        "parent_id": 0,
+        "due": "2023/05/01",
+        "tags": {"tag1": false, "tag2": true},
+        "tags_as_text": "tag1, tag2"
+    }]);
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response))
+        .mount(&mock_server)
+        .await;
+
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    let task = client.get_tasks(1).unwrap().remove(0);
+
+    assert_eq!(task.due, NaiveDate::from_ymd_opt(2023, 5, 1));
+    assert_eq!(
+        task.tags,
+        Tags::from([("tag1".into(), false), ("tag2".into(), true)])
+    );
+}
+
+#[tokio::test]
+async fn tasks_with_unrecognised_due_dates_keep_them() {
+    let response = json!([
+        {"id": 1, "content": "one", "position": 1, "parent_id": 0, "due": "2023/05/01"},
+        {"id": 2, "content": "two", "position": 2, "parent_id": 0, "due": "sometime soon"}
+    ]);
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response))
+        .mount(&mock_server)
+        .await;
+
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    let tasks = client.get_tasks(1).unwrap();
+
+    assert_eq!(tasks[0].due, NaiveDate::from_ymd_opt(2023, 5, 1));
+    assert_eq!(tasks[1].due, None);
+    assert_eq!(tasks[1].extra["due"], json!("sometime soon"));
+    assert_eq!(json!(tasks[1])["due"], json!("sometime soon"));
+}
+
+#[tokio::test]
+async fn add_task_sends_due_date_and_tags() {
+    let task = Task {
+        id: None,
+        position: 1,
+        content: "some text".into(),
+        parent_id: None,
+        due: NaiveDate::from_ymd_opt(2023, 5, 11),
+        tags: Tags::from([("tag1".into(), false), ("tag2".into(), true)]),
+        ..Default::default()
+    };
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/checklists/1/tasks.json"))
+        .and(body_partial_json(
+            json!({"due_date": "2023/05/11", "tags": {"tag1": false, "tag2": true}}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(task)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let returned_task = client.add_task(1, &task).unwrap();
+
+    assert_eq!(task, returned_task);
+}
+
+#[tokio::test]
+async fn updating_a_fetched_task_keeps_unknown_fields() {
+    let fetched = json!({
+        "id": 1,
+        "content": "content",
+        "position": 1,
+        "parent_id": 0,
+        "color": {"background": "red"},
+        "collapsed": true
+    });
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks/1.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([fetched])))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/checklists/1/tasks/1.json"))
+        .and(body_partial_json(json!({
+            "content": "changed",
+            "color": {"background": "red"},
+            "collapsed": true
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&fetched))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let mut task = client.get_task(1, 1).unwrap().remove(0);
+    assert_eq!(task.extra["collapsed"], json!(true));
+    task.content = "changed".into();
+    client.update_task(1, &task).unwrap();
+}
+
+#[tokio::test]
+async fn updating_a_fetched_task_keeps_private_tags() {
+    // as returned by Checkvist
+    let fetched = json!({
+        "id": 61203478,
+        "parent_id": 0,
+        "checklist_id": 774394,
+        "status": 0,
+        "position": 2,
+        "tasks": [],
+        "update_line": "created by cvcap",
+        "updated_at": "2023/06/02 09:14:51 +0000",
+        "created_at": "2023/06/02 09:14:51 +0000",
+        "due": "2023/06/09",
+        "content": "renew passport",
+        "collapsed": false,
+        "comments_count": 0,
+        "assignee_ids": [],
+        "details": {},
+        "link_ids": [],
+        "backlink_ids": [],
+        "tags": {"admin": false, "family": true},
+        "tags_as_text": "admin, family"
+    });
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/774394/tasks/61203478.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([fetched])))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/checklists/774394/tasks/61203478.json"))
+        .and(body_partial_json(json!({
+            "content": "renew passports",
+            "tags": {"admin": false, "family": true},
+            "due_date": "2023/06/09"
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&fetched))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let mut task = client.get_task(774394, 61203478).unwrap().remove(0);
+    let resent: Task = serde_json::from_value(json!(task)).unwrap();
+    assert_eq!(resent, task);
+    task.content = "renew passports".into();
+    client.update_task(774394, &task).unwrap();
+}
+
+#[test]
+fn lists_keep_unknown_fields() {
+    let json = json!({
+        "id": 1,
+        "name": "list1",
+        "updated_at": "a date",
+        "task_count": 1,
+        "public": false,
+        "tags": {"work": false}
+    });
+
+    let list: Checklist = serde_json::from_value(json.clone()).unwrap();
+
+    assert_eq!(list.extra.len(), 2);
+    assert_eq!(serde_json::to_value(&list).unwrap(), json);
+}
+
 #[tokio::test]
 async fn check_locations() {
     let list = Checklist {
@@ -265,6 +516,7 @@ async fn check_locations() {
         name: "list1".to_string(),
         updated_at: "a date".to_string(),
         task_count: 1,
+        ..Default::default()
     };
     let mock_server = MockServer::start().await;
     Mock::given(method("GET"))
@@ -281,7 +533,7 @@ async fn check_locations() {
         .mount(&mock_server)
         .await;
 
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
     let present_location = cvapi::CheckvistLocation {
         list_id: 1,
         parent_task_id: None,
@@ -293,8 +545,8 @@ async fn check_locations() {
     let present_result = client.is_location_valid(&present_location).unwrap();
     let missing_result = client.is_location_valid(&missing_location).unwrap();
 
-    assert_eq!(true, present_result);
-    assert_eq!(false, missing_result);
+    assert!(present_result);
+    assert!(!missing_result);
 }
 
 #[tokio::test]
@@ -304,6 +556,7 @@ async fn add_task_to_list() {
         position: 1,
         content: "some text".into(),
         parent_id: None,
+        ..Default::default()
     };
     let mock_server = MockServer::start().await;
     Mock::given(method("POST"))
@@ -313,7 +566,7 @@ async fn add_task_to_list() {
         .expect(1)
         .mount(&mock_server)
         .await;
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
 
     let returned_task = client.add_task(1, &task).unwrap();
 
@@ -327,6 +580,7 @@ async fn add_task_to_invalid_list_error() {
         position: 1,
         content: "some text".into(),
         parent_id: Some(2),
+        ..Default::default()
     };
     let mock_server = MockServer::start().await;
     Mock::given(method("POST"))
@@ -342,7 +596,7 @@ async fn add_task_to_invalid_list_error() {
         .mount(&mock_server)
         .await;
 
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
 
     let returned_task = client.add_task(1, &task).unwrap_err();
     assert!(matches!(returned_task, CheckvistError::InvalidListError));
@@ -355,6 +609,7 @@ async fn add_task_to_invalid_parent_task_error() {
         position: 1,
         content: "some text".into(),
         parent_id: Some(2),
+        ..Default::default()
     };
     let mock_server = MockServer::start().await;
     Mock::given(method("POST"))
@@ -368,7 +623,7 @@ async fn add_task_to_invalid_parent_task_error() {
         .mount(&mock_server)
         .await;
 
-    let client = CheckvistClient::new(&mock_server.uri(), "token".into(), Box::new(|_token| ()));
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
 
     let returned_task = client.add_task(1, &task).unwrap_err();
 
@@ -377,3 +632,203 @@ async fn add_task_to_invalid_parent_task_error() {
         CheckvistError::InvalidParentIdError
     ));
 }
+
+#[tokio::test]
+async fn set_due_date_resolves_expression() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/checklists/1/tasks/2.json"))
+        .and(body_partial_json(json!({"due_date": "2023/05/12"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": 2,
+            "content": "content",
+            "position": 1,
+            "parent_id": null,
+            "due": "2023/05/12"
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    // a Wednesday
+    let resolver = DueDateResolver::for_date(NaiveDate::from_ymd_opt(2023, 5, 10).unwrap());
+
+    let task = client.set_due_date(1, 2, "friday", &resolver).unwrap();
+
+    assert_eq!(task.due, NaiveDate::from_ymd_opt(2023, 5, 12));
+}
+
+#[test]
+fn set_due_date_rejects_unknown_expression() {
+    let client = CheckvistClient::new("http://localhost", "token", Box::new(|_token| ()));
+    let resolver = DueDateResolver::for_date(NaiveDate::from_ymd_opt(2023, 5, 10).unwrap());
+
+    let error = client
+        .set_due_date(1, 2, "when pigs fly", &resolver)
+        .unwrap_err();
+
+    assert!(matches!(error, CheckvistError::InvalidDueDateError { .. }));
+}
+
+#[tokio::test]
+async fn get_collaborators() {
+    let users = vec![
+        User {
+            id: 1,
+            email: "owner@test.com".into(),
+            username: None,
+            ..Default::default()
+        },
+        User {
+            id: 2,
+            email: "friend@test.com".into(),
+            username: Some("friend".into()),
+            ..Default::default()
+        },
+    ];
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/collaborators.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(users)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let returned_users = client.get_collaborators(1).unwrap();
+
+    assert_eq!(users, returned_users);
+}
+
+#[tokio::test]
+async fn share_list_with_unknown_user_errors() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/checklists/1/collaborators.json"))
+        .and(body_partial_json(json!({"email": "nobody@test.com"})))
+        .respond_with(
+            ResponseTemplate::new(404).set_body_json(json!({"message": "User not found"})),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let error = client.share_list(1, "nobody@test.com").unwrap_err();
+
+    assert!(matches!(error, CheckvistError::UnknownUserError { .. }));
+}
+
+#[tokio::test]
+async fn share_list_without_permission_errors() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/checklists/1/collaborators.json"))
+        .respond_with(
+            ResponseTemplate::new(403)
+                .set_body_json(json!({"message": "Only the owner can share this list"})),
+        )
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let error = client.share_list(1, "friend@test.com").unwrap_err();
+
+    assert!(matches!(error, CheckvistError::PermissionDeniedError { .. }));
+}
+
+#[tokio::test]
+async fn refusals_without_a_message_are_still_permission_errors_when_sharing() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/checklists/1/collaborators.json"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("Forbidden"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks/2.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "id": 2, "content": "content", "position": 1, "assignee_ids": []
+        }])))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/checklists/1/tasks/2.json"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("Forbidden"))
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let share_error = client.share_list(1, "friend@test.com").unwrap_err();
+    let assign_error = client.assign_task(1, 2, 8).unwrap_err();
+
+    assert!(matches!(
+        share_error,
+        CheckvistError::PermissionDeniedError { .. }
+    ));
+    assert!(matches!(
+        assign_error,
+        CheckvistError::PermissionDeniedError { .. }
+    ));
+}
+
+#[tokio::test]
+async fn other_refusals_are_unknown_errors() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1.json"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("Forbidden"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/2.json"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({"message": "Not allowed"})))
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    assert!(matches!(
+        client.get_list(1),
+        Err(CheckvistError::UnknownError { .. })
+    ));
+    assert!(matches!(
+        client.get_list(2),
+        Err(CheckvistError::UnknownError { .. })
+    ));
+}
+
+#[tokio::test]
+async fn assign_and_unassign_task_keep_other_assignees() {
+    let task = Task {
+        id: Some(2),
+        position: 1,
+        content: "content".into(),
+        parent_id: None,
+        assignee_ids: vec![7],
+        ..Default::default()
+    };
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks/2.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(vec![&task])))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/checklists/1/tasks/2.json"))
+        .and(body_partial_json(json!({"assignee_ids": [7, 8]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(&task)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/checklists/1/tasks/2.json"))
+        .and(body_partial_json(json!({"assignee_ids": []})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(&task)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    client.assign_task(1, 2, 8).unwrap();
+    client.unassign_task(1, 2, 7).unwrap();
+}
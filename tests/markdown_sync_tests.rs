@@ -0,0 +1,290 @@
+use std::fs;
+
+use cvapi::due_date::DueDateResolver;
+use cvapi::markdown_sync::{MarkdownSync, SyncConflict};
+use cvapi::{CheckvistClient, Tags, Task};
+use cvfake::FakeCheckvist;
+use temp_dir::TempDir;
+
+fn client(server: &FakeCheckvist) -> CheckvistClient {
+    CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()))
+}
+
+/// Shopping
+///   Food
+///     Bread
+///   Soap
+fn shopping_list(server: &FakeCheckvist) -> (u32, u32, u32, u32) {
+    let list_id = server.add_list("Shopping");
+    let food = server.add_task(list_id, "Food", None);
+    let bread = server.add_task(list_id, "Bread", Some(food));
+    let soap = server.add_task(list_id, "Soap", None);
+    (list_id, food, bread, soap)
+}
+
+fn contents(server: &FakeCheckvist, list_id: u32) -> Vec<(String, u32, u8)> {
+    let mut tasks = server.tasks(list_id);
+    tasks.sort_by_key(|task| (task.parent_id, task.position));
+    tasks
+        .into_iter()
+        .map(|task| (task.content, task.parent_id, task.status))
+        .collect()
+}
+
+#[test]
+fn first_sync_writes_the_file_and_later_ones_change_nothing() {
+    let server = FakeCheckvist::start();
+    let (list_id, food, bread, soap) = shopping_list(&server);
+    let temp_dir = TempDir::new().unwrap();
+    let sync = MarkdownSync::new(list_id, &temp_dir.child("shopping.md"));
+    let client = client(&server);
+
+    let report = sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    assert_eq!(report.to_file.created, vec![food, bread, soap]);
+    assert_eq!(
+        fs::read_to_string(temp_dir.child("shopping.md")).unwrap(),
+        format!(
+            "# Shopping\n\n\
+             - [ ] Food <!-- cv:{} -->\n\
+             \x20 - [ ] Bread <!-- cv:{} -->\n\
+             - [ ] Soap <!-- cv:{} -->\n",
+            food, bread, soap
+        )
+    );
+    assert!(sync.state_path().exists());
+
+    let report = sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    assert!(report.to_checkvist.is_empty());
+    assert!(report.to_file.is_empty());
+    assert!(report.conflicts.is_empty());
+}
+
+#[test]
+fn file_changes_go_to_checkvist() {
+    let server = FakeCheckvist::start();
+    let (list_id, food, bread, soap) = shopping_list(&server);
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.child("shopping.md");
+    let sync = MarkdownSync::new(list_id, &path);
+    let client = client(&server);
+    sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    // rename and close Bread, move Soap beneath a new item, and delete Food
+    fs::write(
+        &path,
+        format!(
+            "# Shopping\n\n\
+             - [ ] Cleaning #home\n\
+             \x20 - [ ] Soap <!-- cv:{} -->\n\
+             \x20 - [x] Bleach\n\
+             - [x] Fresh bread <!-- cv:{} -->\n",
+            soap, bread
+        ),
+    )
+    .unwrap();
+
+    let report = sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.to_checkvist.created.len(), 2);
+    assert_eq!(report.to_checkvist.updated, vec![bread]);
+    assert_eq!(report.to_checkvist.status_changed, vec![bread]);
+    assert!(report.to_checkvist.moved.contains(&soap));
+    assert_eq!(report.to_checkvist.deleted, vec![food]);
+    let cleaning = report.to_checkvist.created[0];
+    assert_eq!(
+        contents(&server, list_id),
+        vec![
+            ("Cleaning".into(), 0, 0),
+            ("Fresh bread".into(), 0, 1),
+            ("Soap".into(), cleaning, 0),
+            ("Bleach".into(), cleaning, 1),
+        ]
+    );
+    assert_eq!(server.task(cleaning).unwrap().tags_as_text, "home");
+    assert!(fs::read_to_string(&path)
+        .unwrap()
+        .contains(&format!("- [ ] Cleaning #home <!-- cv:{} -->", cleaning)));
+    assert!(sync
+        .sync(&client, &DueDateResolver::local())
+        .unwrap()
+        .to_checkvist
+        .is_empty());
+}
+
+#[test]
+fn tags_edited_in_the_file_stay_private() {
+    let server = FakeCheckvist::start();
+    let (list_id, _, _, soap) = shopping_list(&server);
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.child("shopping.md");
+    let sync = MarkdownSync::new(list_id, &path);
+    let client = client(&server);
+    let mut task = client.get_task(list_id, soap).unwrap().remove(0);
+    task.tags = Tags::from([("home".into(), true)]);
+    client.update_task(list_id, &task).unwrap();
+    sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    let file = fs::read_to_string(&path).unwrap();
+    fs::write(&path, file.replace("Soap #home", "Soap #home #urgent")).unwrap();
+    let report = sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    assert_eq!(report.to_checkvist.updated, vec![soap]);
+    assert_eq!(
+        server.task(soap).unwrap().tags,
+        Tags::from([("home".into(), true), ("urgent".into(), false)])
+    );
+}
+
+#[test]
+fn tasks_added_before_a_failed_sync_are_not_added_again() {
+    let server = FakeCheckvist::start();
+    let (list_id, food, bread, soap) = shopping_list(&server);
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.child("shopping.md");
+    let sync = MarkdownSync::new(list_id, &path);
+    let client = client(&server);
+    sync.sync(&client, &DueDateResolver::local()).unwrap();
+    fs::write(
+        &path,
+        format!(
+            "# Shopping\n\n\
+             - [ ] Food <!-- cv:{} -->\n\
+             \x20 - [ ] Bread <!-- cv:{} -->\n\
+             - [ ] Soap <!-- cv:{} -->\n\
+             - [ ] Milk\n\
+             - [ ] Eggs\n",
+            food, bread, soap
+        ),
+    )
+    .unwrap();
+
+    server.refuse_changes_after(Some(1));
+    assert!(sync.sync(&client, &DueDateResolver::local()).is_err());
+    server.refuse_changes_after(None);
+    let report = sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    assert_eq!(report.to_checkvist.created.len(), 1);
+    let added: Vec<String> = contents(&server, list_id)
+        .into_iter()
+        .map(|(content, _parent_id, _status)| content)
+        .collect();
+    assert_eq!(added, vec!["Food", "Soap", "Milk", "Eggs", "Bread"]);
+}
+
+#[test]
+fn checkvist_changes_go_to_the_file() {
+    let server = FakeCheckvist::start();
+    let (list_id, food, bread, soap) = shopping_list(&server);
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.child("shopping.md");
+    let sync = MarkdownSync::new(list_id, &path);
+    let client = client(&server);
+    sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    let milk = server.add_task(list_id, "Milk", Some(food));
+    client.close_task(list_id, bread).unwrap();
+    client.delete_task(list_id, soap).unwrap();
+    client
+        .update_task(
+            list_id,
+            &Task {
+                id: Some(food),
+                content: "Groceries".into(),
+                position: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let report = sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    assert!(report.to_checkvist.is_empty());
+    assert_eq!(report.to_file.created, vec![milk]);
+    assert_eq!(report.to_file.updated, vec![food]);
+    assert_eq!(report.to_file.status_changed, vec![bread]);
+    assert_eq!(report.to_file.deleted, vec![soap]);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format!(
+            "# Shopping\n\n\
+             - [ ] Groceries <!-- cv:{} -->\n\
+             \x20 - [x] Bread <!-- cv:{} -->\n\
+             \x20 - [ ] Milk <!-- cv:{} -->\n",
+            food, bread, milk
+        )
+    );
+}
+
+#[test]
+fn conflicts_are_reported() {
+    let server = FakeCheckvist::start();
+    let (list_id, food, bread, soap) = shopping_list(&server);
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.child("shopping.md");
+    let sync = MarkdownSync::new(list_id, &path);
+    let client = client(&server);
+    sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    // Food is renamed in both, Bread deleted from the file but renamed in
+    // Checkvist, and Soap renamed in the file but deleted from Checkvist
+    fs::write(
+        &path,
+        format!(
+            "# Shopping\n\n- [ ] Food here <!-- cv:{} -->\n- [ ] Soap here <!-- cv:{} -->\n",
+            food, soap
+        ),
+    )
+    .unwrap();
+    for (id, content, parent_id) in [
+        (food, "Food there", None),
+        (bread, "Bread there", Some(food)),
+    ] {
+        client
+            .update_task(
+                list_id,
+                &Task {
+                    id: Some(id),
+                    content: content.into(),
+                    position: 1,
+                    parent_id,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+    }
+    client.delete_task(list_id, soap).unwrap();
+
+    let report = sync.sync(&client, &DueDateResolver::local()).unwrap();
+
+    let new_soap = report.to_checkvist.created[0];
+    assert_eq!(
+        report.conflicts,
+        vec![
+            SyncConflict::BothChanged {
+                task_id: food,
+                local: "Food here".into(),
+                remote: "Food there".into()
+            },
+            SyncConflict::DeletedLocally {
+                task_id: bread,
+                remote: "Bread there".into()
+            },
+            SyncConflict::DeletedRemotely {
+                task_id: soap,
+                local: "Soap here".into(),
+                new_task_id: new_soap
+            },
+        ]
+    );
+    assert_eq!(
+        contents(&server, list_id),
+        vec![
+            ("Food there".into(), 0, 0),
+            ("Soap here".into(), 0, 0),
+            ("Bread there".into(), food, 0),
+        ]
+    );
+}
@@ -0,0 +1,199 @@
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use cvapi::mirror::Mirror;
+use cvapi::{Checklist, CheckvistClient, Task};
+
+#[tokio::test]
+async fn first_sync_adds_all_tasks() {
+    let mock_server = MockServer::start().await;
+    mount_list(
+        &mock_server,
+        list("a date"),
+        vec![task(1, "one"), task(2, "two")],
+        1,
+    )
+    .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    let mut mirror = Mirror::open_in_memory().unwrap();
+
+    let report = mirror.sync(&client, &[1]).unwrap();
+
+    assert_eq!(report.lists.len(), 1);
+    assert!(report.lists[0].fetched);
+    assert_eq!(report.lists[0].added_tasks.len(), 2);
+    assert_eq!(mirror.lists().unwrap(), vec![list("a date")]);
+    assert_eq!(mirror.tasks(1).unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn unchanged_list_is_not_refetched() {
+    let mock_server = MockServer::start().await;
+    // tasks are expected to be fetched once only
+    mount_list(&mock_server, list("a date"), vec![task(1, "one")], 1).await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    let mut mirror = Mirror::open_in_memory().unwrap();
+
+    mirror.sync(&client, &[1]).unwrap();
+    let report = mirror.sync(&client, &[1]).unwrap();
+
+    assert!(!report.lists[0].fetched);
+    assert!(report.is_empty());
+}
+
+#[tokio::test]
+async fn updated_list_reports_task_changes() {
+    let mock_server = MockServer::start().await;
+    mount_list(
+        &mock_server,
+        list("a date"),
+        vec![task(1, "one"), task(2, "two")],
+        1,
+    )
+    .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    let mut mirror = Mirror::open_in_memory().unwrap();
+    mirror.sync(&client, &[1]).unwrap();
+
+    mock_server.reset().await;
+    mount_list(
+        &mock_server,
+        list("a later date"),
+        vec![task(1, "one changed"), task(3, "three")],
+        1,
+    )
+    .await;
+    let report = mirror.sync(&client, &[1]).unwrap();
+
+    let list_report = &report.lists[0];
+    assert!(list_report.fetched);
+    assert_eq!(list_report.added_tasks, vec![task(3, "three")]);
+    assert_eq!(list_report.changed_tasks[0].before, task(1, "one"));
+    assert_eq!(list_report.changed_tasks[0].after, task(1, "one changed"));
+    assert_eq!(list_report.removed_tasks, vec![task(2, "two")]);
+    assert_eq!(
+        mirror.task(1, 1).unwrap().unwrap().content,
+        "one changed".to_string()
+    );
+}
+
+#[tokio::test]
+async fn list_missing_from_checkvist_is_removed_from_mirror() {
+    let mock_server = MockServer::start().await;
+    mount_list(&mock_server, list("a date"), vec![task(1, "one")], 1).await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    let mut mirror = Mirror::open_in_memory().unwrap();
+    mirror.sync(&client, &[1]).unwrap();
+
+    mock_server.reset().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(Vec::<Checklist>::new())))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1.json"))
+        .respond_with(
+            ResponseTemplate::new(403).set_body_json(
+                json!({"message": "The list doesn't exist or is not available to you"}),
+            ),
+        )
+        .mount(&mock_server)
+        .await;
+    let report = mirror.sync(&client, &[1]).unwrap();
+
+    assert!(report.lists[0].removed);
+    assert_eq!(report.lists[0].removed_tasks, vec![task(1, "one")]);
+    assert!(mirror.lists().unwrap().is_empty());
+    assert!(mirror.tasks(1).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn archived_list_stays_in_mirror() {
+    let mock_server = MockServer::start().await;
+    mount_list(&mock_server, list("a date"), vec![task(1, "one")], 1).await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    let mut mirror = Mirror::open_in_memory().unwrap();
+    mirror.sync(&client, &[1]).unwrap();
+
+    // archived lists are left out of the lists Checkvist returns, but can still be fetched
+    mock_server.reset().await;
+    Mock::given(method("GET"))
+        .and(path("/checklists.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(Vec::<Checklist>::new())))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(list("a date"))))
+        .mount(&mock_server)
+        .await;
+    let report = mirror.sync(&client, &[1]).unwrap();
+    let next_report = mirror.sync(&client, &[1]).unwrap();
+
+    assert!(!report.lists[0].removed);
+    assert!(report.is_empty());
+    assert!(next_report.is_empty());
+    assert_eq!(mirror.tasks(1).unwrap(), vec![task(1, "one")]);
+}
+
+#[tokio::test]
+async fn query_mirrored_tasks() {
+    let mock_server = MockServer::start().await;
+    let mut child = task(3, "child of one");
+    child.parent_id = Some(1);
+    mount_list(
+        &mock_server,
+        list("a date"),
+        vec![task(1, "one"), task(2, "Two"), child],
+        1,
+    )
+    .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+    let mut mirror = Mirror::open_in_memory().unwrap();
+    mirror.sync(&client, &[1]).unwrap();
+
+    let top_level = mirror.children(1, None).unwrap();
+    let children = mirror.children(1, Some(1)).unwrap();
+    let found = mirror.find_tasks("ONE").unwrap();
+
+    assert_eq!(top_level.len(), 2);
+    assert_eq!(children[0].id, Some(3));
+    assert_eq!(found.len(), 2);
+    assert!(found.iter().all(|(list_id, _task)| *list_id == 1));
+}
+
+async fn mount_list(mock_server: &MockServer, list: Checklist, tasks: Vec<Task>, times: u64) {
+    Mock::given(method("GET"))
+        .and(path("/checklists.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(vec![list])))
+        .mount(mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(tasks)))
+        .expect(times)
+        .mount(mock_server)
+        .await;
+}
+
+fn list(updated_at: &str) -> Checklist {
+    Checklist {
+        id: 1,
+        name: "list1".into(),
+        updated_at: updated_at.into(),
+        task_count: 1,
+        ..Default::default()
+    }
+}
+
+fn task(id: u32, content: &str) -> Task {
+    Task {
+        id: Some(id),
+        content: content.into(),
+        position: id as u16,
+        parent_id: None,
+        ..Default::default()
+    }
+}
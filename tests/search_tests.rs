@@ -0,0 +1,79 @@
+use cvapi::search::SearchQuery;
+use cvapi::{CheckvistClient, CheckvistError};
+use cvfake::FakeCheckvist;
+
+fn client(server: &FakeCheckvist) -> CheckvistClient {
+    CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()))
+}
+
+/// Work
+///   Inbox
+///     Fix bug
+///   Fix the build
+/// Home
+///   Fix the door
+fn populated_server() -> (FakeCheckvist, u32, u32) {
+    let server = FakeCheckvist::start();
+    let work = server.add_list("Work");
+    let inbox = server.add_task(work, "Inbox", None);
+    server.add_task(work, "Fix bug", Some(inbox));
+    server.add_task(work, "Fix the build", None);
+    let home = server.add_list("Home");
+    server.add_task(home, "Fix the door", None);
+    (server, work, home)
+}
+
+#[test]
+fn search_finds_matches_across_lists_with_paths_and_permalinks() {
+    let (server, work, home) = populated_server();
+    let client = client(&server);
+
+    let matches = client
+        .search(&SearchQuery::parse("fix").unwrap(), None)
+        .unwrap();
+
+    let found: Vec<(u32, &str, Vec<String>)> = matches
+        .iter()
+        .map(|m| (m.list.id, m.task.content.as_str(), m.path.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (work, "Fix bug", vec!["Inbox".to_string()]),
+            (work, "Fix the build", vec![]),
+            (home, "Fix the door", vec![]),
+        ]
+    );
+    assert_eq!(
+        matches[0].permalink,
+        format!(
+            "{}/checklists/{}/tasks/{}",
+            server.uri(),
+            work,
+            matches[0].task.id.unwrap()
+        )
+    );
+}
+
+#[test]
+fn search_can_be_limited_to_chosen_lists() {
+    let (server, _work, home) = populated_server();
+    let client = client(&server);
+
+    let matches = client
+        .search(&SearchQuery::parse("/^fix the/").unwrap(), Some(&[home]))
+        .unwrap();
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].task.content, "Fix the door");
+}
+
+#[test]
+fn search_of_unknown_list_errors() {
+    let (server, _work, _home) = populated_server();
+    let client = client(&server);
+
+    let result = client.search(&SearchQuery::parse("fix").unwrap(), Some(&[999]));
+
+    assert!(matches!(result, Err(CheckvistError::InvalidListError)));
+}
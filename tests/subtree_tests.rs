@@ -0,0 +1,205 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use cvapi::{CheckvistClient, CheckvistError, CheckvistLocation, Note, Tags, Task, TaskStatus};
+
+/// Responds to task creation with the posted task, given a new id (from 100)
+struct CreateTask {
+    next_id: AtomicU32,
+    fail_after: Option<u32>,
+}
+
+impl Respond for CreateTask {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        if matches!(self.fail_after, Some(count) if id >= 100 + count) {
+            return ResponseTemplate::new(500);
+        }
+        let mut task: Value = serde_json::from_slice(&request.body).unwrap();
+        task["id"] = json!(id);
+        ResponseTemplate::new(200).set_body_json(task)
+    }
+}
+
+fn create_task(fail_after: Option<u32>) -> CreateTask {
+    CreateTask {
+        next_id: AtomicU32::new(100),
+        fail_after,
+    }
+}
+
+/// 10
+///   12 (closed)
+///     13
+///   11 (with note)
+/// 20 (not in subtree)
+fn source_tasks() -> Vec<Task> {
+    vec![
+        Task {
+            id: Some(10),
+            content: "root".into(),
+            position: 1,
+            tags: Tags::from([("tag".into(), true)]),
+            ..Default::default()
+        },
+        Task {
+            id: Some(11),
+            content: "second child".into(),
+            position: 2,
+            parent_id: Some(10),
+            notes: vec![Note {
+                id: Some(1),
+                comment: "a note".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+        Task {
+            id: Some(12),
+            content: "first child".into(),
+            position: 1,
+            parent_id: Some(10),
+            status: TaskStatus::Closed,
+            ..Default::default()
+        },
+        Task {
+            id: Some(13),
+            content: "grandchild".into(),
+            position: 1,
+            parent_id: Some(12),
+            ..Default::default()
+        },
+        Task {
+            id: Some(20),
+            content: "not copied".into(),
+            position: 2,
+            ..Default::default()
+        },
+    ]
+}
+
+async fn mount_source(mock_server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/checklists/1/tasks.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(source_tasks())))
+        .mount(mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path_regex(
+            r"/checklists/2/tasks/\d+/(comments|close)\.json",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": 1, "comment": ""})))
+        .mount(mock_server)
+        .await;
+}
+
+fn destination() -> CheckvistLocation {
+    CheckvistLocation {
+        list_id: 2,
+        parent_task_id: Some(5),
+    }
+}
+
+async fn posted_tasks(mock_server: &MockServer) -> Vec<Value> {
+    mock_server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.url.path() == "/checklists/2/tasks.json")
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn copy_subtree_preserves_structure_order_notes_and_status() {
+    let mock_server = MockServer::start().await;
+    mount_source(&mock_server).await;
+    Mock::given(method("POST"))
+        .and(path("/checklists/2/tasks.json"))
+        .respond_with(create_task(None))
+        .expect(4)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let copy = client.copy_subtree(1, 10, &destination()).unwrap();
+
+    assert_eq!(copy.root_task_id, 100);
+    assert_eq!(
+        copy.id_map,
+        vec![(10, 100), (12, 101), (13, 102), (11, 103)]
+    );
+    let posted = posted_tasks(&mock_server).await;
+    assert_eq!(posted[0]["parent_id"], json!(5));
+    assert_eq!(posted[0]["tags"], json!({"tag": true}));
+    assert_eq!(posted[1]["parent_id"], json!(100));
+    assert_eq!(posted[1]["position"], json!(1));
+    assert_eq!(posted[2]["parent_id"], json!(101));
+    assert_eq!(posted[3]["position"], json!(2));
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let paths: Vec<&str> = requests.iter().map(|r| r.url.path()).collect();
+    assert!(paths.contains(&"/checklists/2/tasks/101/close.json"));
+    assert!(paths.contains(&"/checklists/2/tasks/103/comments.json"));
+}
+
+#[tokio::test]
+async fn failed_copy_reports_created_tasks_and_can_be_rolled_back() {
+    let mock_server = MockServer::start().await;
+    mount_source(&mock_server).await;
+    Mock::given(method("POST"))
+        .and(path("/checklists/2/tasks.json"))
+        .respond_with(create_task(Some(2)))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/checklists/2/tasks/100.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let failed = client.copy_subtree(1, 10, &destination()).unwrap_err();
+
+    assert_eq!(failed.created.id_map, vec![(10, 100), (12, 101)]);
+    client.rollback_copy(&failed).unwrap();
+}
+
+#[tokio::test]
+async fn copy_of_missing_task_errors() {
+    let mock_server = MockServer::start().await;
+    mount_source(&mock_server).await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let failed = client.copy_subtree(1, 99, &destination()).unwrap_err();
+
+    assert!(matches!(failed.error, CheckvistError::InvalidTaskError));
+    assert!(failed.created.id_map.is_empty());
+}
+
+#[tokio::test]
+async fn move_subtree_deletes_original() {
+    let mock_server = MockServer::start().await;
+    mount_source(&mock_server).await;
+    Mock::given(method("POST"))
+        .and(path("/checklists/2/tasks.json"))
+        .respond_with(create_task(None))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/checklists/1/tasks/10.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let client = CheckvistClient::new(&mock_server.uri(), "token", Box::new(|_token| ()));
+
+    let moved = client.move_subtree(1, 10, &destination()).unwrap();
+
+    assert_eq!(moved.new_id(13), Some(102));
+}
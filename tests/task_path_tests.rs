@@ -0,0 +1,108 @@
+use cvapi::task_path::TaskPath;
+use cvapi::{CheckvistClient, CheckvistError, CheckvistLocation};
+use cvfake::FakeCheckvist;
+
+fn client(server: &FakeCheckvist) -> CheckvistClient {
+    CheckvistClient::new(&server.uri(), &server.token(), Box::new(|_token| ()))
+}
+
+fn path(path: &str) -> TaskPath {
+    TaskPath::parse(path).unwrap()
+}
+
+#[test]
+fn resolves_list_and_nested_tasks() {
+    let server = FakeCheckvist::start();
+    let work = server.add_list("Work");
+    let inbox = server.add_task(work, "Inbox", None);
+    let bugs = server.add_task(work, "Bugs", Some(inbox));
+    server.add_task(work, "Bugs", None);
+    let client = client(&server);
+
+    assert_eq!(
+        client.resolve_path(&path("work")).unwrap(),
+        CheckvistLocation {
+            list_id: work,
+            parent_task_id: None
+        }
+    );
+    assert_eq!(
+        client.resolve_path(&path("Work > inbox > Bugs")).unwrap(),
+        CheckvistLocation {
+            list_id: work,
+            parent_task_id: Some(bugs)
+        }
+    );
+}
+
+#[test]
+fn missing_segment_errors() {
+    let server = FakeCheckvist::start();
+    let work = server.add_list("Work");
+    server.add_task(work, "Inbox", None);
+    let client = client(&server);
+
+    let result = client.resolve_path(&path("Work > Inbox > Bugs"));
+
+    let Err(CheckvistError::InvalidPathError { message, .. }) = result else {
+        panic!("expected an invalid path error, got {:?}", result);
+    };
+    assert!(message.contains("'Bugs'"));
+    assert!(server.tasks(work).len() == 1);
+}
+
+#[test]
+fn ambiguous_segment_errors_unless_one_matches_exactly() {
+    let server = FakeCheckvist::start();
+    let work = server.add_list("Work");
+    server.add_task(work, "inbox", None);
+    let exact = server.add_task(work, "Inbox", None);
+    server.add_task(work, "Later", None);
+    server.add_task(work, "later", None);
+    let other_work = server.add_list("Work");
+    let client = client(&server);
+
+    assert!(matches!(
+        client.resolve_path(&path("work")),
+        Err(CheckvistError::AmbiguousPathError { count: 2, .. })
+    ));
+    // leave only one list named Work
+    server.forbid_list(other_work);
+    assert_eq!(
+        client
+            .resolve_path(&path("Work > Inbox"))
+            .unwrap()
+            .parent_task_id,
+        Some(exact)
+    );
+    assert!(matches!(
+        client.resolve_path(&path("Work > LATER")),
+        Err(CheckvistError::AmbiguousPathError { count: 2, .. })
+    ));
+}
+
+#[test]
+fn missing_segments_can_be_created() {
+    let server = FakeCheckvist::start();
+    let work = server.add_list("Work");
+    let inbox = server.add_task(work, "Inbox", None);
+    server.add_task(work, "Existing", Some(inbox));
+    let client = client(&server);
+
+    let location = client
+        .resolve_or_create_path(&path("Work > Inbox > Bugs > Urgent"))
+        .unwrap();
+
+    let bugs = server
+        .tasks(work)
+        .into_iter()
+        .find(|task| task.content == "Bugs")
+        .unwrap();
+    assert_eq!((bugs.parent_id, bugs.position), (inbox, 2));
+    let urgent = server.task(location.parent_task_id.unwrap()).unwrap();
+    assert_eq!(urgent.content, "Urgent");
+    assert_eq!(urgent.parent_id, bugs.id);
+
+    let new_list = client.resolve_or_create_path(&path("Home")).unwrap();
+    assert_eq!(server.list(new_list.list_id).unwrap().name, "Home");
+}